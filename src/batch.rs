@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+use crate::config::ExecutionEnvironment;
+use crate::permission::PermissionMode;
+
+/// A single task described by a line of a `--from-file` JSONL file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct BatchEntry {
+    pub prompt: String,
+    #[serde(default)]
+    pub task_id: Option<String>,
+    #[serde(default)]
+    pub permission_mode: Option<PermissionMode>,
+    #[serde(default)]
+    pub approval_tool_permission: Option<String>,
+    #[serde(default)]
+    pub mcp_config: Option<String>,
+    #[serde(default)]
+    pub execution_env: Option<ExecutionEnvironment>,
+    #[serde(default)]
+    pub workspace_dir: Option<String>,
+}
+
+/// Parse a JSONL batch file. Blank lines and lines starting with `#` are ignored.
+pub fn parse_batch(contents: &str) -> Result<Vec<BatchEntry>> {
+    let mut entries = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry: BatchEntry = serde_json::from_str(line)
+            .with_context(|| format!("Invalid task on line {}", index + 1))?;
+        if entry.prompt.trim().is_empty() {
+            anyhow::bail!("Task on line {} has an empty prompt", index + 1);
+        }
+        entries.push(entry);
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    for task_id in entries.iter().filter_map(|e| e.task_id.as_deref()) {
+        if !seen.insert(task_id) {
+            anyhow::bail!("Task ID '{}' is used more than once", task_id);
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn load_batch_file(path: &Path) -> Result<Vec<BatchEntry>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read task file: {}", path.display()))?;
+    let entries = parse_batch(&contents)
+        .with_context(|| format!("Failed to parse task file: {}", path.display()))?;
+
+    if entries.is_empty() {
+        anyhow::bail!("Task file {} contains no tasks", path.display());
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch() {
+        let contents = r#"
+# services
+{"prompt": "Fix the login bug", "taskId": "login-fix"}
+
+{"prompt": "Add tests", "permissionMode": "plan", "executionEnv": "kubernetes"}
+"#;
+
+        let entries = parse_batch(contents).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].task_id.as_deref(), Some("login-fix"));
+        assert_eq!(entries[1].permission_mode, Some(PermissionMode::Plan));
+        assert_eq!(
+            entries[1].execution_env,
+            Some(ExecutionEnvironment::Kubernetes)
+        );
+    }
+
+    #[test]
+    fn test_parse_batch_errors() {
+        let err = parse_batch("{\"prompt\": \"ok\"}\n{\"promt\": \"typo\"}").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        let err = parse_batch("{\"prompt\": \"  \"}").unwrap_err();
+        assert!(err.to_string().contains("empty prompt"));
+
+        let err = parse_batch(
+            "{\"prompt\": \"a\", \"taskId\": \"dup\"}\n{\"prompt\": \"b\", \"taskId\": \"dup\"}",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("more than once"));
+    }
+}
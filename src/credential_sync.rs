@@ -1,11 +1,16 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::time::sleep;
 
+/// Refresh credentials this long before they expire, so a task doesn't start with a
+/// token that runs out a few minutes in
+pub const EXPIRY_REFRESH_MARGIN_SECS: u64 = 300;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SyncLock {
     pub task_id: String,
@@ -28,6 +33,14 @@ pub struct LastValidated {
     pub credential_hash: String,
 }
 
+/// Credentials last pushed to a cluster secret
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushedSecret {
+    pub pushed_at: u64,
+    pub credential_hash: String,
+    pub pushed_by: String,
+}
+
 pub struct CredentialSyncManager {
     metadata_dir: String,
     task_id: String,
@@ -288,6 +301,54 @@ impl CredentialSyncManager {
             .with_context(|| format!("Failed to write last_validated metadata to {path}"))
     }
 
+    /// Whether `credentials` differ from what was last pushed to `target` (e.g. a secret
+    /// identified by context and namespace). Targets never pushed to need an update.
+    pub fn secret_needs_update(&self, target: &str, credentials: &str) -> Result<bool> {
+        let hash = self.calculate_hash(credentials);
+        let pushed = self.read_pushed_secrets()?;
+        Ok(pushed
+            .get(target)
+            .is_none_or(|secret| secret.credential_hash != hash))
+    }
+
+    /// Record that `credentials` were pushed to `target`
+    pub fn record_secret_pushed(&self, target: &str, credentials: &str) -> Result<()> {
+        let mut pushed = self.read_pushed_secrets()?;
+        pushed.insert(
+            target.to_string(),
+            PushedSecret {
+                pushed_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+                credential_hash: self.calculate_hash(credentials),
+                pushed_by: self.task_id.clone(),
+            },
+        );
+
+        let path = format!("{}/pushed_secrets", self.metadata_dir);
+        let json = serde_json::to_string_pretty(&pushed)?;
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write pushed_secrets metadata to {path}"))
+    }
+
+    fn read_pushed_secrets(&self) -> Result<BTreeMap<String, PushedSecret>> {
+        let path = format!("{}/pushed_secrets", self.metadata_dir);
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                serde_json::from_str(&content).context("Failed to parse pushed_secrets metadata")
+            }
+            Err(_) => Ok(BTreeMap::new()),
+        }
+    }
+
+    /// Forget the last validation so the next `sync_credentials_if_needed` syncs again
+    pub fn clear_validation(&self) -> Result<()> {
+        let path = format!("{}/last_validated", self.metadata_dir);
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e).with_context(|| format!("Failed to remove {path}")),
+        }
+    }
+
     pub fn is_credential_error(error_message: &str) -> bool {
         // Common credential error patterns
         error_message.contains("unauthorized")
@@ -298,11 +359,103 @@ impl CredentialSyncManager {
     }
 }
 
+/// When the OAuth token in a `.credentials.json` expires, in milliseconds since the Unix
+/// epoch. Returns `None` if the file has no `expiresAt`.
+pub fn credentials_expires_at(credentials_json: &str) -> Option<u64> {
+    let value: serde_json::Value = serde_json::from_str(credentials_json).ok()?;
+    value
+        .get("claudeAiOauth")
+        .and_then(|oauth| oauth.get("expiresAt"))
+        .or_else(|| value.get("expiresAt"))
+        .and_then(|expires_at| expires_at.as_u64())
+}
+
+/// Whether the credentials have expired or will within `margin` of `now_ms`.
+/// Credentials without a known expiry are assumed to be fine.
+pub fn credentials_need_refresh(credentials_json: &str, now_ms: u64, margin: Duration) -> bool {
+    credentials_expires_at(credentials_json)
+        .is_some_and(|expires_at| expires_at <= now_ms.saturating_add(margin.as_millis() as u64))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_credentials_expiry() {
+        let credentials = r#"{"claudeAiOauth":{"accessToken":"sk-ant-oat01-x","refreshToken":"sk-ant-ort01-y","expiresAt":1748276587173,"scopes":["user:inference"]}}"#;
+        assert_eq!(credentials_expires_at(credentials), Some(1748276587173));
+        assert_eq!(credentials_expires_at(r#"{"expiresAt":42}"#), Some(42));
+        assert_eq!(credentials_expires_at(r#"{"claudeAiOauth":{}}"#), None);
+        assert_eq!(credentials_expires_at("not json"), None);
+
+        let margin = Duration::from_secs(EXPIRY_REFRESH_MARGIN_SECS);
+        let expires_at = 1748276587173;
+        // Expired
+        assert!(credentials_need_refresh(
+            credentials,
+            expires_at + 1,
+            margin
+        ));
+        // Expires within the margin
+        assert!(credentials_need_refresh(
+            credentials,
+            expires_at - 60_000,
+            margin
+        ));
+        // Plenty of time left
+        assert!(!credentials_need_refresh(
+            credentials,
+            expires_at - 3_600_000,
+            margin
+        ));
+        // Unknown expiry
+        assert!(!credentials_need_refresh("{}", expires_at, margin));
+    }
+
+    #[test]
+    fn test_pushed_secret_tracking() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().to_str().unwrap();
+        let manager = CredentialSyncManager::new(path, "test-task-123").unwrap();
+        let target = "kind-dev/claude-task";
+
+        // Never pushed
+        assert!(manager.secret_needs_update(target, "creds-v1").unwrap());
+
+        manager.record_secret_pushed(target, "creds-v1").unwrap();
+        assert!(!manager.secret_needs_update(target, "creds-v1").unwrap());
+        // Local credentials were refreshed
+        assert!(manager.secret_needs_update(target, "creds-v2").unwrap());
+        // Each namespace is tracked separately
+        assert!(manager
+            .secret_needs_update("kind-dev/other", "creds-v1")
+            .unwrap());
+
+        // Persisted across managers
+        let manager = CredentialSyncManager::new(path, "test-task-456").unwrap();
+        assert!(!manager.secret_needs_update(target, "creds-v1").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_clear_validation_forces_sync() {
+        let temp_dir = TempDir::new().unwrap();
+        let manager =
+            CredentialSyncManager::new(temp_dir.path().to_str().unwrap(), "test-task-123").unwrap();
+
+        manager.update_validation_timestamp().unwrap();
+        manager.clear_validation().unwrap();
+        // Clearing twice is fine
+        manager.clear_validation().unwrap();
+
+        let synced = manager
+            .sync_credentials_if_needed(|| async { Ok("test-credentials".to_string()) }, false)
+            .await
+            .unwrap();
+        assert!(synced);
+    }
+
     #[tokio::test]
     async fn test_credential_sync_manager_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -4,6 +4,7 @@ use keyring::Entry;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::time::Duration;
 
 #[cfg(target_os = "macos")]
 use security_framework::passwords::get_generic_password;
@@ -13,18 +14,32 @@ trait CredentialAccess {
     async fn extract_credentials(&self) -> Result<String>;
 }
 
+/// How long to wait for the keychain before giving up, unless configured otherwise
+pub const DEFAULT_KEYCHAIN_TIMEOUT_SECONDS: u64 = 30;
+
 /// macOS-specific credential access using Security framework
 #[cfg(target_os = "macos")]
 struct MacOSCredentialAccess {
     service_name: String,
     account_name: String,
+    use_biometrics: bool,
+    timeout: Duration,
+}
+
+/// Windows credential access using the Credential Manager
+#[cfg(windows)]
+struct WindowsCredentialAccess {
+    service_name: String,
+    account_name: String,
+    timeout: Duration,
 }
 
 /// Generic credential access for other platforms
-#[cfg(not(target_os = "macos"))]
+#[cfg(not(any(target_os = "macos", windows)))]
 struct GenericCredentialAccess {
     service_name: String,
     account_name: String,
+    timeout: Duration,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -77,28 +92,71 @@ fn get_current_username() -> Result<String> {
         .context("Could not determine current username")
 }
 
-pub async fn extract_keychain_credentials() -> Result<String> {
+pub async fn extract_keychain_credentials(
+    claude_user_config: &crate::config::ClaudeUserConfig,
+) -> Result<String> {
     let username = get_current_username()?;
+    let timeout = Duration::from_secs(
+        claude_user_config
+            .keychain_timeout_seconds
+            .unwrap_or(DEFAULT_KEYCHAIN_TIMEOUT_SECONDS),
+    );
 
     #[cfg(target_os = "macos")]
     {
         let access = MacOSCredentialAccess {
             service_name: "Claude Code-credentials".to_string(),
             account_name: username,
+            use_biometrics: claude_user_config.use_biometrics,
+            timeout,
+        };
+        access.extract_credentials().await
+    }
+
+    #[cfg(windows)]
+    {
+        let access = WindowsCredentialAccess {
+            service_name: "Claude Code-credentials".to_string(),
+            account_name: username,
+            timeout,
         };
         access.extract_credentials().await
     }
 
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(not(any(target_os = "macos", windows)))]
     {
         let access = GenericCredentialAccess {
             service_name: "Claude Code-credentials".to_string(),
             account_name: username,
+            timeout,
         };
         access.extract_credentials().await
     }
 }
 
+/// Run a blocking keychain lookup, failing if it doesn't return within `timeout`.
+/// A locked keychain can leave the lookup waiting on a prompt forever, so it runs on
+/// a detached thread that is simply abandoned on timeout.
+async fn read_keychain_with_timeout<F>(timeout: Duration, read: F) -> Result<String>
+where
+    F: FnOnce() -> Result<String> + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = tx.send(read());
+    });
+
+    match tokio::time::timeout(timeout, rx).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err(anyhow::anyhow!("Keychain lookup stopped unexpectedly")),
+        Err(_) => Err(anyhow::anyhow!(
+            "Timed out after {}s waiting for the keychain. Unlock it or answer the access prompt, \
+             or raise claudeUserConfig.keychainTimeoutSeconds",
+            timeout.as_secs()
+        )),
+    }
+}
+
 #[cfg(target_os = "macos")]
 async fn request_biometric_authentication() -> Result<()> {
     use localauthentication_rs::{LAPolicy, LocalAuthentication};
@@ -135,7 +193,7 @@ async fn request_biometric_authentication() -> Result<()> {
 }
 
 pub fn read_and_filter_claude_config(config_path: &str) -> Result<ClaudeConfig> {
-    let expanded_path = crate::config::Config::expand_tilde(config_path);
+    let expanded_path = crate::config::Config::expand_tilde(config_path)?;
 
     let content = fs::read_to_string(&expanded_path)
         .with_context(|| format!("Failed to read {}", expanded_path.display()))?;
@@ -172,12 +230,9 @@ pub async fn setup_credentials_and_config_with_cache(
     println!("Setting up Claude configuration...");
 
     // Expand home directory if needed
-    let base_dir = if task_base_home_dir.starts_with('~') {
-        let home_dir = std::env::var("HOME").context("Could not find HOME directory")?;
-        task_base_home_dir.replacen('~', &home_dir, 1)
-    } else {
-        task_base_home_dir.to_string()
-    };
+    let base_dir = crate::config::Config::expand_tilde(task_base_home_dir)?
+        .to_string_lossy()
+        .to_string();
 
     // Create output directories
     let claude_dir = format!("{base_dir}/.claude");
@@ -186,7 +241,7 @@ pub async fn setup_credentials_and_config_with_cache(
 
     // Extract keychain credentials with biometric authentication
     println!("Extracting keychain credentials...");
-    let credentials = extract_keychain_credentials()
+    let credentials = extract_keychain_credentials(claude_user_config)
         .await
         .context("Failed to extract keychain credentials")?;
 
@@ -214,7 +269,7 @@ pub async fn setup_credentials_and_config_with_cache(
 
     // Copy the user's CLAUDE.md if it exists, otherwise use default
     let user_memory_path =
-        crate::config::Config::expand_tilde(&claude_user_config.user_memory_path);
+        crate::config::Config::expand_tilde(&claude_user_config.user_memory_path)?;
     let claude_md_path = format!("{claude_dir}/CLAUDE.md");
 
     if user_memory_path.exists() {
@@ -374,40 +429,163 @@ async fn inspect_docker_volume_contents() -> Result<()> {
 #[cfg(target_os = "macos")]
 impl CredentialAccess for MacOSCredentialAccess {
     async fn extract_credentials(&self) -> Result<String> {
-        // First request biometric authentication
-        if let Err(e) = request_biometric_authentication().await {
-            println!("⚠️  Biometric authentication failed: {e}");
-            println!("   Falling back to keychain access without biometrics");
+        // First request biometric authentication, unless turned off
+        if self.use_biometrics {
+            if let Err(e) = request_biometric_authentication().await {
+                println!("⚠️  Biometric authentication failed: {e}");
+                println!("   Falling back to keychain access without biometrics");
+            }
         }
 
-        // Use Security framework for native macOS keychain access
-        match get_generic_password(&self.service_name, &self.account_name) {
-            Ok(password_data) => {
-                let password = String::from_utf8(password_data)
-                    .context("Failed to convert password data to string")?;
-                Ok(password)
+        let service_name = self.service_name.clone();
+        let account_name = self.account_name.clone();
+        read_keychain_with_timeout(self.timeout, move || {
+            // Use Security framework for native macOS keychain access
+            match get_generic_password(&service_name, &account_name) {
+                Ok(password_data) => {
+                    let password = String::from_utf8(password_data)
+                        .context("Failed to convert password data to string")?;
+                    Ok(password)
+                }
+                Err(e) => {
+                    // Fall back to keyring crate for compatibility
+                    println!("⚠️  Security framework access failed: {e}");
+                    println!("   Falling back to keyring crate");
+                    let entry = Entry::new(&service_name, &account_name)
+                        .context("Failed to create keychain entry")?;
+                    entry
+                        .get_password()
+                        .context("Failed to retrieve password from keychain")
+                }
             }
+        })
+        .await
+    }
+}
+
+#[cfg(windows)]
+impl CredentialAccess for WindowsCredentialAccess {
+    async fn extract_credentials(&self) -> Result<String> {
+        // keyring reads generic credentials from the Windows Credential Manager
+        let service_name = self.service_name.clone();
+        let account_name = self.account_name.clone();
+        let credential_manager_result = read_keychain_with_timeout(self.timeout, move || {
+            let entry = Entry::new(&service_name, &account_name)
+                .context("Failed to create Credential Manager entry")?;
+            entry
+                .get_password()
+                .context("Failed to retrieve password from Credential Manager")
+        })
+        .await;
+
+        match credential_manager_result {
+            Ok(credentials) => Ok(credentials),
             Err(e) => {
-                // Fall back to keyring crate for compatibility
-                println!("⚠️  Security framework access failed: {e}");
-                println!("   Falling back to keyring crate");
-                let entry = Entry::new(&self.service_name, &self.account_name)
-                    .context("Failed to create keychain entry")?;
-                entry
-                    .get_password()
-                    .context("Failed to retrieve password from keychain")
+                let path = claude_config_dir(
+                    std::env::var("CLAUDE_CONFIG_DIR").ok(),
+                    std::env::var("USERPROFILE").ok(),
+                )
+                .context("Could not find the Claude config directory")?
+                .join(".credentials.json");
+                println!("⚠️  Credential Manager access failed: {e}");
+                println!("   Falling back to {}", path.display());
+                read_credentials_file(&path)
             }
         }
     }
 }
 
-#[cfg(not(target_os = "macos"))]
+#[cfg(not(any(target_os = "macos", windows)))]
 impl CredentialAccess for GenericCredentialAccess {
     async fn extract_credentials(&self) -> Result<String> {
-        let entry = Entry::new(&self.service_name, &self.account_name)
-            .context("Failed to create keychain entry")?;
-        entry
-            .get_password()
-            .context("Failed to retrieve password from keychain")
+        let service_name = self.service_name.clone();
+        let account_name = self.account_name.clone();
+        let keyring_result = read_keychain_with_timeout(self.timeout, move || {
+            let entry = Entry::new(&service_name, &account_name)
+                .context("Failed to create keychain entry")?;
+            entry
+                .get_password()
+                .context("Failed to retrieve password from keychain")
+        })
+        .await;
+
+        // Claude Code on Linux usually keeps its credentials in a file, not the Secret Service
+        #[cfg(target_os = "linux")]
+        {
+            if let Err(e) = keyring_result {
+                let path = claude_config_dir(
+                    std::env::var("CLAUDE_CONFIG_DIR").ok(),
+                    std::env::var("HOME").ok(),
+                )
+                .context("Could not find the Claude config directory")?
+                .join(".credentials.json");
+                println!("⚠️  Keyring access failed: {e}");
+                println!("   Falling back to {}", path.display());
+                return read_credentials_file(&path);
+            }
+        }
+
+        keyring_result
+    }
+}
+
+/// Claude Code's config directory: `$CLAUDE_CONFIG_DIR` if set, otherwise `.claude` in
+/// the home directory
+#[cfg(any(target_os = "linux", windows))]
+fn claude_config_dir(
+    config_dir_env: Option<String>,
+    home: Option<String>,
+) -> Option<std::path::PathBuf> {
+    config_dir_env
+        .filter(|dir| !dir.is_empty())
+        .map(std::path::PathBuf::from)
+        .or_else(|| home.map(|home| std::path::Path::new(&home).join(".claude")))
+}
+
+/// Read a `.credentials.json` written by Claude Code, checking that it's valid JSON
+#[cfg(any(target_os = "linux", windows))]
+fn read_credentials_file(path: &std::path::Path) -> Result<String> {
+    let credentials = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read credentials file {}", path.display()))?;
+    serde_json::from_str::<serde_json::Value>(&credentials)
+        .with_context(|| format!("Credentials file {} is not valid JSON", path.display()))?;
+    Ok(credentials)
+}
+
+#[cfg(all(test, any(target_os = "linux", windows)))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_claude_config_dir() {
+        assert_eq!(
+            claude_config_dir(
+                Some("/etc/claude".to_string()),
+                Some("/home/dev".to_string())
+            ),
+            Some(std::path::PathBuf::from("/etc/claude"))
+        );
+        assert_eq!(
+            claude_config_dir(Some(String::new()), Some("/home/dev".to_string())),
+            Some(std::path::PathBuf::from("/home/dev/.claude"))
+        );
+        assert_eq!(claude_config_dir(None, None), None);
+    }
+
+    #[test]
+    fn test_read_credentials_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(".credentials.json");
+
+        assert!(read_credentials_file(&path).is_err());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert!(read_credentials_file(&path).is_err());
+
+        let credentials =
+            r#"{"claudeAiOauth":{"accessToken":"sk-ant-oat01-x","expiresAt":1748276587173}}"#;
+        std::fs::write(&path, credentials).unwrap();
+        assert_eq!(read_credentials_file(&path).unwrap(), credentials);
     }
 }
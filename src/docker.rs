@@ -1,19 +1,111 @@
 use anyhow::{Context, Result};
 use bollard::{
     container::{
-        Config, CreateContainerOptions, LogOutput, LogsOptions, RemoveContainerOptions,
-        StartContainerOptions, WaitContainerOptions,
+        Config, CreateContainerOptions, ListContainersOptions, LogOutput, LogsOptions,
+        RemoveContainerOptions, StartContainerOptions, StopContainerOptions, WaitContainerOptions,
     },
-    image::BuildImageOptions,
+    exec::{CreateExecOptions, ResizeExecOptions, StartExecOptions, StartExecResults},
+    image::{BuildImageOptions, TagImageOptions},
     models::{BuildInfo, HostConfig, Mount, MountTypeEnum, RestartPolicy, RestartPolicyNameEnum},
+    network::InspectNetworkOptions,
     volume::{CreateVolumeOptions, ListVolumesOptions},
     Docker,
 };
 use futures_util::stream::StreamExt;
-use std::collections::HashMap;
-use std::path::Path;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use crate::config::DockerConfig;
+use crate::permission::PermissionMode;
+
+/// Split an image reference into its repository and tag, e.g.
+/// `ghcr.io/org/image:latest` -> (`ghcr.io/org/image`, Some(`latest`)).
+/// A colon before the last `/` is a registry port, not a tag.
+pub fn split_image_tag(image: &str) -> (&str, Option<&str>) {
+    let name_start = image.rfind('/').map_or(0, |i| i + 1);
+    match image[name_start..].rfind(':') {
+        Some(i) => (&image[..name_start + i], Some(&image[name_start + i + 1..])),
+        None => (image, None),
+    }
+}
+
+/// The image reference pinned to this version of claude-task: `<repo>:<version>` for
+/// untagged and `latest` images, `<repo>:<tag>-<version>` otherwise, so differently
+/// tagged images get their own pins. `None` for digest references and images already
+/// tagged with the version.
+pub fn versioned_image_name(image: &str) -> Option<String> {
+    if image.contains('@') {
+        return None;
+    }
+    let version = env!("CARGO_PKG_VERSION");
+    match split_image_tag(image) {
+        (_, Some(tag)) if tag == version || tag.ends_with(&format!("-{version}")) => None,
+        (repo, None | Some("latest")) => Some(format!("{repo}:{version}")),
+        (repo, Some(tag)) => Some(format!("{repo}:{tag}-{version}")),
+    }
+}
+
+/// Label on images built by claude-task, holding the configured image reference
+pub const SOURCE_IMAGE_LABEL: &str = "dev.claude-task.source-image";
+
+/// What matters about a local image when picking which one to run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalImage {
+    pub id: Option<String>,
+    /// The [`SOURCE_IMAGE_LABEL`] value, for images built by claude-task
+    pub source_image: Option<String>,
+}
+
+/// Pick between the configured image and its version pin. The pin is only used when it
+/// was built from `configured`, and not when the configured tag now points at a
+/// different image that claude-task didn't build (e.g. one that was just pulled).
+pub fn choose_image(
+    configured: &str,
+    configured_local: Option<&LocalImage>,
+    pin: Option<(&str, &LocalImage)>,
+) -> Option<String> {
+    let pin = pin.filter(|(_, pin)| pin.source_image.as_deref() == Some(configured));
+    match (pin, configured_local) {
+        (Some((pin, _)), None) => Some(pin.to_string()),
+        (Some((pin, pin_image)), Some(local))
+            if local.id == pin_image.id || local.source_image.is_some() =>
+        {
+            Some(pin.to_string())
+        }
+        (_, Some(_)) => Some(configured.to_string()),
+        (None, None) => None,
+    }
+}
+
+/// The host's CPU architecture in Docker's naming, e.g. `amd64` or `arm64`
+pub fn host_docker_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        "x86" => "386",
+        arch => arch,
+    }
+}
+
+/// A warning when images for `platform` (e.g. `linux/amd64`) can't run natively on this host
+pub fn platform_mismatch_warning(platform: &str) -> Option<String> {
+    let arch = platform.split('/').nth(1)?;
+    let host = host_docker_arch();
+    (arch != host).then(|| {
+        format!(
+            "Building for {platform} on a {host} host; the build runs under emulation and may be slow"
+        )
+    })
+}
+
+/// Whether a container network mode names a user-defined network rather than
+/// one of Docker's built-in modes
+pub fn is_user_defined_network(network_mode: &str) -> bool {
+    !matches!(network_mode, "bridge" | "host" | "none" | "default")
+        && !network_mode.starts_with("container:")
+}
 
 pub struct DockerManager {
     docker: Docker,
@@ -24,6 +116,10 @@ pub struct DockerManager {
 pub enum TaskRunResult {
     Sync {
         output: String,
+        /// Time spent creating and starting the container
+        container_start: Duration,
+        /// Claude's parsed result, when it ran with JSON output and the output parsed
+        claude_result: Option<ClaudeResult>,
     },
     Async {
         task_id: String,
@@ -31,6 +127,136 @@ pub enum TaskRunResult {
     },
 }
 
+/// Claude's final result object from `--output-format json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ClaudeResult {
+    /// The assistant's final message
+    pub message: String,
+    pub is_error: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_turns: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_cost_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub usage: ClaudeUsage,
+    /// Tool calls by tool name; only known when the output includes the conversation's messages
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub tool_uses: BTreeMap<String, u64>,
+    /// Names of tools whose use was denied
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub permission_denials: Vec<String>,
+}
+
+/// Token counts from Claude's result object
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ClaudeUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub cache_read_input_tokens: u64,
+}
+
+/// Parse Claude's `--output-format json` output: a single result object, or (with
+/// `--verbose`) an array or stream of messages ending in one. `None` if there is no result.
+pub fn parse_claude_json(output: &str) -> Option<ClaudeResult> {
+    use serde_json::Value;
+
+    let values = match serde_json::from_str::<Value>(output.trim()) {
+        Ok(Value::Array(values)) => values,
+        Ok(value) => vec![value],
+        // Anything printed around the JSON, or one message per line
+        Err(_) => output
+            .lines()
+            .filter_map(|line| serde_json::from_str(line.trim()).ok())
+            .collect(),
+    };
+    let result = values
+        .iter()
+        .rev()
+        .find(|value| value["type"] == "result")?;
+
+    let mut tool_uses = BTreeMap::new();
+    for value in values.iter().filter(|value| value["type"] == "assistant") {
+        let content = value["message"]["content"].as_array();
+        for block in content.into_iter().flatten() {
+            if block["type"] == "tool_use" {
+                if let Some(name) = block["name"].as_str() {
+                    *tool_uses.entry(name.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    let usage = &result["usage"];
+    let tokens = |field: &str| usage[field].as_u64().unwrap_or(0);
+    Some(ClaudeResult {
+        message: result["result"].as_str().unwrap_or_default().to_string(),
+        is_error: result["is_error"].as_bool().unwrap_or(false),
+        num_turns: result["num_turns"].as_u64(),
+        duration_ms: result["duration_ms"].as_u64(),
+        total_cost_usd: result["total_cost_usd"].as_f64(),
+        session_id: result["session_id"].as_str().map(str::to_string),
+        usage: ClaudeUsage {
+            input_tokens: tokens("input_tokens"),
+            output_tokens: tokens("output_tokens"),
+            cache_creation_input_tokens: tokens("cache_creation_input_tokens"),
+            cache_read_input_tokens: tokens("cache_read_input_tokens"),
+        },
+        tool_uses,
+        permission_denials: result["permission_denials"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|denial| denial["tool_name"].as_str().map(str::to_string))
+            .collect(),
+    })
+}
+
+/// What already exists on the Docker daemon, looked up once at the start of a run
+#[derive(Debug, Clone, Default)]
+pub struct Preflight {
+    pub home_volume_exists: bool,
+    pub npm_cache_volume_exists: bool,
+    pub node_cache_volume_exists: bool,
+    /// Result of [`DockerManager::preferred_image`], if the image was checked
+    pub image: Option<String>,
+}
+
+/// A claude-task container as shown by `ps`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskContainer {
+    pub task_id: String,
+    pub name: String,
+    /// Container state, e.g. "running" or "exited"
+    pub state: String,
+    /// Docker's human readable status, e.g. "Up 5 minutes"
+    pub status: String,
+    /// Seconds since the container was created, while it is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_seconds: Option<u64>,
+}
+
+/// Result of stopping a task's container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopOutcome {
+    Stopped,
+    NotRunning,
+    NotFound,
+}
+
+/// A claude-task Docker volume as shown by `docker list`
+#[derive(Debug, Clone)]
+pub struct VolumeInfo {
+    pub name: String,
+    /// Human readable size as reported by `du -sh`, or "unknown"
+    pub size: String,
+    pub labels: Vec<(String, String)>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClaudeTaskConfig {
     pub task_id: String,
@@ -40,17 +266,149 @@ pub struct ClaudeTaskConfig {
     pub context_path: String,
     pub ht_mcp_port: Option<u16>,
     pub web_view_proxy_port: Option<u16>,
+    /// Rebuild every layer instead of reusing the build cache
+    pub no_cache: bool,
+    /// Pull a newer version of the base image even if one is cached locally
+    pub pull: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct RunTaskOptions {
     pub prompt: String,
-    pub permission_prompt_tool: String,
+    pub permission_mode: PermissionMode,
+    pub permission_prompt_tool: Option<String>,
     pub debug: bool,
     pub mcp_config: Option<String>,
-    pub skip_permissions: bool,
     pub async_mode: bool,
     pub oauth_token: Option<String>,
+    /// File that receives a timestamped copy of the container output (sync runs only)
+    pub log_file: Option<PathBuf>,
+    /// Stop the container if a sync run takes longer than this
+    pub timeout_seconds: Option<u64>,
+    /// Host SSH agent socket to forward into the container (see [`ssh_agent_socket`])
+    pub ssh_agent_socket: Option<String>,
+    /// Run Claude with `--output-format json` and parse its result (sync runs only)
+    pub claude_json: bool,
+}
+
+/// Where the forwarded SSH agent socket appears inside the container
+const CONTAINER_SSH_AUTH_SOCK: &str = "/run/ssh-agent.sock";
+
+/// The host socket to bind-mount so git in the container can use the host's SSH agent
+pub fn ssh_agent_socket() -> Result<String> {
+    let socket = std::env::var("SSH_AUTH_SOCK").map_err(|_| {
+        anyhow::anyhow!(
+            "SSH_AUTH_SOCK is not set; start an agent with `eval $(ssh-agent)` and add a key with `ssh-add`"
+        )
+    })?;
+
+    if cfg!(target_os = "macos") {
+        // Docker Desktop can't bind-mount macOS sockets, but forwards the agent here
+        return Ok("/run/host-services/ssh-auth.sock".to_string());
+    }
+    if cfg!(windows) {
+        anyhow::bail!("SSH agent forwarding is only supported on macOS and Linux");
+    }
+    if !Path::new(&socket).exists() {
+        anyhow::bail!("SSH_AUTH_SOCK points to {socket}, which does not exist");
+    }
+    Ok(socket)
+}
+
+/// Pack the build context directory into an in-memory tar archive.
+/// Does blocking file I/O; call it from `spawn_blocking` in async code.
+fn create_tar_archive(context_path: &Path) -> Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(Vec::new());
+
+    // Add all files in the context directory
+    archive
+        .append_dir_all(".", context_path)
+        .context("Failed to create tar archive")?;
+
+    archive
+        .into_inner()
+        .context("Failed to finalize tar archive")
+}
+
+/// A sync task exceeded its timeout and its container was stopped
+#[derive(Debug)]
+pub struct TaskTimeoutError {
+    pub timeout: Duration,
+    /// Output Claude produced before the timeout
+    pub output: String,
+}
+
+impl std::fmt::Display for TaskTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Task timed out after {}s and its container was stopped",
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for TaskTimeoutError {}
+
+/// A sync task's container exited with a non-zero status
+#[derive(Debug)]
+pub struct ContainerExitError {
+    pub status_code: i64,
+}
+
+impl std::fmt::Display for ContainerExitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Container exited with non-zero status: {}",
+            self.status_code
+        )
+    }
+}
+
+impl std::error::Error for ContainerExitError {}
+
+/// Timestamped copy of a task's container output, flushed when dropped
+struct TaskLog {
+    writer: std::io::BufWriter<std::fs::File>,
+}
+
+impl TaskLog {
+    fn create(path: &Path, task_id: &str) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create log directory: {}", parent.display()))?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+
+        let mut log = Self {
+            writer: std::io::BufWriter::new(file),
+        };
+        log.write("task", &format!("Starting task {task_id}\n"));
+        Ok(log)
+    }
+
+    /// Write `text` with each line prefixed by a timestamp and the stream it came from
+    fn write(&mut self, stream: &str, text: &str) {
+        use std::io::Write;
+
+        let timestamp = chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z");
+        for line in text.lines() {
+            // A failing log file shouldn't abort the task
+            let _ = writeln!(self.writer, "[{timestamp}] [{stream}] {line}");
+        }
+    }
+}
+
+impl Drop for TaskLog {
+    fn drop(&mut self) {
+        use std::io::Write;
+        let _ = self.writer.flush();
+    }
 }
 
 impl Default for ClaudeTaskConfig {
@@ -61,12 +419,146 @@ impl Default for ClaudeTaskConfig {
                 .unwrap_or_default()
                 .to_string_lossy()
                 .to_string(),
-            timezone: "America/New_York".to_string(),
+            timezone: host_timezone(),
             dockerfile_path: "Dockerfile".to_string(),
             context_path: "claude-task".to_string(),
             ht_mcp_port: None,
             web_view_proxy_port: None,
+            no_cache: false,
+            pull: false,
+        }
+    }
+}
+
+/// The host's IANA timezone name, e.g. `Europe/Berlin`, or UTC if it can't be determined
+pub fn host_timezone() -> String {
+    resolve_timezone(
+        std::env::var("TZ").ok().as_deref(),
+        std::fs::read_link("/etc/localtime").ok().as_deref(),
+        std::fs::read_to_string("/etc/timezone").ok().as_deref(),
+    )
+}
+
+/// Pick a timezone from, in order: the `TZ` variable, the target of the `/etc/localtime`
+/// symlink and the contents of `/etc/timezone`, falling back to UTC
+fn resolve_timezone(
+    tz_env: Option<&str>,
+    localtime_target: Option<&Path>,
+    etc_timezone: Option<&str>,
+) -> String {
+    // TZ may be written as ":Area/City"
+    if let Some(tz) = tz_env
+        .map(|tz| tz.trim().trim_start_matches(':'))
+        .filter(|tz| !tz.is_empty() && !tz.starts_with('/'))
+    {
+        return tz.to_string();
+    }
+
+    // e.g. /usr/share/zoneinfo/Europe/Berlin or /var/db/timezone/zoneinfo/Europe/Berlin
+    if let Some(target) = localtime_target.and_then(|target| target.to_str()) {
+        if let Some((_, name)) = target.rsplit_once("zoneinfo/") {
+            if !name.is_empty() {
+                return name.to_string();
+            }
+        }
+    }
+
+    if let Some(name) = etc_timezone.map(str::trim).filter(|name| !name.is_empty()) {
+        return name.to_string();
+    }
+
+    "UTC".to_string()
+}
+
+/// Merge `overrides` into a list of `KEY=value` strings, replacing entries with the same key.
+/// Overrides are appended in key order so the result is deterministic.
+fn merge_env_vars(defaults: Vec<String>, overrides: &HashMap<String, String>) -> Vec<String> {
+    let mut env_vars: Vec<String> = defaults
+        .into_iter()
+        .filter(|var| {
+            let key = var.split_once('=').map_or(var.as_str(), |(key, _)| key);
+            !overrides.contains_key(key)
+        })
+        .collect();
+
+    let mut keys: Vec<&String> = overrides.keys().collect();
+    keys.sort();
+    env_vars.extend(
+        keys.into_iter()
+            .map(|key| format!("{key}={}", overrides[key])),
+    );
+
+    env_vars
+}
+
+/// Variables whose values are never printed in dry runs or debug output
+const SECRET_ENV_VARS: &[&str] = &[
+    "CLAUDE_CODE_OAUTH_TOKEN",
+    "GIT_TOKEN",
+    "GITHUB_TOKEN",
+    "REGISTRY_PASSWORD",
+];
+
+/// A `KEY=value` entry for display, with the value hidden for known secrets and for
+/// anything set through `environmentVariables`, which may hold API keys
+fn redact_env_var(var: &str, configured: &HashMap<String, String>) -> String {
+    match var.split_once('=') {
+        Some((key, _)) if SECRET_ENV_VARS.contains(&key) || configured.contains_key(key) => {
+            format!("{key}=<redacted>")
         }
+        _ => var.to_string(),
+    }
+}
+
+/// Puts the host terminal into raw mode for an interactive session and restores it when dropped
+struct RawTerminal {
+    saved_settings: String,
+}
+
+impl RawTerminal {
+    fn enable() -> Option<Self> {
+        use std::process::{Command, Stdio};
+
+        let output = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let saved_settings = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        Command::new("stty")
+            .args(["raw", "-echo"])
+            .stdin(Stdio::inherit())
+            .status()
+            .ok()
+            .filter(|status| status.success())?;
+
+        Some(Self { saved_settings })
+    }
+
+    /// Current terminal size as (rows, columns)
+    fn size() -> Option<(u16, u16)> {
+        use std::process::{Command, Stdio};
+
+        let output = Command::new("stty")
+            .arg("size")
+            .stdin(Stdio::inherit())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let size = String::from_utf8_lossy(&output.stdout);
+        let mut parts = size.split_whitespace().map(|part| part.parse().ok());
+        Some((parts.next()??, parts.next()??))
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = std::process::Command::new("stty")
+            .arg(&self.saved_settings)
+            .stdin(std::process::Stdio::inherit())
+            .status();
     }
 }
 
@@ -77,20 +569,53 @@ impl DockerManager {
         Ok(Self { docker, config })
     }
 
-    /// Create necessary volumes for Claude task
-    pub async fn create_volumes(&self, _config: &ClaudeTaskConfig) -> Result<()> {
+    /// Check the volumes and (with `check_image`) the task image concurrently,
+    /// so a run needs a single round of daemon requests before it starts
+    pub async fn preflight(&self, check_image: bool) -> Result<Preflight> {
+        let volumes = &self.config.volumes;
+        let image = async {
+            if check_image {
+                self.preferred_image().await
+            } else {
+                None
+            }
+        };
+        let (home, npm_cache, node_cache, image) = tokio::join!(
+            self.volume_exists(&volumes.home),
+            self.volume_exists(&volumes.npm_cache),
+            self.volume_exists(&volumes.node_cache),
+            image,
+        );
+
+        Ok(Preflight {
+            home_volume_exists: home?,
+            npm_cache_volume_exists: npm_cache?,
+            node_cache_volume_exists: node_cache?,
+            image,
+        })
+    }
+
+    /// Create the shared cache volumes that `preflight` didn't find
+    pub async fn create_volumes(&self, preflight: &Preflight) -> Result<()> {
         let volumes = vec![
             (
                 self.config.volumes.npm_cache.clone(),
                 "Shared npm cache volume".to_string(),
+                preflight.npm_cache_volume_exists,
             ),
             (
                 self.config.volumes.node_cache.clone(),
                 "Shared node cache volume".to_string(),
+                preflight.node_cache_volume_exists,
             ),
         ];
 
-        for (volume_name, description) in volumes {
+        for (volume_name, description, exists) in volumes {
+            if exists {
+                println!("✓ Volume '{volume_name}' already exists");
+                continue;
+            }
+
             let create_options = CreateVolumeOptions {
                 name: volume_name.clone(),
                 labels: {
@@ -128,6 +653,55 @@ impl DockerManager {
         }
     }
 
+    /// Check that a user-defined network exists
+    pub async fn check_network_exists(&self, network: &str) -> Result<()> {
+        self.docker
+            .inspect_network(network, None::<InspectNetworkOptions<String>>)
+            .await
+            .map(|_| ())
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Docker network '{}' not found: {}\nCreate it with: docker network create {}",
+                    network,
+                    e,
+                    network
+                )
+            })
+    }
+
+    /// The local image to run tasks with: the tag pinned to this version of
+    /// claude-task if it was built from the configured image (see [`choose_image`]),
+    /// otherwise the configured image
+    pub async fn preferred_image(&self) -> Option<String> {
+        let configured = &self.config.image_name;
+        let configured_local = self.local_image(configured).await;
+        let pin = match versioned_image_name(configured) {
+            Some(pin) => self.local_image(&pin).await.map(|image| (pin, image)),
+            None => None,
+        };
+        choose_image(
+            configured,
+            configured_local.as_ref(),
+            pin.as_ref().map(|(pin, image)| (pin.as_str(), image)),
+        )
+    }
+
+    async fn local_image(&self, image: &str) -> Option<LocalImage> {
+        let inspect = self.docker.inspect_image(image).await.ok()?;
+        Some(LocalImage {
+            id: inspect.id,
+            source_image: inspect
+                .config
+                .and_then(|config| config.labels)
+                .and_then(|mut labels| labels.remove(SOURCE_IMAGE_LABEL)),
+        })
+    }
+
+    /// Run task containers from `image` instead of the configured image
+    pub fn use_image(&mut self, image: &str) {
+        self.config.image_name = image.to_string();
+    }
+
     /// Build the Claude task image
     pub async fn build_image(&self, config: &ClaudeTaskConfig) -> Result<()> {
         println!("🔨 Building Claude task image...");
@@ -158,11 +732,23 @@ impl DockerManager {
                 args.insert("TZ".to_string(), config.timezone.clone());
                 args
             },
+            nocache: config.no_cache,
+            pull: config.pull,
+            platform: self.config.platform.clone().unwrap_or_default(),
+            labels: HashMap::from([(
+                SOURCE_IMAGE_LABEL.to_string(),
+                self.config.image_name.clone(),
+            )]),
             ..Default::default()
         };
 
-        // Create tar archive of build context
-        let tar_data = self.create_tar_archive(context_path)?;
+        // Create tar archive of build context. Walking and reading the context is
+        // blocking I/O, so keep it off the async executor. bollard 0.17 only takes
+        // the whole body as `Bytes`, so the archive is still buffered in memory.
+        let tar_context = context_path.to_path_buf();
+        let tar_data = tokio::task::spawn_blocking(move || create_tar_archive(&tar_context))
+            .await
+            .context("Build context archiving task panicked")??;
 
         // Build image
         let mut stream = self
@@ -187,6 +773,22 @@ impl DockerManager {
             }
         }
 
+        // Keep a version-pinned tag alongside the configured one for rollbacks
+        if let Some(versioned) = versioned_image_name(&self.config.image_name) {
+            let (repo, tag) = split_image_tag(&versioned);
+            self.docker
+                .tag_image(
+                    &self.config.image_name,
+                    Some(TagImageOptions {
+                        repo,
+                        tag: tag.unwrap_or_default(),
+                    }),
+                )
+                .await
+                .with_context(|| format!("Failed to tag image as {versioned}"))?;
+            println!("🏷️  Tagged image as {versioned}");
+        }
+
         println!("✓ Image built successfully");
         Ok(())
     }
@@ -198,11 +800,12 @@ impl DockerManager {
         options: &RunTaskOptions,
     ) -> Result<TaskRunResult> {
         println!("🚀 Starting Claude task container...");
+        let container_start_at = Instant::now();
 
         // Create container configuration
         let container_config = self.create_container_config(config, options).await?;
 
-        let container_name = format!("{}{}", self.config.container_name_prefix, config.task_id);
+        let container_name = self.container_name(&config.task_id);
 
         // Remove existing container if it exists
         let remove_options = RemoveContainerOptions {
@@ -217,7 +820,7 @@ impl DockerManager {
         // Create container with auto-remove
         let create_options = CreateContainerOptions {
             name: container_name.clone(),
-            ..Default::default()
+            platform: self.config.platform.clone(),
         };
 
         let container = self
@@ -235,13 +838,21 @@ impl DockerManager {
             .context("Failed to start container")?;
 
         println!("✓ Container started");
+        crate::reporter::emit(crate::reporter::RunEvent::ContainerStarted {
+            task_id: config.task_id.clone(),
+            container_id: Some(container.id.clone()),
+            job_name: None,
+            namespace: None,
+        });
+        let container_start = container_start_at.elapsed();
 
         if options.async_mode {
             // Return immediately for async mode
             println!("📋 Task started in background mode");
             println!("   Task ID: {}", config.task_id);
             println!("   Container ID: {}", container.id);
-            println!("   Monitor with: docker logs {container_name}");
+            println!("   Monitor with: claude-task logs -f {}", config.task_id);
+            println!("   Stop with: claude-task stop {}", config.task_id);
 
             Ok(TaskRunResult::Async {
                 task_id: config.task_id.clone(),
@@ -252,45 +863,157 @@ impl DockerManager {
             println!();
             println!("⏳ Waiting for Claude's response...");
 
+            let mut task_log = match options.log_file {
+                Some(ref path) => Some(TaskLog::create(path, &config.task_id)?),
+                None => None,
+            };
+
             // Stream logs and parse output for sync mode
-            let claude_output = self
-                .stream_and_parse_logs(&container.id, options.debug)
+            let mut claude_output = String::new();
+            let run = async {
+                self.stream_and_parse_logs(
+                    &container.id,
+                    options.debug,
+                    true,
+                    !options.claude_json,
+                    task_log.as_mut(),
+                    &mut claude_output,
+                )
                 .await?;
-
-            // Wait for container to finish
-            let wait_options = WaitContainerOptions {
-                condition: "not-running".to_string(),
+                self.wait_for_exit(&container.id, task_log.as_mut()).await
             };
 
-            let mut wait_stream = self
-                .docker
-                .wait_container(&container.id, Some(wait_options));
-            if let Some(result) = wait_stream.next().await {
-                match result {
-                    Ok(wait_result) => {
-                        if wait_result.status_code != 0 {
-                            return Err(anyhow::anyhow!(
-                                "Container exited with non-zero status: {}",
-                                wait_result.status_code
-                            ));
+            match options.timeout_seconds {
+                Some(seconds) => {
+                    let limit = Duration::from_secs(seconds);
+                    if tokio::time::timeout(limit, run).await.is_err() {
+                        println!();
+                        println!("⏰ Task timed out after {seconds}s, stopping container...");
+                        if let Some(ref mut log) = task_log {
+                            log.write("task", &format!("Timed out after {seconds}s"));
                         }
+                        // Give Claude a few seconds to exit cleanly before Docker kills it
+                        let stop_options = StopContainerOptions { t: 10 };
+                        if let Err(e) = self
+                            .docker
+                            .stop_container(&container.id, Some(stop_options))
+                            .await
+                        {
+                            println!("⚠️  Failed to stop container {}: {e}", container.id);
+                        }
+                        return Err(TaskTimeoutError {
+                            timeout: limit,
+                            output: claude_output,
+                        }
+                        .into());
                     }
-                    Err(e) => return Err(anyhow::anyhow!("Wait error: {}", e)),
                 }
+                None => run.await?,
             }
 
             // Container will auto-remove itself due to auto_remove: true
 
+            // The raw JSON wasn't echoed; show the message, or the raw output if it didn't parse
+            let claude_result = if options.claude_json {
+                let claude_result = parse_claude_json(&claude_output);
+                match claude_result {
+                    Some(ref result) => {
+                        print!("{}", result.message);
+                        crate::reporter::emit(crate::reporter::RunEvent::ClaudeResult {
+                            result: serde_json::to_value(result).unwrap_or_default(),
+                        });
+                    }
+                    None => {
+                        println!("⚠️  Couldn't parse Claude's JSON output; showing it as text");
+                        print!("{claude_output}");
+                        crate::reporter::emit(crate::reporter::RunEvent::ClaudeOutput {
+                            text: claude_output.clone(),
+                        });
+                    }
+                }
+                claude_result
+            } else {
+                None
+            };
+
             println!();
             println!("=============== 💬 CLAUDE'S RESPONSE END 💬 ===============");
             println!();
 
+            if let Some(ref path) = options.log_file {
+                println!("📝 Output saved to {}", path.display());
+            }
+
             Ok(TaskRunResult::Sync {
                 output: claude_output,
+                container_start,
+                claude_result,
             })
         }
     }
 
+    /// Print the container that `run_claude_task` would create, without creating it
+    pub async fn print_dry_run(
+        &self,
+        config: &ClaudeTaskConfig,
+        options: &RunTaskOptions,
+    ) -> Result<()> {
+        let container_config = self.create_container_config(config, options).await?;
+        let host_config = container_config.host_config.unwrap_or_default();
+
+        println!("📄 Container (not created):");
+        println!("   Name: {}", self.container_name(&config.task_id));
+        println!("   Image: {}", container_config.image.unwrap_or_default());
+        if let Some(entrypoint) = container_config.entrypoint {
+            println!("   Entrypoint: {}", entrypoint.join(" "));
+        }
+        println!(
+            "   Command: {}",
+            container_config.cmd.unwrap_or_default().join(" ")
+        );
+        println!("   Mounts:");
+        for mount in host_config.mounts.unwrap_or_default() {
+            println!(
+                "     {} -> {}{}",
+                mount.source.unwrap_or_default(),
+                mount.target.unwrap_or_default(),
+                if mount.read_only == Some(true) {
+                    " (read-only)"
+                } else {
+                    ""
+                }
+            );
+        }
+        println!("   Environment:");
+        for var in container_config.env.unwrap_or_default() {
+            println!(
+                "     {}",
+                redact_env_var(&var, &self.config.environment_variables)
+            );
+        }
+        if let Some(network) = host_config.network_mode {
+            println!("   Network: {network}");
+        }
+        if let Some(memory) = host_config.memory {
+            println!("   Memory limit: {memory} bytes");
+        }
+        if let Some(nano_cpus) = host_config.nano_cpus {
+            println!("   CPUs: {}", nano_cpus as f64 / 1e9);
+        }
+        if let Some(platform) = &self.config.platform {
+            println!("   Platform: {platform}");
+        }
+        println!(
+            "   Mode: {}",
+            if options.async_mode {
+                "background"
+            } else {
+                "foreground"
+            }
+        );
+        Ok(())
+    }
+
     async fn create_container_config(
         &self,
         config: &ClaudeTaskConfig,
@@ -337,6 +1060,27 @@ impl DockerManager {
             });
         }
 
+        if let Some(socket) = &options.ssh_agent_socket {
+            mounts.push(Mount {
+                target: Some(CONTAINER_SSH_AUTH_SOCK.to_string()),
+                source: Some(socket.clone()),
+                typ: Some(MountTypeEnum::BIND),
+                ..Default::default()
+            });
+        }
+
+        // User-specified bind mounts from docker.extraMounts and --mount
+        for extra in &self.config.extra_mounts {
+            let source = extra.resolved_source()?;
+            mounts.push(Mount {
+                target: Some(extra.target.clone()),
+                source: Some(source.to_string_lossy().to_string()),
+                typ: Some(MountTypeEnum::BIND),
+                read_only: Some(extra.read_only),
+                ..Default::default()
+            });
+        }
+
         // Add custom MCP config mount if provided
         if let Some(ref mcp_config_path) = options.mcp_config {
             let source_path = Path::new(mcp_config_path);
@@ -357,6 +1101,7 @@ impl DockerManager {
             "NODE_OPTIONS=--max-old-space-size=4096".to_string(),
             "CLAUDE_CONFIG_DIR=/home/node/.claude".to_string(),
             "POWERLEVEL9K_DISABLE_GITSTATUS=true".to_string(),
+            format!("TZ={}", config.timezone),
             format!(
                 "DEBUG_MODE={}",
                 if options.debug { "true" } else { "false" }
@@ -373,6 +1118,23 @@ impl DockerManager {
             env_vars.push(format!("CLAUDE_CODE_OAUTH_TOKEN={token}"));
         }
 
+        if options.ssh_agent_socket.is_some() {
+            env_vars.push(format!("SSH_AUTH_SOCK={CONTAINER_SSH_AUTH_SOCK}"));
+        }
+
+        // Configured variables override the built-in defaults
+        let env_vars = merge_env_vars(env_vars, &self.config.environment_variables);
+
+        if options.debug {
+            println!("🔍 Container environment:");
+            for var in &env_vars {
+                println!(
+                    "   {}",
+                    redact_env_var(var, &self.config.environment_variables)
+                );
+            }
+        }
+
         let mut host_config = HostConfig {
             mounts: Some(mounts),
             restart_policy: Some(RestartPolicy {
@@ -380,9 +1142,23 @@ impl DockerManager {
                 ..Default::default()
             }),
             auto_remove: Some(!options.async_mode), // Don't auto-remove in async mode
+            memory: self.config.resources.memory_bytes()?,
+            nano_cpus: self.config.resources.nano_cpus(),
+            network_mode: self.config.network_mode.clone(),
             ..Default::default()
         };
 
+        if let Some(network) = self
+            .config
+            .network_mode
+            .as_deref()
+            .filter(|mode| is_user_defined_network(mode))
+        {
+            self.check_network_exists(network).await?;
+            // Docker only maps host.docker.internal automatically on the default bridge
+            host_config.extra_hosts = Some(vec!["host.docker.internal:host-gateway".to_string()]);
+        }
+
         // Add port mapping for web view proxy if specified
         if let Some(port) = config.web_view_proxy_port {
             if port > 0 {
@@ -408,14 +1184,14 @@ impl DockerManager {
         }
 
         // Build the claude command
-        let mut claude_cmd = vec!["claude".to_string()];
+        let claude_binary = self.config.claude_binary.as_deref().unwrap_or("claude");
+        let mut claude_cmd = vec![claude_binary.to_string()];
 
-        if options.skip_permissions {
-            claude_cmd.push("--dangerously-skip-permissions".to_string());
-        } else {
-            claude_cmd.push("--permission-prompt-tool".to_string());
-            claude_cmd.push(options.permission_prompt_tool.to_string());
-        }
+        claude_cmd.extend(
+            options
+                .permission_mode
+                .claude_args(options.permission_prompt_tool.as_deref()),
+        );
 
         if options.debug {
             claude_cmd.push("--debug".to_string());
@@ -429,6 +1205,11 @@ impl DockerManager {
             }
         }
 
+        if options.claude_json {
+            claude_cmd.push("--output-format".to_string());
+            claude_cmd.push("json".to_string());
+        }
+
         claude_cmd.extend(vec!["-p".to_string(), options.prompt.to_string()]);
 
         // The entrypoint script will run automatically, we just need to pass the claude command
@@ -437,11 +1218,15 @@ impl DockerManager {
         if options.debug {
             println!("🔍 Container command:");
             println!("   Claude command: {}", cmd.join(" "));
-            println!("   (Entrypoint script will run automatically)");
+            match self.config.entrypoint {
+                Some(ref entrypoint) => println!("   Entrypoint: {}", entrypoint.join(" ")),
+                None => println!("   (Entrypoint script will run automatically)"),
+            }
         }
 
         let mut container_config = Config {
             image: Some(self.config.image_name.clone()),
+            entrypoint: self.config.entrypoint.clone(),
             cmd: Some(cmd),
             env: Some(env_vars),
             working_dir: Some("/workspace".to_string()),
@@ -462,16 +1247,64 @@ impl DockerManager {
         Ok(container_config)
     }
 
-    async fn stream_and_parse_logs(&self, container_id: &str, debug: bool) -> Result<String> {
+    /// Wait for a container to stop and fail if it exited with a non-zero status
+    async fn wait_for_exit(
+        &self,
+        container_id: &str,
+        task_log: Option<&mut TaskLog>,
+    ) -> Result<()> {
+        let wait_options = WaitContainerOptions {
+            condition: "not-running".to_string(),
+        };
+
+        let mut wait_stream = self.docker.wait_container(container_id, Some(wait_options));
+        if let Some(result) = wait_stream.next().await {
+            match result {
+                Ok(wait_result) => {
+                    if let Some(log) = task_log {
+                        log.write(
+                            "task",
+                            &format!("Container exited with status {}", wait_result.status_code),
+                        );
+                    }
+                    if wait_result.status_code != 0 {
+                        return Err(ContainerExitError {
+                            status_code: wait_result.status_code,
+                        }
+                        .into());
+                    }
+                }
+                Err(e) => {
+                    if let Some(log) = task_log {
+                        log.write("task", &format!("Wait error: {e}"));
+                    }
+                    return Err(anyhow::anyhow!("Wait error: {}", e));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Stream a container's logs, printing Claude's response between markers.
+    /// Stdout is appended to `claude_output` as it arrives, and printed if `echo_stdout`.
+    async fn stream_and_parse_logs(
+        &self,
+        container_id: &str,
+        debug: bool,
+        follow: bool,
+        echo_stdout: bool,
+        mut task_log: Option<&mut TaskLog>,
+        claude_output: &mut String,
+    ) -> Result<()> {
         let logs_options = LogsOptions::<String> {
-            follow: true,
+            follow,
             stdout: true,
             stderr: true,
             ..Default::default()
         };
 
         let mut log_stream = self.docker.logs(container_id, Some(logs_options));
-        let mut claude_output = String::new();
         let mut capturing_claude = false;
         let mut response_started = false;
 
@@ -488,13 +1321,23 @@ impl DockerManager {
                         response_started = true;
                     }
 
-                    // Always stream stdout to the user
-                    print!("{text}");
+                    if echo_stdout {
+                        print!("{text}");
+                        crate::reporter::emit(crate::reporter::RunEvent::ClaudeOutput {
+                            text: text.to_string(),
+                        });
+                    }
+                    if let Some(log) = task_log.as_deref_mut() {
+                        log.write("stdout", &text);
+                    }
                     // Capture it for return value
                     claude_output.push_str(&text);
                 }
                 Ok(LogOutput::StdErr { message }) => {
                     let text = String::from_utf8_lossy(&message);
+                    if let Some(log) = task_log.as_deref_mut() {
+                        log.write("stderr", &text);
+                    }
 
                     // Check for output markers
                     if text.contains("=== CLAUDE_OUTPUT_START ===") {
@@ -520,32 +1363,282 @@ impl DockerManager {
             }
         }
 
-        Ok(claude_output)
+        Ok(())
     }
 
-    fn create_tar_archive(&self, context_path: &Path) -> Result<Vec<u8>> {
-        use std::io::Cursor;
-        use tar::Builder;
+    /// Name of the container that runs the given task
+    pub fn container_name(&self, task_id: &str) -> String {
+        format!("{}{}", self.config.container_name_prefix, task_id)
+    }
 
-        let mut archive_data = Vec::new();
-        {
-            let cursor = Cursor::new(&mut archive_data);
-            let mut archive = Builder::new(cursor);
+    /// List containers whose names start with the task container prefix.
+    /// Stopped containers (e.g. finished background tasks) are only included with `all`.
+    pub async fn list_task_containers(&self, all: bool) -> Result<Vec<TaskContainer>> {
+        let prefix = &self.config.container_name_prefix;
+        let mut filters = HashMap::new();
+        filters.insert("name".to_string(), vec![prefix.clone()]);
+
+        let containers = self
+            .docker
+            .list_containers(Some(ListContainersOptions::<String> {
+                all,
+                filters,
+                ..Default::default()
+            }))
+            .await
+            .context("Failed to list containers")?;
+
+        let now = chrono::Utc::now().timestamp();
+        let mut tasks: Vec<TaskContainer> = containers
+            .into_iter()
+            .filter_map(|container| {
+                // The name filter matches substrings, so check the prefix ourselves
+                let name = container
+                    .names
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|name| name.trim_start_matches('/').to_string())
+                    .find(|name| name.starts_with(prefix.as_str()))?;
+                let state = container.state.unwrap_or_else(|| "unknown".to_string());
+                let uptime_seconds = match (state.as_str(), container.created) {
+                    ("running", Some(created)) => Some(now.saturating_sub(created).max(0) as u64),
+                    _ => None,
+                };
+
+                Some(TaskContainer {
+                    task_id: name[prefix.len()..].to_string(),
+                    name,
+                    state,
+                    status: container.status.unwrap_or_default(),
+                    uptime_seconds,
+                })
+            })
+            .collect();
+        tasks.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+
+        Ok(tasks)
+    }
+
+    /// State of a task's container (e.g. "running" or "exited") and its exit code once it
+    /// has exited. `None` if there is no such container.
+    pub async fn task_container_state(
+        &self,
+        task_id: &str,
+    ) -> Result<Option<(String, Option<i64>)>> {
+        let container_name = self.container_name(task_id);
+        match self.docker.inspect_container(&container_name, None).await {
+            Ok(container) => {
+                let state = container.state.unwrap_or_default();
+                let status = state
+                    .status
+                    .map(|status| status.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+                Ok(Some((status, state.exit_code)))
+            }
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => Ok(None),
+            Err(e) => Err(e).context("Failed to inspect container"),
+        }
+    }
+
+    /// The last `tail` lines of a task container's raw output
+    pub async fn task_logs(&self, task_id: &str, tail: usize) -> Result<String> {
+        let container_name = self.container_name(task_id);
+        let mut stream = self.docker.logs(
+            &container_name,
+            Some(LogsOptions::<String> {
+                stdout: true,
+                stderr: true,
+                tail: tail.to_string(),
+                ..Default::default()
+            }),
+        );
+
+        let mut logs = String::new();
+        while let Some(output) = stream.next().await {
+            logs.push_str(&output.context("Failed to read container logs")?.to_string());
+        }
+        Ok(logs)
+    }
+
+    /// Print a task container's output, delimiting Claude's response like a foreground run
+    pub async fn print_task_logs(&self, task_id: &str, follow: bool, debug: bool) -> Result<()> {
+        let container_name = self.container_name(task_id);
+        match self.docker.inspect_container(&container_name, None).await {
+            Ok(_) => {}
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => anyhow::bail!(
+                "No such container '{container_name}'. Foreground tasks remove their container when they finish; \
+                only background tasks can be followed"
+            ),
+            Err(e) => return Err(e).context("Failed to inspect container"),
+        }
+
+        let mut output = String::new();
+        self.stream_and_parse_logs(&container_name, debug, follow, true, None, &mut output)
+            .await?;
+        if !output.is_empty() {
+            println!();
+            println!("=============== 💬 CLAUDE'S RESPONSE END 💬 ===============");
+        }
+
+        Ok(())
+    }
+
+    /// Run a command inside a task's running container, attached to this terminal.
+    /// Returns the command's exit code.
+    pub async fn exec_in_task(&self, task_id: &str, command: Vec<String>) -> Result<i64> {
+        use std::io::{IsTerminal, Write};
+        use tokio::io::AsyncWriteExt;
+
+        let container_name = self.container_name(task_id);
+        let container = match self.docker.inspect_container(&container_name, None).await {
+            Ok(container) => container,
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => anyhow::bail!(
+                "No such container '{container_name}'. It may have already finished and been removed"
+            ),
+            Err(e) => return Err(e).context("Failed to inspect container"),
+        };
+        let running = container
+            .state
+            .and_then(|state| state.running)
+            .unwrap_or(false);
+        if !running {
+            anyhow::bail!("Container '{container_name}' is not running");
+        }
+
+        let tty = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        let exec = self
+            .docker
+            .create_exec(
+                &container_name,
+                CreateExecOptions {
+                    attach_stdin: Some(true),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    tty: Some(tty),
+                    cmd: Some(command),
+                    working_dir: Some("/workspace".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .context("Failed to create exec instance")?;
+
+        let start_options = StartExecOptions {
+            detach: false,
+            tty,
+            ..Default::default()
+        };
+        let StartExecResults::Attached {
+            mut output,
+            mut input,
+        } = self
+            .docker
+            .start_exec(&exec.id, Some(start_options))
+            .await
+            .context("Failed to start exec instance")?
+        else {
+            anyhow::bail!("Exec instance started detached");
+        };
+
+        let raw_terminal = if tty { RawTerminal::enable() } else { None };
+        if let Some((height, width)) = RawTerminal::size().filter(|_| tty) {
+            let _ = self
+                .docker
+                .resize_exec(&exec.id, ResizeExecOptions { height, width })
+                .await;
+        }
+
+        // Forward our stdin to the command until it exits. Stdin is read on a plain thread
+        // because a pending blocking read would otherwise keep the runtime from shutting down.
+        let (stdin_tx, mut stdin_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(16);
+        std::thread::spawn(move || {
+            use std::io::Read;
+            let mut stdin = std::io::stdin();
+            let mut buffer = [0u8; 1024];
+            while let Ok(read) = stdin.read(&mut buffer) {
+                if read == 0 || stdin_tx.blocking_send(buffer[..read].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        let stdin_task = tokio::spawn(async move {
+            while let Some(bytes) = stdin_rx.recv().await {
+                if input.write_all(&bytes).await.is_err() {
+                    break;
+                }
+            }
+            let _ = input.shutdown().await;
+        });
+
+        while let Some(result) = output.next().await {
+            match result {
+                Ok(LogOutput::StdErr { message }) => {
+                    let mut stderr = std::io::stderr();
+                    stderr.write_all(&message)?;
+                    stderr.flush()?;
+                }
+                Ok(LogOutput::StdOut { message }) | Ok(LogOutput::Console { message }) => {
+                    let mut stdout = std::io::stdout();
+                    stdout.write_all(&message)?;
+                    stdout.flush()?;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    drop(raw_terminal);
+                    return Err(e).context("Exec stream error");
+                }
+            }
+        }
 
-            // Add all files in the context directory
-            archive
-                .append_dir_all(".", context_path)
-                .context("Failed to create tar archive")?;
+        stdin_task.abort();
+        drop(raw_terminal);
 
-            archive.finish().context("Failed to finalize tar archive")?;
+        let exit_code = self
+            .docker
+            .inspect_exec(&exec.id)
+            .await
+            .context("Failed to inspect exec instance")?
+            .exit_code
+            .unwrap_or(0);
+
+        Ok(exit_code)
+    }
+
+    /// Stop the container running the given task
+    pub async fn stop_task(&self, task_id: &str) -> Result<StopOutcome> {
+        let container_name = self.container_name(task_id);
+        match self.docker.stop_container(&container_name, None).await {
+            Ok(_) => Ok(StopOutcome::Stopped),
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 304, ..
+            }) => Ok(StopOutcome::NotRunning),
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => Ok(StopOutcome::NotFound),
+            Err(e) => {
+                Err(e).with_context(|| format!("Failed to stop container '{container_name}'"))
+            }
         }
+    }
 
-        Ok(archive_data)
+    /// Check that the Docker daemon is reachable, with a friendlier error than the raw socket failure
+    pub async fn ensure_daemon_available(&self) -> Result<()> {
+        self.docker.ping().await.map_err(|e| {
+            anyhow::anyhow!(
+                "Docker daemon is not available. Make sure Docker is installed and running.\n   Details: {e}"
+            )
+        })?;
+        Ok(())
     }
 
     /// List volumes related to Claude tasks
-    #[allow(dead_code)]
-    pub async fn list_claude_volumes(&self) -> Result<Vec<(String, String)>> {
+    pub async fn list_claude_volumes(&self) -> Result<Vec<VolumeInfo>> {
         let list_options = ListVolumesOptions::<String> {
             filters: {
                 let mut filters = HashMap::new();
@@ -562,20 +1655,25 @@ impl DockerManager {
 
         let mut volume_info = Vec::new();
         for volume in volumes_response.volumes.unwrap_or_default() {
-            let name = volume.name;
             // Get volume size by inspecting it
             let size = self
-                .get_volume_size(&name)
+                .get_volume_size(&volume.name)
                 .await
                 .unwrap_or_else(|_| "unknown".to_string());
-            volume_info.push((name, size));
+            let mut labels: Vec<(String, String)> = volume.labels.into_iter().collect();
+            labels.sort();
+            volume_info.push(VolumeInfo {
+                name: volume.name,
+                size,
+                labels,
+            });
         }
+        volume_info.sort_by(|a, b| a.name.cmp(&b.name));
 
         Ok(volume_info)
     }
 
     /// Get the size of a Docker volume
-    #[allow(dead_code)]
     async fn get_volume_size(&self, volume_name: &str) -> Result<String> {
         use std::process::Command;
 
@@ -609,12 +1707,416 @@ impl DockerManager {
         Ok(size)
     }
 
-    /// Check if claude-task-home volume exists
-    pub async fn check_home_volume_exists(&self) -> Result<bool> {
-        match self.docker.inspect_volume(&self.config.volumes.home).await {
+    /// Names of volumes mounted by currently running containers
+    async fn volumes_in_use(&self) -> Result<HashSet<String>> {
+        let containers = self
+            .docker
+            .list_containers(Some(ListContainersOptions::<String> {
+                all: false,
+                ..Default::default()
+            }))
+            .await
+            .context("Failed to list running containers")?;
+
+        Ok(containers
+            .into_iter()
+            .flat_map(|container| container.mounts.unwrap_or_default())
+            .filter_map(|mount| mount.name)
+            .collect())
+    }
+
+    /// Remove the shared volumes created for Claude tasks (labeled `project=claude-task`).
+    /// The home volume holds the task credentials and is only removed when `include_home` is set.
+    /// Volumes mounted by a running container are never removed.
+    pub async fn clean_shared_volumes(
+        &self,
+        skip_confirmation: bool,
+        include_home: bool,
+    ) -> Result<Vec<String>> {
+        let list_options = ListVolumesOptions::<String> {
+            filters: {
+                let mut filters = HashMap::new();
+                filters.insert("label".to_string(), vec!["project=claude-task".to_string()]);
+                filters
+            },
+        };
+
+        let volumes_response = self
+            .docker
+            .list_volumes(Some(list_options))
+            .await
+            .context("Failed to list volumes")?;
+
+        let mut volumes: Vec<String> = volumes_response
+            .volumes
+            .unwrap_or_default()
+            .into_iter()
+            .map(|volume| volume.name)
+            .filter(|name| include_home || *name != self.config.volumes.home)
+            .collect();
+        volumes.sort();
+
+        if volumes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let in_use = self.volumes_in_use().await?;
+        let (skipped, volumes): (Vec<String>, Vec<String>) =
+            volumes.into_iter().partition(|name| in_use.contains(name));
+
+        for name in &skipped {
+            println!("   ⚠️  Skipping volume '{name}': it is mounted by a running container");
+        }
+
+        if volumes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        println!("   The following volumes will be removed:");
+        for name in &volumes {
+            println!("   - {name}");
+        }
+
+        if !skip_confirmation {
+            print!("   Are you sure you want to remove these volumes? [y/N]: ");
+            use std::io::{self, Write};
+            io::stdout().flush().context("Failed to flush stdout")?;
+
+            let mut input = String::new();
+            io::stdin()
+                .read_line(&mut input)
+                .context("Failed to read input")?;
+            if input.trim().to_lowercase() != "y" {
+                println!("   Volume cleanup cancelled.");
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut removed = Vec::new();
+        for name in volumes {
+            match self.docker.remove_volume(&name, None).await {
+                Ok(_) => {
+                    println!("   ✓ Removed volume '{name}'");
+                    removed.push(name);
+                }
+                Err(e) => println!("   ⚠️  Could not remove volume '{name}': {e}"),
+            }
+        }
+
+        if !skipped.is_empty() {
+            println!(
+                "   Skipped {} volume(s) in use: {}",
+                skipped.len(),
+                skipped.join(", ")
+            );
+        }
+
+        Ok(removed)
+    }
+
+    /// Check if a volume exists
+    async fn volume_exists(&self, name: &str) -> Result<bool> {
+        match self.docker.inspect_volume(name).await {
             Ok(_) => Ok(true),
             Err(e) if e.to_string().contains("no such volume") => Ok(false),
             Err(e) => Err(anyhow::anyhow!("Failed to check volume: {}", e)),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_platform_mismatch_warning() {
+        let host = host_docker_arch();
+        assert!(platform_mismatch_warning(&format!("linux/{host}")).is_none());
+
+        let other = if host == "arm64" { "amd64" } else { "arm64" };
+        let warning = platform_mismatch_warning(&format!("linux/{other}")).unwrap();
+        assert!(warning.contains(&format!("linux/{other}")));
+        assert!(warning.contains(host));
+    }
+
+    #[test]
+    fn test_resolve_timezone() {
+        let localtime = Path::new("/usr/share/zoneinfo/Europe/Berlin");
+
+        // TZ wins, with or without the leading colon
+        assert_eq!(
+            resolve_timezone(Some("Asia/Tokyo"), Some(localtime), Some("Etc/UTC\n")),
+            "Asia/Tokyo"
+        );
+        assert_eq!(
+            resolve_timezone(Some(":Asia/Tokyo"), None, None),
+            "Asia/Tokyo"
+        );
+        // An empty TZ or a path to a zone file falls through to /etc/localtime
+        assert_eq!(
+            resolve_timezone(Some(""), Some(localtime), None),
+            "Europe/Berlin"
+        );
+        assert_eq!(
+            resolve_timezone(Some("/etc/localtime"), Some(localtime), None),
+            "Europe/Berlin"
+        );
+        // macOS keeps zone files in a different directory
+        assert_eq!(
+            resolve_timezone(
+                None,
+                Some(Path::new("/var/db/timezone/zoneinfo/America/Chicago")),
+                None
+            ),
+            "America/Chicago"
+        );
+        // A symlink that isn't into a zoneinfo directory falls through to /etc/timezone
+        assert_eq!(
+            resolve_timezone(None, Some(Path::new("/tmp/localtime")), Some("Etc/UTC\n")),
+            "Etc/UTC"
+        );
+        assert_eq!(resolve_timezone(None, None, Some("  \n")), "UTC");
+        assert_eq!(resolve_timezone(None, None, None), "UTC");
+    }
+
+    #[test]
+    fn test_parse_claude_json() {
+        let output = r#"{"type":"result","subtype":"success","is_error":false,"duration_ms":5120,"num_turns":3,"result":"Done.","session_id":"abc","total_cost_usd":0.0123,"usage":{"input_tokens":10,"output_tokens":20,"cache_read_input_tokens":30},"permission_denials":[{"tool_name":"Bash","tool_use_id":"t1","tool_input":{}}]}"#;
+        let result = parse_claude_json(&format!("{output}\n")).unwrap();
+        assert_eq!(result.message, "Done.");
+        assert!(!result.is_error);
+        assert_eq!(result.num_turns, Some(3));
+        assert_eq!(result.session_id.as_deref(), Some("abc"));
+        assert_eq!(result.total_cost_usd, Some(0.0123));
+        assert_eq!(
+            result.usage,
+            ClaudeUsage {
+                input_tokens: 10,
+                output_tokens: 20,
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: 30,
+            }
+        );
+        assert_eq!(result.permission_denials, vec!["Bash"]);
+        assert!(result.tool_uses.is_empty());
+
+        // --verbose output lists every message before the result
+        let verbose = r#"[
+            {"type":"system","subtype":"init"},
+            {"type":"assistant","message":{"content":[{"type":"text","text":"Looking"},{"type":"tool_use","name":"Read","input":{}}]}},
+            {"type":"assistant","message":{"content":[{"type":"tool_use","name":"Read","input":{}},{"type":"tool_use","name":"Edit","input":{}}]}},
+            {"type":"result","is_error":true,"subtype":"error_max_turns"}
+        ]"#;
+        let result = parse_claude_json(verbose).unwrap();
+        assert!(result.is_error);
+        assert_eq!(result.message, "");
+        assert_eq!(result.tool_uses.get("Read"), Some(&2));
+        assert_eq!(result.tool_uses.get("Edit"), Some(&1));
+
+        // Text around the JSON still parses line by line
+        let mixed = format!("some warning\n{output}\n");
+        assert_eq!(parse_claude_json(&mixed).unwrap().message, "Done.");
+
+        assert_eq!(parse_claude_json("Plain text response"), None);
+        assert_eq!(parse_claude_json(r#"{"type":"assistant"}"#), None);
+    }
+
+    #[test]
+    fn test_create_tar_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Dockerfile"), "FROM scratch\n").unwrap();
+        std::fs::create_dir(dir.path().join("scripts")).unwrap();
+        std::fs::write(dir.path().join("scripts/setup.sh"), "echo hi\n").unwrap();
+
+        let data = create_tar_archive(dir.path()).unwrap();
+        let mut archive = tar::Archive::new(data.as_slice());
+        let paths: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect();
+        assert!(paths.iter().any(|p| p == "Dockerfile"));
+        assert!(paths.iter().any(|p| p == "scripts/setup.sh"));
+    }
+
+    #[test]
+    fn test_is_user_defined_network() {
+        assert!(!is_user_defined_network("bridge"));
+        assert!(!is_user_defined_network("host"));
+        assert!(!is_user_defined_network("none"));
+        assert!(!is_user_defined_network("container:approval-server"));
+        assert!(is_user_defined_network("mcp-net"));
+    }
+
+    #[test]
+    fn test_versioned_image_name() {
+        let version = env!("CARGO_PKG_VERSION");
+
+        assert_eq!(
+            split_image_tag("ghcr.io/toolprint/claude-task:latest"),
+            ("ghcr.io/toolprint/claude-task", Some("latest"))
+        );
+        assert_eq!(
+            split_image_tag("localhost:5000/claude-task"),
+            ("localhost:5000/claude-task", None)
+        );
+
+        assert_eq!(
+            versioned_image_name("ghcr.io/toolprint/claude-task:latest"),
+            Some(format!("ghcr.io/toolprint/claude-task:{version}"))
+        );
+        assert_eq!(
+            versioned_image_name("localhost:5000/claude-task"),
+            Some(format!("localhost:5000/claude-task:{version}"))
+        );
+        assert_eq!(
+            versioned_image_name(&format!("claude-task:{version}")),
+            None
+        );
+        assert_eq!(versioned_image_name("claude-task@sha256:abc123"), None);
+
+        // Differently tagged images keep separate pins
+        assert_eq!(
+            versioned_image_name("acme/claude-task:py"),
+            Some(format!("acme/claude-task:py-{version}"))
+        );
+        assert_eq!(
+            versioned_image_name("acme/claude-task:node"),
+            Some(format!("acme/claude-task:node-{version}"))
+        );
+        assert_eq!(
+            versioned_image_name(&format!("acme/claude-task:py-{version}")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_choose_image() {
+        let built = |id: &str, source: &str| LocalImage {
+            id: Some(id.to_string()),
+            source_image: Some(source.to_string()),
+        };
+        let pulled = LocalImage {
+            id: Some("sha256:pulled".to_string()),
+            source_image: None,
+        };
+
+        // Two tagged images built side by side each run their own pin
+        let py = built("sha256:py", "acme/claude-task:py");
+        let node = built("sha256:node", "acme/claude-task:node");
+        assert_eq!(
+            choose_image("acme/claude-task:py", Some(&py), Some(("py-pin", &py))),
+            Some("py-pin".to_string())
+        );
+        assert_eq!(
+            choose_image(
+                "acme/claude-task:node",
+                Some(&node),
+                Some(("node-pin", &node))
+            ),
+            Some("node-pin".to_string())
+        );
+
+        // A pin built from another reference is never used
+        assert_eq!(
+            choose_image("acme/claude-task:py", Some(&py), Some(("pin", &node))),
+            Some("acme/claude-task:py".to_string())
+        );
+        assert_eq!(
+            choose_image("acme/claude-task:py", None, Some(("pin", &node))),
+            None
+        );
+
+        // A freshly pulled image wins over an older pin
+        let old_build = built("sha256:old", "acme/claude-task:latest");
+        assert_eq!(
+            choose_image(
+                "acme/claude-task:latest",
+                Some(&pulled),
+                Some(("pin", &old_build))
+            ),
+            Some("acme/claude-task:latest".to_string())
+        );
+
+        // Rolling back: the configured tag was rebuilt by another version, so the pin
+        // for this version is used
+        let newer_build = built("sha256:new", "acme/claude-task:latest");
+        assert_eq!(
+            choose_image(
+                "acme/claude-task:latest",
+                Some(&newer_build),
+                Some(("pin", &old_build))
+            ),
+            Some("pin".to_string())
+        );
+        assert_eq!(
+            choose_image("acme/claude-task:latest", None, Some(("pin", &old_build))),
+            Some("pin".to_string())
+        );
+        assert_eq!(choose_image("acme/claude-task:latest", None, None), None);
+    }
+
+    #[test]
+    fn test_redact_env_var() {
+        let configured = HashMap::from([("API_KEY".to_string(), "secret".to_string())]);
+
+        assert_eq!(redact_env_var("TZ=UTC", &configured), "TZ=UTC");
+        assert_eq!(
+            redact_env_var("GIT_TOKEN=ghp_abc", &configured),
+            "GIT_TOKEN=<redacted>"
+        );
+        assert_eq!(
+            redact_env_var("CLAUDE_CODE_OAUTH_TOKEN=sk-ant", &configured),
+            "CLAUDE_CODE_OAUTH_TOKEN=<redacted>"
+        );
+        assert_eq!(
+            redact_env_var("API_KEY=secret", &configured),
+            "API_KEY=<redacted>"
+        );
+    }
+
+    #[test]
+    fn test_merge_env_vars() {
+        let defaults = vec![
+            "TASK_ID=abc".to_string(),
+            "NODE_OPTIONS=--max-old-space-size=4096".to_string(),
+        ];
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            "NODE_OPTIONS".to_string(),
+            "--max-old-space-size=8192".to_string(),
+        );
+        overrides.insert(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "https://proxy.example.com".to_string(),
+        );
+
+        assert_eq!(
+            merge_env_vars(defaults, &overrides),
+            vec![
+                "TASK_ID=abc",
+                "ANTHROPIC_BASE_URL=https://proxy.example.com",
+                "NODE_OPTIONS=--max-old-space-size=8192",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_log_prefixes_lines() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let path = temp.path().join("logs").join("task.log");
+        {
+            let mut log = TaskLog::create(&path, "abc").unwrap();
+            log.write("stderr", "setting up\ncloning\n");
+            log.write("stdout", "All done");
+        }
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with("[task] Starting task abc"));
+        assert!(lines[1].starts_with('['));
+        assert!(lines[2].ends_with("[stderr] cloning"));
+        assert!(lines[3].ends_with("[stdout] All done"));
+    }
+}
@@ -0,0 +1,242 @@
+use anyhow::Result;
+use std::process::Command;
+
+use crate::config::{Config, ExecutionEnvironment};
+
+/// Outcome of a single doctor check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Collects check results and prints them as they come in
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    fn record(&mut self, status: CheckStatus, name: &str, detail: &str, hint: Option<&str>) {
+        match status {
+            CheckStatus::Pass => println!("  ✅ {name}"),
+            CheckStatus::Warn => {
+                self.warnings += 1;
+                println!("  ⚠️  {name}: {detail}");
+            }
+            CheckStatus::Fail => {
+                self.failures += 1;
+                println!("  ❌ {name}: {detail}");
+            }
+        }
+        if status != CheckStatus::Pass {
+            if let Some(hint) = hint {
+                println!("     💡 {hint}");
+            }
+        }
+    }
+
+    fn pass(&mut self, name: &str) {
+        self.record(CheckStatus::Pass, name, "", None);
+    }
+
+    /// Record a problem as a failure when `critical`, otherwise as a warning
+    fn problem(&mut self, critical: bool, name: &str, detail: &str, hint: &str) {
+        let status = if critical {
+            CheckStatus::Fail
+        } else {
+            CheckStatus::Warn
+        };
+        self.record(status, name, detail, Some(hint));
+    }
+}
+
+/// Run environment diagnostics and fail if any check critical to the
+/// configured runner fails.
+pub async fn run_doctor(config: &Config) -> Result<()> {
+    println!("🩺 Checking claude-task environment");
+    println!("   Runner: {:?}", config.task_runner);
+    println!();
+
+    let mut report = Report::default();
+    let uses_docker = config.task_runner == ExecutionEnvironment::Docker;
+    let uses_kube = config.task_runner == ExecutionEnvironment::Kubernetes;
+
+    println!("Docker:");
+    check_docker(config, uses_docker, &mut report).await;
+    println!();
+
+    if uses_kube {
+        println!("Kubernetes:");
+        check_kubernetes(config, &mut report).await;
+        println!();
+    }
+
+    println!("Tools:");
+    check_ht_mcp(config, &mut report);
+    check_github(&mut report);
+    println!();
+
+    println!("Credentials:");
+    check_credentials(config, &mut report).await;
+    println!();
+
+    if report.failures > 0 {
+        anyhow::bail!(
+            "{} critical check(s) failed, {} warning(s)",
+            report.failures,
+            report.warnings
+        );
+    }
+    if report.warnings > 0 {
+        println!(
+            "⚠️  No critical problems found, {} warning(s)",
+            report.warnings
+        );
+    } else {
+        println!("✅ All checks passed");
+    }
+    Ok(())
+}
+
+async fn check_docker(config: &Config, critical: bool, report: &mut Report) {
+    let manager = match crate::docker::DockerManager::new(config.docker.clone()) {
+        Ok(manager) => manager,
+        Err(e) => {
+            report.problem(
+                critical,
+                "Docker daemon reachable",
+                &format!("{e:#}"),
+                "Install Docker Desktop or Docker Engine and make sure it is running",
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = manager.ensure_daemon_available().await {
+        report.problem(
+            critical,
+            "Docker daemon reachable",
+            &format!("{e:#}"),
+            "Start Docker (e.g. open Docker Desktop or `sudo systemctl start docker`)",
+        );
+        return;
+    }
+    report.pass("Docker daemon reachable");
+
+    let image = &config.docker.image_name;
+    let name = format!("Image '{image}' present");
+    match manager.check_image_exists(image).await {
+        Ok(()) => report.pass(&name),
+        Err(e) => report.problem(
+            critical,
+            &name,
+            &format!("{e:#}"),
+            "Build it with `ct docker init` or pull it with `docker pull`",
+        ),
+    }
+}
+
+async fn check_kubernetes(config: &Config, report: &mut Report) {
+    match Command::new("kubectl")
+        .args(["version", "--client"])
+        .output()
+    {
+        Ok(output) if output.status.success() => report.pass("kubectl installed"),
+        _ => report.problem(
+            false,
+            "kubectl installed",
+            "kubectl not found in PATH",
+            "Install kubectl to inspect jobs outside of claude-task",
+        ),
+    }
+
+    let kube_context = config
+        .kube_config
+        .as_ref()
+        .and_then(|kube| kube.context.clone())
+        .or_else(Config::get_current_kube_context);
+    let Some(context) = kube_context else {
+        report.problem(
+            true,
+            "Cluster access",
+            "no context configured and no current kubectl context",
+            "Set kubeConfig.context in the config or run `kubectl config use-context <name>`",
+        );
+        return;
+    };
+
+    let name = format!("Cluster access (context '{context}')");
+    match crate::validate_kubernetes_access(&context).await {
+        Ok(()) => report.pass(&name),
+        Err(e) => report.problem(
+            true,
+            &name,
+            &format!("{e:#}"),
+            "Check your kubeconfig credentials and that you can create jobs in the namespace",
+        ),
+    }
+}
+
+fn check_ht_mcp(config: &Config, report: &mut Report) {
+    if Config::check_ht_mcp_availability() {
+        report.pass("ht-mcp available");
+    } else {
+        report.problem(
+            config.global_option_defaults.require_ht_mcp,
+            "ht-mcp available",
+            "ht-mcp binary not found",
+            "Install ht-mcp to use --ht-mcp-port and the web view",
+        );
+    }
+}
+
+fn check_github(report: &mut Report) {
+    if std::env::var("GITHUB_TOKEN").is_ok() {
+        report.pass("GitHub token (GITHUB_TOKEN env var)");
+        return;
+    }
+    match Command::new("gh").args(["auth", "token"]).output() {
+        Ok(output) if output.status.success() => report.pass("GitHub token (gh CLI)"),
+        Ok(_) => report.problem(
+            false,
+            "GitHub token",
+            "gh CLI is installed but not logged in",
+            "Run `gh auth login` or export GITHUB_TOKEN",
+        ),
+        Err(_) => report.problem(
+            false,
+            "GitHub token",
+            "GITHUB_TOKEN is not set and gh CLI is not installed",
+            "Install gh and run `gh auth login`, or export GITHUB_TOKEN",
+        ),
+    }
+}
+
+async fn check_credentials(config: &Config, report: &mut Report) {
+    if config.claude_credentials.is_some() {
+        report.pass("Claude token configured");
+        return;
+    }
+
+    #[cfg(target_os = "macos")]
+    match crate::credentials::extract_keychain_credentials(&config.claude_user_config).await {
+        Ok(_) => report.pass("Keychain access"),
+        Err(e) => report.problem(
+            true,
+            "Keychain access",
+            &format!("{e:#}"),
+            "Log in with `claude` once so its credentials are stored, or set a token with `ct config token`",
+        ),
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    report.problem(
+        false,
+        "Claude credentials",
+        "no token configured; the keychain is only checked on macOS",
+        "Set a token with `ct config token`, or CLAUDE_CODE_OAUTH_TOKEN with CLAUDE_TASK_USE_ENV_TOKEN=1",
+    );
+}
@@ -3,37 +3,74 @@ use dialoguer::Select;
 use std::path::PathBuf;
 use std::process::Command;
 
-use crate::config::{Config, ExecutionEnvironment};
-use crate::ConfigCommands;
+use crate::config::{
+    diff_config_values, Config, ConfigFormat, ExecutionEnvironment, ImagePullPolicy, MemoryLimit,
+    RedactedConfig, TOKEN_FROM_ENV_OPT_IN,
+};
+use crate::{ConfigCommands, ProfileCommands};
 
 pub async fn handle_config_command(
     command: ConfigCommands,
     config_path: Option<&PathBuf>,
 ) -> Result<()> {
     match command {
-        ConfigCommands::Init { force } => {
-            let path = config_path
-                .cloned()
-                .unwrap_or_else(Config::default_config_path);
+        ConfigCommands::Init {
+            force,
+            no_schema,
+            format,
+        } => {
+            let custom_path = match config_path {
+                Some(path) => Some(path.clone()),
+                None => Config::path_from_env()?,
+            };
+            let (path, existing) = match custom_path {
+                Some(ref path) => {
+                    if format.is_some_and(|format| format != ConfigFormat::from_path(path)) {
+                        anyhow::bail!(
+                            "--format doesn't match the extension of {}; use a .json, .yaml or .yml path",
+                            path.display()
+                        );
+                    }
+                    (path.clone(), path.clone())
+                }
+                // A config in another format would still be loaded instead of the new one
+                None => (
+                    Config::default_config_dir()
+                        .join(format.unwrap_or_default().default_file_name()),
+                    Config::default_config_path(),
+                ),
+            };
+            let format = ConfigFormat::from_path(&path);
 
-            if path.exists() && !force {
-                println!("⚠️  Config file already exists at: {}", path.display());
+            if existing.exists() && !force {
+                println!("⚠️  Config file already exists at: {}", existing.display());
                 println!("   Use --force to overwrite");
                 return Ok(());
             }
+            if existing.exists() && existing != path {
+                let backup_path = Config::backup_path(&existing);
+                std::fs::rename(&existing, &backup_path)
+                    .with_context(|| format!("Failed to move {} aside", existing.display()))?;
+                println!("📦 Moved the existing config to: {}", backup_path.display());
+            }
 
-            let default_config = Config::default();
+            let mut default_config = Config::default();
+            if !no_schema {
+                // Editors resolve a relative $schema against the config file's directory
+                let schema_path = path.with_file_name(CONFIG_SCHEMA_FILE);
+                write_config_schema(&schema_path)?;
+                default_config.schema = Some(format!("./{CONFIG_SCHEMA_FILE}"));
+                println!("✅ Wrote config schema to: {}", schema_path.display());
+            }
             default_config.save(&path)?;
             println!("✅ Created config file at: {}", path.display());
 
             // Show a sample of the config
             println!("\nSample configuration:");
-            println!("{}", serde_json::to_string_pretty(&default_config)?);
+            println!("{}", format.serialize(&default_config)?);
         }
         ConfigCommands::Edit => {
-            let path = config_path
-                .cloned()
-                .unwrap_or_else(Config::default_config_path);
+            let path = Config::resolve_path(config_path)?;
 
             if !path.exists() {
                 println!("⚠️  Config file not found at: {}", path.display());
@@ -73,16 +110,61 @@ pub async fn handle_config_command(
                 }
             }
         }
-        ConfigCommands::Show { json } => {
-            let config = Config::load(config_path)?;
+        ConfigCommands::Show { json, reveal, diff } => {
+            // Show the effective config, including any CLAUDE_TASK_* overrides
+            let mut config = Config::load(config_path)?;
+            let env_overrides = config.apply_env_overrides()?;
+
+            if diff {
+                let to_value = |config: &Config| {
+                    if reveal {
+                        serde_json::to_value(config)
+                    } else {
+                        serde_json::to_value(RedactedConfig(config))
+                    }
+                };
+                let changes =
+                    diff_config_values(&to_value(&Config::default())?, &to_value(&config)?);
 
-            if json {
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&changes)?);
+                } else if changes.is_empty() {
+                    println!("No changes from the defaults");
+                } else {
+                    if !env_overrides.is_empty() {
+                        println!("Environment overrides: {}", env_overrides.join(", "));
+                        println!();
+                    }
+                    println!("Changes from defaults:");
+                    let show = |value: &Option<serde_json::Value>| match value {
+                        Some(value) => value.to_string(),
+                        None => "(unset)".to_string(),
+                    };
+                    for change in &changes {
+                        println!(
+                            "  {}: {} → {}",
+                            change.key,
+                            show(&change.default),
+                            show(&change.value)
+                        );
+                    }
+                }
+            } else if json && reveal {
                 println!("{}", serde_json::to_string_pretty(&config)?);
+            } else if json {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&RedactedConfig(&config))?
+                );
             } else {
                 // Pretty print with sections
                 println!("Claude Task Configuration");
                 println!("========================");
                 println!();
+                if !env_overrides.is_empty() {
+                    println!("Environment overrides: {}", env_overrides.join(", "));
+                    println!();
+                }
                 println!("Version: {}", config.version);
                 println!();
                 println!("Paths:");
@@ -105,6 +187,36 @@ pub async fn handle_config_command(
                 if let Some(port) = config.docker.default_ht_mcp_port {
                     println!("  Default HT-MCP Port: {port}");
                 }
+                if let Some(entrypoint) = &config.docker.entrypoint {
+                    println!("  Entrypoint: {}", entrypoint.join(" "));
+                }
+                if let Some(claude_binary) = &config.docker.claude_binary {
+                    println!("  Claude Binary: {claude_binary}");
+                }
+                match &config.docker.resources.memory {
+                    Some(MemoryLimit::Bytes(bytes)) => {
+                        println!("  Memory Limit: {bytes} bytes")
+                    }
+                    Some(MemoryLimit::Size(size)) => println!("  Memory Limit: {size}"),
+                    None => println!("  Memory Limit: unlimited"),
+                }
+                match config.docker.resources.cpus {
+                    Some(cpus) => println!("  CPU Limit: {cpus}"),
+                    None => println!("  CPU Limit: unlimited"),
+                }
+                match config.docker.timeout_seconds {
+                    Some(seconds) => println!("  Timeout: {seconds}s"),
+                    None => println!("  Timeout: none"),
+                }
+                if let Some(platform) = &config.docker.platform {
+                    println!("  Platform: {platform}");
+                }
+                if let Some(network_mode) = &config.docker.network_mode {
+                    println!("  Network Mode: {network_mode}");
+                }
+                if let Some(timezone) = &config.docker.timezone {
+                    println!("  Timezone: {timezone}");
+                }
                 println!();
                 println!("Claude User Config:");
                 println!("  Config Path: {}", config.claude_user_config.config_path);
@@ -112,6 +224,10 @@ pub async fn handle_config_command(
                     "  User Memory Path: {}",
                     config.claude_user_config.user_memory_path
                 );
+                println!(
+                    "  Use Biometrics: {}",
+                    config.claude_user_config.use_biometrics
+                );
                 println!();
                 println!("Worktree:");
                 if let Some(cmd) = &config.worktree.default_open_command {
@@ -121,6 +237,13 @@ pub async fn handle_config_command(
                     "  Auto Clean on Remove: {}",
                     config.worktree.auto_clean_on_remove
                 );
+                println!(
+                    "  Recurse Submodules: {}",
+                    config.worktree.recurse_submodules
+                );
+                if let Some(hook) = &config.worktree.post_create_hook {
+                    println!("  Post-Create Hook: {hook}");
+                }
                 println!();
                 println!("Global Option Defaults:");
                 println!("  Debug: {}", config.global_option_defaults.debug);
@@ -136,6 +259,10 @@ pub async fn handle_config_command(
                     "  Require HT-MCP: {}",
                     config.global_option_defaults.require_ht_mcp
                 );
+                if let Some(log_file) = &config.global_option_defaults.log_file {
+                    println!("  Log File: {log_file}");
+                }
+                println!("  Notify: {}", config.global_option_defaults.notify);
                 println!();
                 println!("Task Runner: {:?}", config.task_runner);
 
@@ -143,16 +270,11 @@ pub async fn handle_config_command(
                 println!();
                 println!("Claude Credentials:");
                 if let Some(credentials) = &config.claude_credentials {
-                    let masked_token = if credentials.token.len() > 8 {
-                        format!(
-                            "{}...{}",
-                            &credentials.token[..4],
-                            &credentials.token[credentials.token.len() - 4..]
-                        )
+                    if reveal {
+                        println!("  Token: {} (configured)", credentials.token);
                     } else {
-                        "****".to_string()
-                    };
-                    println!("  Token: {masked_token} (configured)");
+                        println!("  Token: {} (configured)", credentials.masked_token());
+                    }
                 } else {
                     println!("  Token: <not configured>");
                 }
@@ -176,36 +298,96 @@ pub async fn handle_config_command(
                             .unwrap_or(&"<auto-generate>".to_string())
                     );
                     println!("  Image: {}", kube_config.image);
+                    match kube_config.image_pull_policy {
+                        Some(policy) => println!("  Image Pull Policy: {}", policy.as_str()),
+                        None => println!(
+                            "  Image Pull Policy: {} (default for this image)",
+                            ImagePullPolicy::default_for_image(&kube_config.image).as_str()
+                        ),
+                    }
                     println!("  Git Secret Name: {}", kube_config.git_secret_name);
                     println!("  Git Secret Key: {}", kube_config.git_secret_key);
                     if let Some(pull_secret) = &kube_config.image_pull_secret {
                         println!("  Image Pull Secret: {pull_secret}");
                     }
+                    if let Some(credentials) = &kube_config.registry_credentials {
+                        println!(
+                            "  Registry Credentials: {} (password configured)",
+                            credentials.username
+                        );
+                    }
                     println!("  Namespace Confirmed: {}", kube_config.namespace_confirmed);
+                    if let Some(entrypoint) = &kube_config.entrypoint {
+                        println!("  Entrypoint: {}", entrypoint.join(" "));
+                    }
+                    if let Some(claude_binary) = &kube_config.claude_binary {
+                        println!("  Claude Binary: {claude_binary}");
+                    }
+                    let resources = &kube_config.resources;
+                    let show = |value: &Option<String>| value.clone().unwrap_or("-".to_string());
+                    println!(
+                        "  Requests: cpu={}, memory={}",
+                        show(&resources.requests.cpu),
+                        show(&resources.requests.memory)
+                    );
+                    println!(
+                        "  Limits: cpu={}, memory={}",
+                        show(&resources.limits.cpu),
+                        show(&resources.limits.memory)
+                    );
+                    if let Some(node_selector) = &kube_config.node_selector {
+                        let labels: Vec<String> = node_selector
+                            .iter()
+                            .map(|(key, value)| format!("{key}={value}"))
+                            .collect();
+                        println!("  Node Selector: {}", labels.join(", "));
+                    }
+                    if let Some(tolerations) = &kube_config.tolerations {
+                        println!("  Tolerations: {}", tolerations.len());
+                    }
+                    println!("  Retries: {}", kube_config.backoff_limit.unwrap_or(0));
+                    if let Some(pvc) = &kube_config.workspace_pvc {
+                        println!("  Workspace PVC: {pvc}");
+                    }
+                    println!(
+                        "  Job Timeout: {}s",
+                        kube_config
+                            .default_timeout_seconds
+                            .unwrap_or(claude_task::kube::DEFAULT_JOB_TIMEOUT_SECONDS)
+                    );
                 } else {
                     println!();
                     println!("Kubernetes Configuration: <not configured>");
                 }
             }
         }
-        ConfigCommands::Validate => {
-            let path = config_path
-                .cloned()
-                .unwrap_or_else(Config::default_config_path);
+        ConfigCommands::Validate { check_connectivity } => {
+            let path = Config::resolve_path(config_path)?;
 
             println!("🔍 Validating config file at: {}", path.display());
 
-            match Config::load(Some(&path)) {
-                Ok(config) => {
-                    // Additional validation beyond basic loading
-                    let expanded_worktree = Config::expand_tilde(&config.paths.worktree_base_dir);
-                    let expanded_task_home = Config::expand_tilde(&config.paths.task_base_home_dir);
+            // Additional validation beyond basic loading: the configured paths must expand
+            let loaded = Config::load(Some(&path)).and_then(|config| {
+                let expanded_worktree = Config::expand_tilde(&config.paths.worktree_base_dir)?;
+                let expanded_task_home = Config::expand_tilde(&config.paths.task_base_home_dir)?;
+                Ok((config, expanded_worktree, expanded_task_home))
+            });
 
+            match loaded {
+                Ok((config, expanded_worktree, expanded_task_home)) => {
                     println!("✅ Config file is valid!");
+                    for warning in config.image_warnings() {
+                        println!("⚠️  {warning}");
+                    }
                     println!();
                     println!("Resolved paths:");
                     println!("  Worktree Base Dir: {}", expanded_worktree.display());
                     println!("  Task Base Home Dir: {}", expanded_task_home.display());
+
+                    if check_connectivity {
+                        println!();
+                        check_runner_connectivity(&config).await;
+                    }
                 }
                 Err(e) => {
                     println!("❌ Config file validation failed:");
@@ -215,11 +397,9 @@ pub async fn handle_config_command(
             }
         }
         ConfigCommands::Runner { runner } => {
-            let path = config_path
-                .cloned()
-                .unwrap_or_else(Config::default_config_path);
+            let path = Config::resolve_path(config_path)?;
 
-            let mut config = Config::load(Some(&path))?;
+            let mut config = Config::load_for_update(Some(&path))?;
 
             // If runner not specified, show interactive selection
             let new_runner = if let Some(r) = runner {
@@ -265,11 +445,9 @@ pub async fn handle_config_command(
             }
         }
         ConfigCommands::Token => {
-            let path = config_path
-                .cloned()
-                .unwrap_or_else(Config::default_config_path);
+            let path = Config::resolve_path(config_path)?;
 
-            let mut config = Config::load(Some(&path))?;
+            let mut config = Config::load_for_update(Some(&path))?;
 
             // Prompt for token using password input
             use dialoguer::Password;
@@ -299,7 +477,191 @@ pub async fn handle_config_command(
             println!("Your claude-task setup can now use this token for authentication.");
             println!("The token will be injected as CLAUDE_CODE_OAUTH_TOKEN in containers.");
         }
+        ConfigCommands::UnsetToken => {
+            let path = Config::resolve_path(config_path)?;
+            let mut config = Config::load_for_update(Some(&path))?;
+
+            if config.claude_credentials.take().is_none() {
+                println!("ℹ️  No Claude OAuth token is stored in {}", path.display());
+            } else {
+                config.save(&path)?;
+                println!("✅ Claude OAuth token removed from {}", path.display());
+                println!("   Tasks will use credentials extracted from the keychain again.");
+                println!("   Run 'ct setup' if they haven't been set up yet.");
+            }
+            let opted_in = std::env::var(TOKEN_FROM_ENV_OPT_IN)
+                .is_ok_and(|flag| matches!(flag.trim(), "1" | "true"));
+            if opted_in
+                && std::env::var("CLAUDE_CODE_OAUTH_TOKEN").is_ok_and(|token| !token.is_empty())
+            {
+                println!(
+                    "⚠️  CLAUDE_CODE_OAUTH_TOKEN is set and {} enables it, so it still takes precedence",
+                    TOKEN_FROM_ENV_OPT_IN
+                );
+            }
+        }
+        ConfigCommands::Set { key, value } => {
+            let path = Config::resolve_path(config_path)?;
+            let config = Config::load_for_update(Some(&path))?;
+
+            let updated = config.with_value(&key, &value)?;
+            updated
+                .with_expanded_env(&|name| std::env::var(name).ok())
+                .and_then(|expanded| expanded.validate())
+                .with_context(|| format!("Invalid value for {key}"))?;
+            updated.save(&path)?;
+
+            let shown = if key.ends_with(".token") || key.ends_with(".password") {
+                "<hidden>"
+            } else {
+                value.as_str()
+            };
+            println!("✅ Set {key} = {shown}");
+        }
+        ConfigCommands::Profile { command } => {
+            let path = Config::resolve_path(config_path)?;
+            let profiles_dir = Config::profiles_dir(&path);
+            let mut config = Config::load_for_update(Some(&path))?;
+
+            match command {
+                ProfileCommands::List => {
+                    let profiles = config.available_profiles(&profiles_dir);
+                    if profiles.is_empty() {
+                        println!("No profiles found.");
+                        println!(
+                            "   Add them under \"profiles\" in the config or as {}/<name>.json",
+                            profiles_dir.display()
+                        );
+                    } else {
+                        println!("Profiles:");
+                        for (name, source) in profiles {
+                            let marker = if config.active_profile.as_deref() == Some(&name) {
+                                "*"
+                            } else {
+                                " "
+                            };
+                            println!(" {marker} {name} ({source})");
+                        }
+                    }
+                }
+                ProfileCommands::Use { name } => {
+                    // Make sure the profile exists and produces a valid config
+                    config.with_profile_from_dir(&name, &profiles_dir)?;
+                    config.active_profile = Some(name.clone());
+                    config.save(&path)?;
+                    println!("✅ Active profile set to '{name}'");
+                }
+                ProfileCommands::Clear => {
+                    config.active_profile = None;
+                    config.save(&path)?;
+                    println!("✅ Active profile cleared; the base config is used by default");
+                }
+            }
+        }
+        ConfigCommands::Migrate => {
+            let path = Config::resolve_path(config_path)?;
+
+            if !path.exists() {
+                println!("⚠️  Config file not found at: {}", path.display());
+                println!("   Run 'ct config init' to create a default config");
+                return Ok(());
+            }
+
+            let changes = Config::migrate_file(&path)?;
+            if changes.is_empty() {
+                println!(
+                    "✅ Config is already up to date (version {})",
+                    env!("CARGO_PKG_VERSION")
+                );
+            } else {
+                Config::report_migration(&path, &changes);
+            }
+        }
+        ConfigCommands::Schema { output } => match output {
+            Some(path) => {
+                write_config_schema(&path)?;
+                println!("✅ Wrote config schema to: {}", path.display());
+            }
+            None => println!("{}", serde_json::to_string_pretty(&Config::json_schema())?),
+        },
     }
 
     Ok(())
 }
+
+/// File name of the schema written next to the config by `config init`
+const CONFIG_SCHEMA_FILE: &str = "config.schema.json";
+
+fn write_config_schema(path: &std::path::Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    let schema = serde_json::to_string_pretty(&Config::json_schema())?;
+    std::fs::write(path, schema)
+        .with_context(|| format!("Failed to write config schema: {}", path.display()))
+}
+
+/// Print a pass/fail line for each connectivity check of the configured runner.
+/// Failures are reported rather than returned, so every check gets a chance to run.
+async fn check_runner_connectivity(config: &Config) {
+    println!("Connectivity ({:?}):", config.task_runner);
+
+    let mut failures = 0;
+    let mut report = |name: &str, result: Result<()>| match result {
+        Ok(()) => println!("  ✅ {name}"),
+        Err(e) => {
+            failures += 1;
+            println!("  ❌ {name}: {e:#}");
+        }
+    };
+
+    match config.task_runner {
+        ExecutionEnvironment::Docker => {
+            match crate::docker::DockerManager::new(config.docker.clone()) {
+                Ok(manager) => {
+                    let daemon = manager.ensure_daemon_available().await;
+                    let daemon_ok = daemon.is_ok();
+                    report("Docker daemon reachable", daemon);
+                    if daemon_ok {
+                        report(
+                            &format!("Image '{}' exists", config.docker.image_name),
+                            manager.check_image_exists(&config.docker.image_name).await,
+                        );
+                    }
+                }
+                Err(e) => report("Docker daemon reachable", Err(e)),
+            }
+        }
+        ExecutionEnvironment::Kubernetes => {
+            let kube_context = config
+                .kube_config
+                .as_ref()
+                .and_then(|kube| kube.context.clone())
+                .or_else(Config::get_current_kube_context);
+            report(
+                "Kubernetes client configured",
+                crate::kube::KubernetesJobRunner::new().await.map(|_| ()),
+            );
+            match kube_context {
+                Some(context) => report(
+                    &format!("Can create jobs in context '{context}'"),
+                    crate::validate_kubernetes_access(&context).await,
+                ),
+                None => report(
+                    "Can create jobs",
+                    Err(anyhow::anyhow!(
+                        "no context configured and no current kubectl context"
+                    )),
+                ),
+            }
+        }
+    }
+
+    println!();
+    if failures == 0 {
+        println!("✅ All connectivity checks passed");
+    } else {
+        println!("⚠️  {failures} connectivity check(s) failed; the next run will likely fail");
+    }
+}
@@ -0,0 +1,374 @@
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::ExecutionEnvironment;
+
+const PROMPT_PREVIEW_LEN: usize = 60;
+
+/// How a recorded task run ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+    Background,
+    Cancelled,
+}
+
+impl RunStatus {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            RunStatus::Succeeded => "✅",
+            RunStatus::Failed => "❌",
+            RunStatus::Background => "📋",
+            RunStatus::Cancelled => "🚫",
+        }
+    }
+}
+
+/// A single entry in the local run history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryRecord {
+    /// When the run started (seconds since the Unix epoch)
+    pub timestamp: u64,
+    /// When the run ended; missing in records written by older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    pub task_id: String,
+    pub runner: ExecutionEnvironment,
+    /// The full prompt, only kept with `history.storeFullPrompts`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    pub prompt_hash: String,
+    pub prompt_preview: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub status: RunStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i64>,
+    pub duration_secs: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HistoryRecord {
+    pub fn new(
+        task_id: &str,
+        prompt: &str,
+        runner: ExecutionEnvironment,
+        branch: Option<String>,
+        status: RunStatus,
+        duration: Duration,
+    ) -> Self {
+        let finished_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        let hash = format!("{:x}", Sha256::digest(prompt.as_bytes()));
+
+        let mut prompt_preview: String = prompt
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .take(PROMPT_PREVIEW_LEN)
+            .collect();
+        if prompt.chars().count() > prompt_preview.chars().count() {
+            prompt_preview.push('…');
+        }
+
+        Self {
+            timestamp: finished_at.saturating_sub(duration.as_secs()),
+            finished_at: Some(finished_at),
+            task_id: task_id.to_string(),
+            runner,
+            prompt: None,
+            prompt_hash: hash.chars().take(12).collect(),
+            prompt_preview,
+            branch,
+            exit_code: None,
+            status,
+            duration_secs: duration.as_secs_f64(),
+            log_path: None,
+            error: None,
+        }
+    }
+
+    fn formatted_time(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Criteria for `history` listings; unset fields match every record
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    /// Only runs started at or after this Unix timestamp
+    pub since: Option<u64>,
+    pub status: Option<RunStatus>,
+    /// Case-insensitive substring of the prompt
+    pub prompt_contains: Option<String>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, record: &HistoryRecord) -> bool {
+        if self.since.is_some_and(|since| record.timestamp < since) {
+            return false;
+        }
+        if self.status.is_some_and(|status| record.status != status) {
+            return false;
+        }
+        if let Some(needle) = &self.prompt_contains {
+            let prompt = record.prompt.as_deref().unwrap_or(&record.prompt_preview);
+            if !prompt.to_lowercase().contains(&needle.to_lowercase()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Parse `history --since`: a date (`2024-05-01`, local midnight) or an age
+/// relative to `now` such as `48h` or `7d`
+pub fn parse_since(input: &str, now: u64) -> Result<u64> {
+    if let Ok(date) = NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+        return Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|t| t.timestamp().max(0) as u64)
+            .ok_or_else(|| anyhow::anyhow!("Invalid local date '{input}'"));
+    }
+
+    let age = crate::worktree::parse_age(input)
+        .with_context(|| format!("Invalid --since '{input}' (use YYYY-MM-DD or an age like 7d)"))?;
+    Ok(now.saturating_sub(age.as_secs()))
+}
+
+/// `~/.claude-task/history.jsonl`, or `None` if the home directory is unknown
+pub fn default_history_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".claude-task").join("history.jsonl"))
+}
+
+fn require_history_path() -> Result<PathBuf> {
+    default_history_path()
+        .ok_or_else(|| anyhow::anyhow!("Could not determine home directory for the run history"))
+}
+
+/// Append a record to the history file, creating it if needed
+pub fn append_record(path: &Path, record: &HistoryRecord) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create history directory: {}", parent.display()))?;
+    }
+
+    let line = serde_json::to_string(record).context("Failed to serialize history record")?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open history file: {}", path.display()))?;
+    writeln!(file, "{line}")
+        .with_context(|| format!("Failed to write history file: {}", path.display()))?;
+
+    Ok(())
+}
+
+/// Load all records from the history file, oldest first. Malformed lines are skipped.
+pub fn load_records(path: &Path) -> Result<Vec<HistoryRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Print the most recent runs matching `filter`, newest first
+pub fn print_history(limit: usize, filter: &HistoryFilter, json: bool) -> Result<()> {
+    let records = load_records(&require_history_path()?)?;
+    let matching: Vec<&HistoryRecord> = records
+        .iter()
+        .rev()
+        .filter(|record| filter.matches(record))
+        .take(limit)
+        .collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&matching)?);
+        return Ok(());
+    }
+
+    if records.is_empty() {
+        println!("No task runs recorded yet.");
+        return Ok(());
+    }
+    if matching.is_empty() {
+        println!("No task runs match the given filters.");
+        return Ok(());
+    }
+
+    println!("Recent task runs:");
+    println!();
+    for record in matching {
+        println!(
+            "{} {}  {:<20} {:<10} {:>7.1}s  {}",
+            record.status.icon(),
+            record.formatted_time(),
+            record.task_id,
+            format!("{:?}", record.runner).to_lowercase(),
+            record.duration_secs,
+            record.prompt_preview
+        );
+    }
+
+    Ok(())
+}
+
+/// Print the details of the most recent run with the given task ID
+pub fn print_record_details(task_id: &str) -> Result<()> {
+    let records = load_records(&require_history_path()?)?;
+
+    let record = records
+        .iter()
+        .rev()
+        .find(|r| r.task_id == task_id)
+        .ok_or_else(|| anyhow::anyhow!("No history found for task '{}'", task_id))?;
+
+    println!("Task: {}", record.task_id);
+    println!("  Started: {}", record.formatted_time());
+    if let Some(finished_at) = record.finished_at {
+        println!("  Finished: {}", format_timestamp(finished_at));
+    }
+    println!("  Status: {} {:?}", record.status.icon(), record.status);
+    println!("  Runner: {:?}", record.runner);
+    if let Some(branch) = &record.branch {
+        println!("  Branch: {branch}");
+    }
+    if let Some(exit_code) = record.exit_code {
+        println!("  Exit code: {exit_code}");
+    }
+    println!("  Duration: {:.1}s", record.duration_secs);
+    println!(
+        "  Prompt: {}",
+        record.prompt.as_deref().unwrap_or(&record.prompt_preview)
+    );
+    println!("  Prompt hash: {}", record.prompt_hash);
+    if let Some(log_path) = &record.log_path {
+        println!("  Log: {log_path}");
+    }
+    if let Some(error) = &record.error {
+        println!("  Error: {error}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(task_id: &str, prompt: &str, status: RunStatus, timestamp: u64) -> HistoryRecord {
+        let mut record = HistoryRecord::new(
+            task_id,
+            prompt,
+            ExecutionEnvironment::Docker,
+            None,
+            status,
+            Duration::from_secs(10),
+        );
+        record.timestamp = timestamp;
+        record
+    }
+
+    #[test]
+    fn test_history_filter() {
+        let old = record("old", "Fix the login bug", RunStatus::Succeeded, 1_000);
+        let new = record("new", "Add dark mode", RunStatus::Failed, 5_000);
+
+        assert!(HistoryFilter::default().matches(&old));
+
+        let since = HistoryFilter {
+            since: Some(2_000),
+            ..Default::default()
+        };
+        assert!(!since.matches(&old));
+        assert!(since.matches(&new));
+
+        let failed = HistoryFilter {
+            status: Some(RunStatus::Failed),
+            ..Default::default()
+        };
+        assert!(!failed.matches(&old));
+        assert!(failed.matches(&new));
+
+        let login = HistoryFilter {
+            prompt_contains: Some("LOGIN".to_string()),
+            ..Default::default()
+        };
+        assert!(login.matches(&old));
+        assert!(!login.matches(&new));
+    }
+
+    #[test]
+    fn test_record_times_and_old_format() {
+        let record = record("t", "prompt", RunStatus::Succeeded, 0);
+        assert!(record.finished_at.is_some());
+
+        // Records written before prompts and end times were stored still load
+        let old: HistoryRecord = serde_json::from_str(
+            r#"{"timestamp":1,"taskId":"t","runner":"docker","promptHash":"abc","promptPreview":"p","status":"failed","durationSecs":1.0}"#,
+        )
+        .unwrap();
+        assert!(old.prompt.is_none());
+        assert!(old.finished_at.is_none());
+        assert!(HistoryFilter {
+            prompt_contains: Some("p".to_string()),
+            ..Default::default()
+        }
+        .matches(&old));
+    }
+
+    #[test]
+    fn test_record_keeps_only_a_prompt_summary() {
+        let prompt = format!("Rotate the API key sk-live-1234\n{}", "details ".repeat(20));
+        let record = record("t", &prompt, RunStatus::Succeeded, 0);
+
+        assert!(record.prompt.is_none());
+        assert!(record.prompt_preview.chars().count() <= PROMPT_PREVIEW_LEN + 1);
+        assert!(!record.prompt_preview.contains("details"));
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(!json.contains("details"));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = 10 * 24 * 60 * 60;
+        assert_eq!(parse_since("2d", now).unwrap(), now - 2 * 24 * 60 * 60);
+        assert!(parse_since("2024-05-01", now).unwrap() > 0);
+        assert!(parse_since("yesterday", now).is_err());
+    }
+}
@@ -7,7 +7,10 @@ include!("generated_constants.rs");
 use futures::{StreamExt, TryStreamExt};
 use k8s_openapi::api::{
     batch::v1::{Job, JobSpec},
-    core::v1::{Container, EnvVar, EnvVarSource, PodSpec, PodTemplateSpec, SecretKeySelector},
+    core::v1::{
+        Container, EnvVar, EnvVarSource, PodSecurityContext, PodSpec, PodTemplateSpec,
+        ResourceRequirements, SecretKeySelector, SecurityContext, Toleration,
+    },
 };
 use kube::{
     api::{Api, PostParams, WatchEvent, WatchParams},
@@ -15,26 +18,73 @@ use kube::{
 };
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+
+use crate::config::ImagePullPolicy;
+use crate::permission::PermissionMode;
 use tokio::time::{timeout, Duration};
 
+/// Init container that clones the repository into the shared workspace
+const CLONE_CONTAINER: &str = "git-clone";
+/// Main container that runs Claude
+const JOB_CONTAINER: &str = "job-runner";
+
+/// Job deadline used when neither `--timeout` nor `kubeConfig.defaultTimeoutSeconds` is set
+pub const DEFAULT_JOB_TIMEOUT_SECONDS: u64 = 600;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobConfig {
     pub name: String,
     pub namespace: String,
     pub git_repo: String,
     pub git_branch: Option<String>,
+    /// Branch, tag or commit to create `git_branch` from (default: the cloned default branch)
+    pub base_branch: Option<String>,
     pub secret_name: String,
     pub secret_key: String,
     pub claude_prompt: String,
     pub claude_permission_tool: Option<String>,
     pub claude_mcp_config: Option<String>,
     pub claude_debug: bool,
-    pub claude_skip_permissions: bool,
+    pub claude_permission_mode: PermissionMode,
     pub image: Option<String>,
     pub image_pull_secret: Option<String>,
     pub async_mode: bool,
     pub timeout_seconds: Option<u64>,
     pub oauth_token: Option<String>,
+    pub entrypoint: Option<Vec<String>>,
+    pub claude_binary: Option<String>,
+    pub resources: Option<ResourceRequirements>,
+    /// Resources for the init container that clones the repository
+    pub clone_resources: Option<ResourceRequirements>,
+    pub node_selector: Option<BTreeMap<String, String>>,
+    pub tolerations: Option<Vec<Toleration>>,
+    /// Existing PersistentVolumeClaim to mount at /workspace instead of cloning into the pod
+    pub workspace_pvc: Option<String>,
+    /// Number of times a failed pod is retried before the job is marked failed
+    pub backoff_limit: i32,
+    /// Pull policy for the job image; see [`JobConfig::resolved_image_pull_policy`]
+    pub image_pull_policy: Option<ImagePullPolicy>,
+    /// ServiceAccount for the job's pod; the namespace default when unset
+    pub service_account_name: Option<String>,
+    pub pod_security_context: Option<PodSecurityContext>,
+    pub container_security_context: Option<SecurityContext>,
+    /// Extra variables for the job container, added after claude-task's own
+    pub env: Vec<(String, String)>,
+}
+
+impl JobConfig {
+    /// The job image, or the default claude-task image when none is set
+    pub fn resolved_image(&self) -> String {
+        self.image
+            .clone()
+            .unwrap_or_else(|| DEFAULT_DOCKER_IMAGE.to_string())
+    }
+
+    /// The configured pull policy, or the default for the image's tag when unset
+    pub fn resolved_image_pull_policy(&self) -> ImagePullPolicy {
+        self.image_pull_policy
+            .unwrap_or_else(|| ImagePullPolicy::default_for_image(&self.resolved_image()))
+    }
 }
 
 #[derive(Debug)]
@@ -50,6 +100,42 @@ pub enum JobResult {
     },
 }
 
+/// A sync job's container exited with a non-zero (or unknown) status
+#[derive(Debug)]
+pub struct JobExitError {
+    pub exit_code: Option<i32>,
+}
+
+impl std::fmt::Display for JobExitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exit_code {
+            Some(code) => write!(f, "Claude task failed with exit code: {code}"),
+            None => write!(f, "Claude task failed with an unknown exit code"),
+        }
+    }
+}
+
+impl std::error::Error for JobExitError {}
+
+/// A claude-task job as shown by `ps` and `kube status`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskJob {
+    pub task_id: String,
+    pub name: String,
+    /// One of "running", "succeeded", "failed" or "pending"
+    pub status: String,
+    /// Seconds since the job started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime_seconds: Option<u64>,
+    /// Number of pods currently running
+    pub active: i32,
+    /// Number of pods that completed successfully
+    pub succeeded: i32,
+    /// Number of pods that failed
+    pub failed: i32,
+}
+
 pub struct KubernetesJobRunner {
     client: Client,
 }
@@ -92,10 +178,16 @@ impl KubernetesJobRunner {
             println!();
         }
 
+        // Without the account the job is created but its pods never are
+        if let Some(ref service_account) = config.service_account_name {
+            self.validate_service_account_exists(&config.namespace, service_account)
+                .await?;
+        }
+
         // Use the configured secret name
         let actual_secret_name = config.secret_name.clone();
 
-        let job = self.create_job_manifest(&config, has_git_secret, &actual_secret_name)?;
+        let job = Self::create_job_manifest(&config, has_git_secret, &actual_secret_name)?;
 
         // Submit the job to Kubernetes
         println!("📝 Submitting job to Kubernetes...");
@@ -129,6 +221,15 @@ impl KubernetesJobRunner {
             .ok_or_else(|| anyhow::anyhow!("Job name not found"))?;
 
         println!("📋 Job created: {job_name}");
+        crate::reporter::emit(crate::reporter::RunEvent::ContainerStarted {
+            task_id: job_name
+                .strip_prefix("claude-task-")
+                .unwrap_or(job_name)
+                .to_string(),
+            container_id: None,
+            job_name: Some(job_name.clone()),
+            namespace: Some(config.namespace.clone()),
+        });
 
         // If async mode, return immediately
         if config.async_mode {
@@ -142,6 +243,10 @@ impl KubernetesJobRunner {
                 "   kubectl logs -f job/{} -n {}",
                 job_name, config.namespace
             );
+            println!(
+                "   kubectl logs job/{} -n {} -c {CLONE_CONTAINER}  (repository clone)",
+                job_name, config.namespace
+            );
             println!();
             println!("🧹 Clean up when done:");
             println!("   kubectl delete job {} -n {}", job_name, config.namespace);
@@ -154,21 +259,48 @@ impl KubernetesJobRunner {
 
         println!("⏳ Waiting for pod to start...");
 
-        let result = self
-            .wait_for_completion(&config.namespace, job_name, config.timeout_seconds)
-            .await?;
+        // Stream the pod's logs while watching the job, so long tasks don't look hung
+        let streaming = self.stream_job_logs(
+            &config.namespace,
+            job_name,
+            config.backoff_limit,
+            config.claude_debug,
+        );
+        let waiting = self.wait_for_completion(&config.namespace, job_name, config.timeout_seconds);
+        tokio::pin!(streaming, waiting);
+
+        let (result, streamed) = tokio::select! {
+            streamed = &mut streaming => (waiting.await, Some(streamed)),
+            result = &mut waiting => {
+                // Give the log stream a moment to drain once the job has finished
+                let streamed = timeout(Duration::from_secs(10), streaming).await.ok();
+                (result, streamed)
+            }
+        };
+        let result = result?;
 
-        // Get logs from the job's pod
-        let logs = self
-            .get_job_logs(&config.namespace, job_name)
-            .await
-            .unwrap_or_else(|e| {
-                eprintln!("⚠️  Failed to get final logs: {e}");
-                Logs {
-                    stdout: String::new(),
-                    stderr: String::new(),
+        let logs = match streamed {
+            Some(Ok(logs)) => logs,
+            streamed => {
+                if let Some(Err(e)) = streamed {
+                    eprintln!("⚠️  Failed to stream logs: {e}");
                 }
-            });
+                // Fall back to fetching the complete log after the fact
+                match self.get_job_logs(&config.namespace, job_name).await {
+                    Ok(raw) => {
+                        let mut splitter = JobLogSplitter::default();
+                        for line in raw.lines() {
+                            print_job_log_line(splitter.push(line), config.claude_debug);
+                        }
+                        splitter.logs
+                    }
+                    Err(e) => {
+                        eprintln!("⚠️  Failed to get final logs: {e}");
+                        Logs::default()
+                    }
+                }
+            }
+        };
 
         // Clean up the job (optional - you might want to keep it for debugging)
         if config.claude_debug {
@@ -184,18 +316,34 @@ impl KubernetesJobRunner {
         })
     }
 
+    /// Render the Job that `run_job` would submit as YAML, without contacting the cluster.
+    /// The git secret is assumed to exist and the OAuth token is redacted.
+    pub fn render_job_manifest(config: &JobConfig) -> Result<String> {
+        let mut job = Self::create_job_manifest(config, true, &config.secret_name)?;
+
+        let containers = job
+            .spec
+            .as_mut()
+            .and_then(|spec| spec.template.spec.as_mut())
+            .map(|pod_spec| pod_spec.containers.iter_mut());
+        for container in containers.into_iter().flatten() {
+            for env in container.env.iter_mut().flatten() {
+                if env.name == "CLAUDE_CODE_OAUTH_TOKEN" && env.value.is_some() {
+                    env.value = Some("<redacted>".to_string());
+                }
+            }
+        }
+
+        serde_yaml::to_string(&job).context("Failed to serialize job manifest")
+    }
+
     /// Create the Kubernetes Job manifest
     fn create_job_manifest(
-        &self,
         config: &JobConfig,
         has_git_secret: bool,
         actual_secret_name: &str,
     ) -> Result<Job> {
-        // Use the provided image or default to GHCR
-        let image = config
-            .image
-            .clone()
-            .unwrap_or_else(|| DEFAULT_DOCKER_IMAGE.to_string());
+        let image = config.resolved_image();
 
         let git_branch = config
             .git_branch
@@ -204,41 +352,19 @@ impl KubernetesJobRunner {
             .clone();
 
         // Use the entrypoint script to ensure proper setup
-        let command = vec!["/usr/local/bin/claude-entrypoint.sh".to_string()];
-
-        // Build the claude command
-        let mut claude_cmd = vec!["claude".to_string()];
-
-        if config.claude_skip_permissions {
-            claude_cmd.push("--dangerously-skip-permissions".to_string());
-        } else if let Some(ref permission_tool) = config.claude_permission_tool {
-            claude_cmd.push("--permission-prompt-tool".to_string());
-            claude_cmd.push(permission_tool.clone());
-        }
-
-        if config.claude_debug {
-            claude_cmd.push("--debug".to_string());
-        }
-
-        if let Some(ref mcp_config) = config.claude_mcp_config {
-            claude_cmd.push("--mcp-config".to_string());
-            claude_cmd.push(format!("/workspace/{mcp_config}"));
-        }
-
-        claude_cmd.push("-p".to_string());
-        claude_cmd.push(config.claude_prompt.clone());
-
-        let _claude_command = claude_cmd.join(" ");
+        let command = config
+            .entrypoint
+            .clone()
+            .unwrap_or_else(|| vec!["/usr/local/bin/claude-entrypoint.sh".to_string()]);
 
-        let args = vec![
-            "/bin/sh".to_string(),
-            "-c".to_string(),
-            format!(
-                r#"
+        // The init container clones the repository into the shared workspace volume,
+        // so the main container's log only has Claude's run in it
+        let clone_script = format!(
+            r#"
                 set -e
                 
                 # Parse repository URL to extract owner/repo
-                REPO_URL="{}"
+                REPO_URL={}
                 
                 # Debug: Check environment
                 if [ -n "$GIT_TOKEN" ]; then
@@ -261,10 +387,14 @@ impl KubernetesJobRunner {
                         
                         if [ -n "$OWNER" ] && [ -n "$REPO" ]; then
                             echo "Debug: Parsed OWNER=${{OWNER}}, REPO=${{REPO}}"
-                            # Clone using token in URL
-                            CLONE_URL="https://${{GIT_TOKEN}}@github.com/${{OWNER}}/${{REPO}}.git"
+                            # The token is handed to git by a credential helper that reads
+                            # $GIT_TOKEN, so it never ends up in the remote URL or on disk
+                            CLONE_URL="https://github.com/${{OWNER}}/${{REPO}}.git"
+                            GIT_CREDENTIAL_HELPER='!f() {{ test "$1" = get || return 0; echo username=x-access-token; echo "password=$GIT_TOKEN"; }}; f'
+                            export GIT_CONFIG_COUNT=1
+                            export GIT_CONFIG_KEY_0=credential.helper
+                            export GIT_CONFIG_VALUE_0="$GIT_CREDENTIAL_HELPER"
                             echo "Cloning private repository: github.com/${{OWNER}}/${{REPO}}"
-                            echo "Debug: Using authenticated URL"
                         else
                             echo "Warning: Could not parse GitHub repository URL: $REPO_URL"
                             CLONE_URL="$REPO_URL"
@@ -278,57 +408,46 @@ impl KubernetesJobRunner {
                     CLONE_URL="$REPO_URL"
                 fi
                 
-                echo "Cloning repository..."
-                if ! git clone "$CLONE_URL" /workspace; then
-                    echo "Failed to clone repository. This may be because:"
-                    echo "1. The repository is private and no git credentials were provided"
-                    echo "2. The repository URL is incorrect"
-                    echo "3. Network connectivity issues"
-                    echo "4. Invalid or expired GitHub token"
-                    exit 1
-                fi
+                {}
                 
                 cd /workspace
                 
-                # Configure git for future operations
-                git config --global user.email "claude-task@example.com"
-                git config --global user.name "Claude Task"
+                # Configure git for Claude's commits; the repository config is shared
+                # with the main container through the workspace volume
+                git config user.email "claude-task@example.com"
+                git config user.name "Claude Task"
+                if [ -n "$GIT_CREDENTIAL_HELPER" ]; then
+                    # Lets Claude push from the main container, which also has $GIT_TOKEN
+                    git config credential.helper "$GIT_CREDENTIAL_HELPER"
+                fi
                 
+                {}
                 echo "Creating new branch..."
                 git checkout -b {}
                 
                 echo "Repository cloned successfully to /workspace"
-                echo "New branch created: {}"
-                echo ""
+                echo "New branch created:" {}
+                "#,
+            shell_quote(&config.git_repo),
+            workspace_checkout_script(config.workspace_pvc.is_some()),
+            base_checkout_script(config.base_branch.as_deref()),
+            shell_quote(&git_branch),
+            shell_quote(&git_branch),
+        );
+
+        let args = vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            format!(
+                r#"
+                cd /workspace
                 
                 # Run Claude with the provided prompt
                 echo "Running Claude with prompt..."
                 echo ""
                 
-                # Build Claude command
-                CLAUDE_CMD="claude"
-                
-                # Add permission/skip permissions flags
                 {}
                 
-                # Add debug flag if requested
-                {}
-                
-                # Add MCP config if provided
-                {}
-                
-                # Add the prompt
-                CLAUDE_CMD="$CLAUDE_CMD -p \"{}\""
-                
-                echo "Executing: $CLAUDE_CMD"
-                echo ""
-                
-                # Execute Claude
-                eval $CLAUDE_CMD
-                
-                # Capture exit code
-                CLAUDE_EXIT=$?
-                
                 if [ $CLAUDE_EXIT -eq 0 ]; then
                     echo ""
                     echo "✅ Claude task completed successfully"
@@ -339,30 +458,7 @@ impl KubernetesJobRunner {
                 
                 exit $CLAUDE_EXIT
                 "#,
-                config.git_repo,
-                git_branch,
-                git_branch,
-                // Permission flags
-                if config.claude_skip_permissions {
-                    r#"CLAUDE_CMD="$CLAUDE_CMD --dangerously-skip-permissions""#.to_string()
-                } else if let Some(ref tool) = config.claude_permission_tool {
-                    format!(r#"CLAUDE_CMD="$CLAUDE_CMD --permission-prompt-tool \"{tool}\"""#)
-                } else {
-                    "".to_string()
-                },
-                // Debug flag
-                if config.claude_debug {
-                    r#"CLAUDE_CMD="$CLAUDE_CMD --debug""#.to_string()
-                } else {
-                    "".to_string()
-                },
-                // MCP config
-                if let Some(ref mcp_config) = config.claude_mcp_config {
-                    format!(r#"CLAUDE_CMD="$CLAUDE_CMD --mcp-config /workspace/{mcp_config}""#)
-                } else {
-                    "".to_string()
-                },
-                config.claude_prompt
+                claude_invocation_script(config),
             ),
         ];
 
@@ -376,6 +472,13 @@ impl KubernetesJobRunner {
             value_from: None,
         });
 
+        // The prompt is passed through the environment so the shell never interprets it
+        env_vars.push(EnvVar {
+            name: "CLAUDE_PROMPT".to_string(),
+            value: Some(config.claude_prompt.clone()),
+            value_from: None,
+        });
+
         // Add debug mode if requested
         if config.claude_debug {
             env_vars.push(EnvVar {
@@ -394,8 +497,10 @@ impl KubernetesJobRunner {
             });
         }
 
+        // The clone needs the git token and the user's variables (e.g. proxy settings)
+        let mut clone_env_vars = Vec::new();
         if has_git_secret {
-            env_vars.push(EnvVar {
+            clone_env_vars.push(EnvVar {
                 name: "GIT_TOKEN".to_string(),
                 value: None,
                 value_from: Some(EnvVarSource {
@@ -408,18 +513,55 @@ impl KubernetesJobRunner {
                 }),
             });
         }
+        env_vars.extend(clone_env_vars.iter().cloned());
+
+        // User-configured variables, skipping any that would replace one set above
+        for (name, value) in &config.env {
+            let var = EnvVar {
+                name: name.clone(),
+                value: Some(value.clone()),
+                value_from: None,
+            };
+            if !clone_env_vars.iter().any(|existing| &existing.name == name) {
+                clone_env_vars.push(var.clone());
+            }
+            if !env_vars.iter().any(|existing| &existing.name == name) {
+                env_vars.push(var);
+            }
+        }
 
         // Add volume mounts for Claude credentials
         // Mount the entire secret as /home/base directory structure
-        let volume_mounts = vec![k8s_openapi::api::core::v1::VolumeMount {
-            name: "claude-home".to_string(),
-            mount_path: "/home/base".to_string(),
-            read_only: Some(true),
+        let workspace_mount = k8s_openapi::api::core::v1::VolumeMount {
+            name: "workspace".to_string(),
+            mount_path: "/workspace".to_string(),
             ..Default::default()
-        }];
+        };
+        let volume_mounts = vec![
+            k8s_openapi::api::core::v1::VolumeMount {
+                name: "claude-home".to_string(),
+                mount_path: "/home/base".to_string(),
+                read_only: Some(true),
+                ..Default::default()
+            },
+            workspace_mount.clone(),
+        ];
 
+        let image_pull_policy = config.resolved_image_pull_policy().as_str().to_string();
+        let clone_container = Container {
+            name: CLONE_CONTAINER.to_string(),
+            image_pull_policy: Some(image_pull_policy.clone()),
+            image: Some(image.clone()),
+            command: Some(vec!["/bin/sh".to_string(), "-c".to_string(), clone_script]),
+            env: Some(clone_env_vars).filter(|env| !env.is_empty()),
+            volume_mounts: Some(vec![workspace_mount]),
+            resources: config.clone_resources.clone(),
+            security_context: config.container_security_context.clone(),
+            ..Default::default()
+        };
         let container = Container {
-            name: "job-runner".to_string(),
+            name: JOB_CONTAINER.to_string(),
+            image_pull_policy: Some(image_pull_policy),
             image: Some(image),
             command: Some(command),
             args: Some(args),
@@ -430,6 +572,8 @@ impl KubernetesJobRunner {
             },
             working_dir: Some("/workspace".to_string()),
             volume_mounts: Some(volume_mounts),
+            resources: config.resources.clone(),
+            security_context: config.container_security_context.clone(),
             ..Default::default()
         };
 
@@ -444,7 +588,7 @@ impl KubernetesJobRunner {
 
         // Define volumes
         // Add single volume that recreates the /home/base directory structure
-        let volumes = vec![k8s_openapi::api::core::v1::Volume {
+        let mut volumes = vec![k8s_openapi::api::core::v1::Volume {
             name: "claude-home".to_string(),
             secret: Some(k8s_openapi::api::core::v1::SecretVolumeSource {
                 secret_name: Some("claude-credentials".to_string()),
@@ -470,12 +614,46 @@ impl KubernetesJobRunner {
             }),
             ..Default::default()
         }];
+        volumes.push(match config.workspace_pvc {
+            Some(ref claim_name) => k8s_openapi::api::core::v1::Volume {
+                name: "workspace".to_string(),
+                persistent_volume_claim: Some(
+                    k8s_openapi::api::core::v1::PersistentVolumeClaimVolumeSource {
+                        claim_name: claim_name.clone(),
+                        read_only: Some(false),
+                    },
+                ),
+                ..Default::default()
+            },
+            // Scratch space that lives as long as the pod
+            None => k8s_openapi::api::core::v1::Volume {
+                name: "workspace".to_string(),
+                empty_dir: Some(Default::default()),
+                ..Default::default()
+            },
+        });
 
         let pod_spec = PodSpec {
+            init_containers: Some(vec![clone_container]),
             containers: vec![container],
             restart_policy: Some("Never".to_string()),
+            // Let the cluster kill runaway pods, even when nobody is watching in async mode
+            active_deadline_seconds: config
+                .timeout_seconds
+                .and_then(|seconds| i64::try_from(seconds).ok()),
             image_pull_secrets,
             volumes: Some(volumes),
+            // Leave scheduling untouched unless a selector or tolerations are configured
+            node_selector: config
+                .node_selector
+                .clone()
+                .filter(|selector| !selector.is_empty()),
+            tolerations: config
+                .tolerations
+                .clone()
+                .filter(|tolerations| !tolerations.is_empty()),
+            service_account_name: config.service_account_name.clone(),
+            security_context: config.pod_security_context.clone(),
             ..Default::default()
         };
 
@@ -486,7 +664,7 @@ impl KubernetesJobRunner {
 
         let job_spec = JobSpec {
             template: pod_template,
-            backoff_limit: Some(0),                // Don't retry on failure
+            backoff_limit: Some(config.backoff_limit),
             ttl_seconds_after_finished: Some(300), // Clean up after 5 minutes
             ..Default::default()
         };
@@ -520,7 +698,8 @@ impl KubernetesJobRunner {
             .timeout(30);
 
         let mut stream = api.watch(&wp, "0").await?.boxed();
-        let timeout_duration = Duration::from_secs(timeout_seconds.unwrap_or(300));
+        let timeout_duration =
+            Duration::from_secs(timeout_seconds.unwrap_or(DEFAULT_JOB_TIMEOUT_SECONDS));
 
         let result = timeout(timeout_duration, async {
             while let Some(event) = stream.try_next().await? {
@@ -533,7 +712,9 @@ impl KubernetesJobRunner {
                                     exit_code: Some(0),
                                 });
                             }
-                            if status.failed.unwrap_or(0) > 0 {
+                            // With a backoff limit, failed pods are retried until the
+                            // job controller marks the whole job as failed
+                            if job_failed(status) {
                                 return Ok(JobStatus {
                                     completed: true,
                                     exit_code: Some(1),
@@ -558,8 +739,8 @@ impl KubernetesJobRunner {
         }
     }
 
-    /// Get logs from the job's pod
-    async fn get_job_logs(&self, namespace: &str, job_name: &str) -> Result<Logs> {
+    /// Find the pod created by a job
+    async fn find_job_pod(&self, namespace: &str, job_name: &str) -> Result<String> {
         use k8s_openapi::api::core::v1::Pod;
 
         let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
@@ -570,10 +751,11 @@ impl KubernetesJobRunner {
             .await
             .context("Failed to list pods")?;
 
+        // With retries a job can have several pods; the most recent one is the current attempt
         let job_pod = pods
             .items
             .into_iter()
-            .find(|pod| {
+            .filter(|pod| {
                 pod.metadata
                     .labels
                     .as_ref()
@@ -581,33 +763,364 @@ impl KubernetesJobRunner {
                     .map(|name| name == job_name)
                     .unwrap_or(false)
             })
+            .max_by(|a, b| {
+                a.metadata
+                    .creation_timestamp
+                    .cmp(&b.metadata.creation_timestamp)
+            })
             .ok_or_else(|| anyhow::anyhow!("Pod for job {} not found", job_name))?;
 
-        let pod_name = job_pod
+        job_pod
             .metadata
             .name
-            .ok_or_else(|| anyhow::anyhow!("Pod name not found"))?;
+            .ok_or_else(|| anyhow::anyhow!("Pod name not found"))
+    }
+
+    /// Wait until a pod of the job other than `seen` has started (or already finished)
+    /// and return its name
+    async fn wait_for_pod_start(
+        &self,
+        namespace: &str,
+        job_name: &str,
+        seen: &[String],
+    ) -> Result<String> {
+        use k8s_openapi::api::core::v1::Pod;
+        use kube::api::ListParams;
+
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+        let list_params = ListParams::default().labels(&format!("job-name={job_name}"));
+
+        loop {
+            let pods = pod_api
+                .list(&list_params)
+                .await
+                .context("Failed to list pods")?;
+
+            let started = pods.items.into_iter().find(|pod| {
+                let phase = pod
+                    .status
+                    .as_ref()
+                    .and_then(|status| status.phase.as_deref());
+                let is_new = pod
+                    .metadata
+                    .name
+                    .as_ref()
+                    .is_some_and(|name| !seen.contains(name));
+                is_new && matches!(phase, Some("Running" | "Succeeded" | "Failed"))
+            });
+            if let Some(name) = started.and_then(|pod| pod.metadata.name) {
+                return Ok(name);
+            }
+
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        }
+    }
+
+    /// Wait until a pod has finished and return whether it succeeded
+    async fn wait_for_pod_finish(&self, namespace: &str, pod_name: &str) -> Result<bool> {
+        use k8s_openapi::api::core::v1::Pod;
 
-        // Get logs
-        let logs = pod_api
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+        loop {
+            let pod = pod_api
+                .get(pod_name)
+                .await
+                .with_context(|| format!("Failed to get pod '{pod_name}'"))?;
+            match pod.status.and_then(|status| status.phase).as_deref() {
+                Some("Succeeded") => return Ok(true),
+                Some("Failed") => return Ok(false),
+                _ => tokio::time::sleep(Duration::from_secs(1)).await,
+            }
+        }
+    }
+
+    /// Follow the job's pod logs as they arrive, printing Claude's response to stdout and
+    /// everything else to stderr. When the job allows retries, the logs of each new attempt
+    /// are followed until one succeeds or the retries run out. Returns the last attempt's
+    /// logs and fails only if the stream could not be started.
+    async fn stream_job_logs(
+        &self,
+        namespace: &str,
+        job_name: &str,
+        backoff_limit: i32,
+        debug: bool,
+    ) -> Result<Logs> {
+        use futures::AsyncBufReadExt;
+        use k8s_openapi::api::core::v1::Pod;
+        use kube::api::LogParams;
+
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+        let log_params = LogParams {
+            follow: true,
+            container: Some(JOB_CONTAINER.to_string()),
+            ..Default::default()
+        };
+        let attempts = backoff_limit.max(0) as usize + 1;
+        let mut seen = Vec::new();
+
+        loop {
+            let pod_name = self.wait_for_pod_start(namespace, job_name, &seen).await?;
+
+            let mut splitter = JobLogSplitter::default();
+            if self.report_clone_logs(&pod_api, &pod_name, debug).await? {
+                // The main container never started, so there are no Claude logs to follow
+                splitter.logs.stderr = "Failed to clone repository\n".to_string();
+            } else {
+                println!("📜 Streaming logs from pod {pod_name}...");
+
+                let mut lines = pod_api
+                    .log_stream(&pod_name, &log_params)
+                    .await
+                    .context("Failed to stream pod logs")?
+                    .lines();
+
+                loop {
+                    match lines.try_next().await {
+                        Ok(Some(line)) => print_job_log_line(splitter.push(&line), debug),
+                        Ok(None) => break,
+                        Err(e) => {
+                            eprintln!("Log stream error: {e}");
+                            break;
+                        }
+                    }
+                }
+            }
+
+            seen.push(pod_name.clone());
+            if seen.len() >= attempts || self.wait_for_pod_finish(namespace, &pod_name).await? {
+                return Ok(splitter.logs);
+            }
+            println!(
+                "🔁 Attempt {} of {attempts} failed, waiting for the job to retry...",
+                seen.len()
+            );
+        }
+    }
+
+    /// Print the clone init container's log if the clone failed, or always in debug
+    /// mode. Returns whether the clone failed.
+    async fn report_clone_logs(
+        &self,
+        pod_api: &Api<k8s_openapi::api::core::v1::Pod>,
+        pod_name: &str,
+        debug: bool,
+    ) -> Result<bool> {
+        let pod = pod_api
+            .get(pod_name)
+            .await
+            .with_context(|| format!("Failed to get pod '{pod_name}'"))?;
+        let clone_failed = pod
+            .status
+            .and_then(|status| status.init_container_statuses)
+            .into_iter()
+            .flatten()
+            .filter(|status| status.name == CLONE_CONTAINER)
+            .filter_map(|status| status.state.and_then(|state| state.terminated))
+            .any(|terminated| terminated.exit_code != 0);
+
+        if clone_failed || debug {
+            let log_params = kube::api::LogParams {
+                container: Some(CLONE_CONTAINER.to_string()),
+                ..Default::default()
+            };
+            match pod_api.logs(pod_name, &log_params).await {
+                Ok(logs) => {
+                    eprintln!("📥 Clone log ({CLONE_CONTAINER} init container):");
+                    for line in logs.lines() {
+                        eprintln!("{line}");
+                    }
+                }
+                Err(e) => eprintln!("⚠️  Failed to get clone logs: {e}"),
+            }
+        }
+        if clone_failed {
+            eprintln!("❌ Cloning or checking out the repository failed in pod {pod_name}");
+        }
+        Ok(clone_failed)
+    }
+
+    /// Get the complete log of the job's pod
+    pub async fn get_job_logs(&self, namespace: &str, job_name: &str) -> Result<String> {
+        use k8s_openapi::api::core::v1::Pod;
+
+        let pod_name = self.find_job_pod(namespace, job_name).await?;
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+
+        pod_api
             .logs(&pod_name, &Default::default())
             .await
-            .context("Failed to get pod logs")?;
+            .context("Failed to get pod logs")
+    }
 
-        Ok(Logs {
-            stdout: logs,
-            stderr: String::new(), // In this simple example, we don't separate stderr
-        })
+    /// Print the logs of a job's pod, optionally following them until the pod exits.
+    /// Claude's response is delimited the same way as in foreground runs.
+    pub async fn print_job_logs(
+        &self,
+        namespace: &str,
+        job_name: &str,
+        follow: bool,
+        debug: bool,
+    ) -> Result<()> {
+        use futures::AsyncBufReadExt;
+        use k8s_openapi::api::core::v1::Pod;
+        use kube::api::LogParams;
+
+        let pod_name = self.find_job_pod(namespace, job_name).await?;
+        let pod_api: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+
+        let log_params = LogParams {
+            follow,
+            container: Some(JOB_CONTAINER.to_string()),
+            ..Default::default()
+        };
+        let mut lines = pod_api
+            .log_stream(&pod_name, &log_params)
+            .await
+            .context("Failed to stream pod logs")?
+            .lines();
+
+        let mut splitter = JobLogSplitter::default();
+        while let Some(line) = lines.try_next().await.context("Log stream error")? {
+            print_job_log_line(splitter.push(&line), debug);
+        }
+
+        Ok(())
     }
 
     /// Clean up the job and its pods
-    #[allow(dead_code)]
-    async fn cleanup_job(&self, namespace: &str, job_name: &str) -> Result<()> {
+    pub async fn cleanup_job(&self, namespace: &str, job_name: &str) -> Result<()> {
+        if !self.delete_job(namespace, job_name).await? {
+            anyhow::bail!("Job '{job_name}' not found in namespace '{namespace}'");
+        }
+        Ok(())
+    }
+
+    /// List claude-task jobs (labeled `app=job-runner`) in a namespace
+    pub async fn list_task_jobs(&self, namespace: &str) -> Result<Vec<TaskJob>> {
+        use kube::api::ListParams;
+
         let api: Api<Job> = Api::namespaced(self.client.clone(), namespace);
-        api.delete(job_name, &Default::default())
+        let jobs = api
+            .list(&ListParams::default().labels("app=job-runner"))
             .await
-            .context("Failed to delete job")?;
-        Ok(())
+            .context("Failed to list jobs")?;
+
+        let now = chrono::Utc::now();
+        let mut tasks: Vec<TaskJob> = jobs
+            .items
+            .into_iter()
+            .filter_map(|job| {
+                let name = job.metadata.name?;
+                let status = job.status.unwrap_or_default();
+                let state = if status.succeeded.unwrap_or(0) > 0 {
+                    "succeeded"
+                } else if job_failed(&status) {
+                    "failed"
+                } else if status.active.unwrap_or(0) > 0 {
+                    "running"
+                } else {
+                    "pending"
+                };
+                let uptime_seconds = status
+                    .start_time
+                    .map(|start| (now - start.0).num_seconds().max(0) as u64);
+
+                Some(TaskJob {
+                    task_id: name
+                        .strip_prefix("claude-task-")
+                        .unwrap_or(&name)
+                        .to_string(),
+                    name,
+                    status: state.to_string(),
+                    uptime_seconds,
+                    active: status.active.unwrap_or(0),
+                    succeeded: status.succeeded.unwrap_or(0),
+                    failed: status.failed.unwrap_or(0),
+                })
+            })
+            .collect();
+        tasks.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+
+        Ok(tasks)
+    }
+
+    /// The claude-task job running the given task, if there is one
+    pub async fn find_task_job(&self, namespace: &str, task_id: &str) -> Result<Option<TaskJob>> {
+        Ok(self
+            .list_task_jobs(namespace)
+            .await?
+            .into_iter()
+            .find(|job| job.task_id == task_id))
+    }
+
+    /// Delete a job and its pods. Returns false if the job doesn't exist.
+    pub async fn delete_job(&self, namespace: &str, job_name: &str) -> Result<bool> {
+        use kube::api::{DeleteParams, PropagationPolicy};
+
+        let api: Api<Job> = Api::namespaced(self.client.clone(), namespace);
+        let delete_params = DeleteParams {
+            propagation_policy: Some(PropagationPolicy::Background),
+            ..Default::default()
+        };
+
+        match api.delete(job_name, &delete_params).await {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(response)) if response.code == 404 => Ok(false),
+            Err(e) => Err(e).with_context(|| format!("Failed to delete job '{job_name}'")),
+        }
+    }
+
+    /// Delete the temporary git/Claude secrets (labeled `app=claude-task,temporary=true`)
+    /// in a namespace, keeping any that a job which hasn't finished still references.
+    /// Returns the names of the deleted secrets.
+    pub async fn delete_temporary_secrets(&self, namespace: &str) -> Result<Vec<String>> {
+        use k8s_openapi::api::core::v1::Secret;
+        use kube::api::{DeleteParams, ListParams, PropagationPolicy};
+
+        let delete_params = DeleteParams {
+            propagation_policy: Some(PropagationPolicy::Background),
+            ..Default::default()
+        };
+
+        let job_api: Api<Job> = Api::namespaced(self.client.clone(), namespace);
+        let in_use: std::collections::BTreeSet<String> = job_api
+            .list(&ListParams::default().labels("app=job-runner"))
+            .await
+            .context("Failed to list jobs")?
+            .items
+            .iter()
+            .filter(|job| {
+                let status = job.status.clone().unwrap_or_default();
+                status.succeeded.unwrap_or(0) == 0 && !job_failed(&status)
+            })
+            .flat_map(job_secret_names)
+            .collect();
+
+        let secret_api: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+        let secrets = secret_api
+            .list(&ListParams::default().labels("app=claude-task,temporary=true"))
+            .await
+            .context("Failed to list secrets")?;
+
+        let mut deleted = Vec::new();
+        for name in secrets
+            .items
+            .into_iter()
+            .filter_map(|secret| secret.metadata.name)
+        {
+            if in_use.contains(&name) {
+                println!("   • Keeping secret '{name}' (used by a job that hasn't finished)");
+                continue;
+            }
+            secret_api
+                .delete(&name, &delete_params)
+                .await
+                .with_context(|| format!("Failed to delete secret '{name}'"))?;
+            println!("   ✓ Deleted secret '{name}'");
+            deleted.push(name);
+        }
+
+        Ok(deleted)
     }
 
     /// Validate that a secret exists in the namespace
@@ -621,6 +1134,30 @@ impl KubernetesJobRunner {
             .map_err(|e| anyhow::anyhow!("Secret validation failed: {}", e))
     }
 
+    /// Validate that a ServiceAccount exists in the namespace
+    async fn validate_service_account_exists(
+        &self,
+        namespace: &str,
+        service_account: &str,
+    ) -> Result<()> {
+        use k8s_openapi::api::core::v1::ServiceAccount;
+
+        let api: Api<ServiceAccount> = Api::namespaced(self.client.clone(), namespace);
+        match api.get_opt(service_account).await {
+            Ok(Some(_)) => Ok(()),
+            Ok(None) => Err(anyhow::anyhow!(
+                "ServiceAccount '{}' not found in namespace '{}'. Create it first or unset kubeConfig.serviceAccountName",
+                service_account,
+                namespace
+            )),
+            Err(e) => Err(anyhow::anyhow!(
+                "Failed to check ServiceAccount '{}': {}",
+                service_account,
+                e
+            )),
+        }
+    }
+
     /// Create a git credentials secret
     pub async fn create_git_secret(
         &self,
@@ -673,7 +1210,6 @@ impl KubernetesJobRunner {
     }
 
     /// Delete a secret
-    #[allow(dead_code)]
     async fn delete_secret(&self, namespace: &str, secret_name: &str) -> Result<()> {
         use k8s_openapi::api::core::v1::Secret;
         use kube::api::DeleteParams;
@@ -794,6 +1330,26 @@ impl KubernetesJobRunner {
             .map_err(|e| anyhow::anyhow!("Failed to create docker registry secret: {}", e))
     }
 
+    /// Replace the Claude credentials secret with the current local files
+    pub async fn recreate_claude_credentials_secret(
+        &self,
+        namespace: &str,
+        secret_name: &str,
+        home_volume_path: &std::path::Path,
+    ) -> Result<()> {
+        if self
+            .validate_secret_exists(namespace, secret_name)
+            .await
+            .is_ok()
+        {
+            self.delete_secret(namespace, secret_name).await?;
+            println!("   ✓ Deleted outdated secret '{secret_name}'");
+        }
+
+        self.create_claude_credentials_secret(namespace, secret_name, home_volume_path)
+            .await
+    }
+
     /// Create a secret containing Claude credentials and configuration
     pub async fn create_claude_credentials_secret(
         &self,
@@ -879,42 +1435,757 @@ struct JobStatus {
     exit_code: Option<i32>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct Logs {
     stdout: String,
     stderr: String,
 }
 
+/// Quote a value for safe use as a single word in a POSIX shell script
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r#"'"'"'"#))
+}
+
+/// Shell snippet that runs Claude with the prompt from `$CLAUDE_PROMPT`, with its
+/// response bracketed by the output markers
+fn claude_invocation_script(config: &JobConfig) -> String {
+    let claude_binary = config.claude_binary.as_deref().unwrap_or("claude");
+    let mut claude_cmd = vec![claude_binary.to_string()];
+    claude_cmd.extend(
+        config
+            .claude_permission_mode
+            .claude_args(config.claude_permission_tool.as_deref()),
+    );
+    if config.claude_debug {
+        claude_cmd.push("--debug".to_string());
+    }
+    if let Some(ref mcp_config) = config.claude_mcp_config {
+        claude_cmd.push("--mcp-config".to_string());
+        claude_cmd.push(format!("/workspace/{mcp_config}"));
+    }
+
+    let quoted: Vec<String> = claude_cmd.iter().map(|arg| shell_quote(arg)).collect();
+    format!(
+        r#"
+                echo {}
+                echo ""
+                CLAUDE_EXIT=0
+                echo "{CLAUDE_OUTPUT_START}"
+                {} -p "$CLAUDE_PROMPT" || CLAUDE_EXIT=$?
+                echo "{CLAUDE_OUTPUT_END}""#,
+        shell_quote(&format!(
+            "Executing: {} -p \"$CLAUDE_PROMPT\"",
+            claude_cmd.join(" ")
+        )),
+        quoted.join(" "),
+    )
+}
+
+/// Whether the job controller has given up on a job (retries exhausted or deadline exceeded)
+fn job_failed(status: &k8s_openapi::api::batch::v1::JobStatus) -> bool {
+    status.conditions.as_ref().is_some_and(|conditions| {
+        conditions
+            .iter()
+            .any(|condition| condition.type_ == "Failed" && condition.status == "True")
+    })
+}
+
+/// Names of the secrets a job's pod template mounts or reads environment variables from
+fn job_secret_names(job: &Job) -> Vec<String> {
+    let Some(pod_spec) = job
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.template.spec.as_ref())
+    else {
+        return Vec::new();
+    };
+
+    let mut names = Vec::new();
+    for container in pod_spec
+        .containers
+        .iter()
+        .chain(pod_spec.init_containers.iter().flatten())
+    {
+        for env in container.env.iter().flatten() {
+            if let Some(name) = env
+                .value_from
+                .as_ref()
+                .and_then(|source| source.secret_key_ref.as_ref())
+                .map(|key_ref| key_ref.name.clone())
+            {
+                names.push(name);
+            }
+        }
+        for env_from in container.env_from.iter().flatten() {
+            if let Some(name) = env_from
+                .secret_ref
+                .as_ref()
+                .map(|secret_ref| secret_ref.name.clone())
+            {
+                names.push(name);
+            }
+        }
+    }
+    for volume in pod_spec.volumes.iter().flatten() {
+        if let Some(name) = volume
+            .secret
+            .as_ref()
+            .and_then(|secret| secret.secret_name.clone())
+        {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Shell snippet that leaves `$CLONE_URL` checked out in /workspace. With a persistent
+/// workspace an existing checkout is refreshed instead of cloned again; an empty volume
+/// (which may still contain e.g. `lost+found`) is cloned once via a temporary directory.
+fn workspace_checkout_script(persistent: bool) -> String {
+    let clone_failed = r#"echo "Failed to clone repository. This may be because:"
+                    echo "1. The repository is private and no git credentials were provided"
+                    echo "2. The repository URL is incorrect"
+                    echo "3. Network connectivity issues"
+                    echo "4. Invalid or expired GitHub token"
+                    exit 1"#;
+
+    if !persistent {
+        return format!(
+            r#"echo "Cloning repository..."
+                if ! git clone "$CLONE_URL" /workspace; then
+                    {clone_failed}
+                fi"#
+        );
+    }
+
+    format!(
+        r#"if [ -d /workspace/.git ]; then
+                    echo "Reusing existing checkout in persistent workspace..."
+                    cd /workspace
+                    # Also drops a token that older versions stored in the remote URL
+                    git remote set-url origin "$CLONE_URL"
+                    if ! git fetch --prune origin; then
+                        {clone_failed}
+                    fi
+                    git remote set-head origin --auto
+                    git reset --hard
+                    git clean -fd
+                    git checkout --force --detach origin/HEAD
+                else
+                    echo "Cloning repository into persistent workspace..."
+                    rm -rf /tmp/workspace-clone
+                    if ! git clone --no-checkout "$CLONE_URL" /tmp/workspace-clone; then
+                        {clone_failed}
+                    fi
+                    mv /tmp/workspace-clone/.git /workspace/.git
+                    rm -rf /tmp/workspace-clone
+                    git -C /workspace reset --hard
+                fi"#
+    )
+}
+
+/// Shell snippet that checks out `base` (a remote branch, tag or commit) before the
+/// task branch is created, failing the clone if it doesn't exist
+fn base_checkout_script(base: Option<&str>) -> String {
+    let Some(base) = base else {
+        return String::new();
+    };
+    format!(
+        r#"BASE_REF={}
+                if git rev-parse --verify --quiet "origin/$BASE_REF^{{commit}}" >/dev/null; then
+                    echo "Starting from origin/$BASE_REF"
+                    git checkout --quiet --detach "origin/$BASE_REF"
+                elif git rev-parse --verify --quiet "$BASE_REF^{{commit}}" >/dev/null; then
+                    echo "Starting from $BASE_REF"
+                    git checkout --quiet --detach "$BASE_REF"
+                else
+                    echo "Base ref '$BASE_REF' does not exist in the repository. Use a branch, tag or commit that has been pushed."
+                    exit 1
+                fi
+"#,
+        shell_quote(base)
+    )
+}
+
+/// Markers the job script prints around Claude's response, matching the Docker entrypoint
+const CLAUDE_OUTPUT_START: &str = "=== CLAUDE_OUTPUT_START ===";
+const CLAUDE_OUTPUT_END: &str = "=== CLAUDE_OUTPUT_END ===";
+
+/// A classified line of a job's log
+enum JobLogLine<'a> {
+    ResponseStart,
+    ResponseEnd,
+    Response(&'a str),
+    Setup(&'a str),
+}
+
+/// Splits a pod's log into Claude's response (stdout) and everything else (stderr),
+/// one line at a time so it works on a live stream
+#[derive(Default)]
+struct JobLogSplitter {
+    in_response: bool,
+    logs: Logs,
+}
+
+impl JobLogSplitter {
+    fn push<'a>(&mut self, line: &'a str) -> JobLogLine<'a> {
+        if line.trim() == CLAUDE_OUTPUT_START {
+            self.in_response = true;
+            JobLogLine::ResponseStart
+        } else if line.trim() == CLAUDE_OUTPUT_END {
+            self.in_response = false;
+            JobLogLine::ResponseEnd
+        } else if self.in_response {
+            self.logs.stdout.push_str(line);
+            self.logs.stdout.push('\n');
+            JobLogLine::Response(line)
+        } else {
+            self.logs.stderr.push_str(line);
+            self.logs.stderr.push('\n');
+            JobLogLine::Setup(line)
+        }
+    }
+}
+
+/// Print a job log line the way Docker task output is shown
+fn print_job_log_line(line: JobLogLine, debug: bool) {
+    match line {
+        JobLogLine::ResponseStart => {
+            if debug {
+                eprintln!("{CLAUDE_OUTPUT_START}");
+            }
+            println!();
+            println!("=============== 💬 CLAUDE'S RESPONSE BEGIN 💬 ===============");
+            println!();
+        }
+        JobLogLine::ResponseEnd => {
+            println!();
+            println!("=============== 💬 CLAUDE'S RESPONSE END 💬 ===============");
+            println!();
+            if debug {
+                eprintln!("{CLAUDE_OUTPUT_END}");
+            }
+        }
+        JobLogLine::Response(text) => {
+            println!("{text}");
+            crate::reporter::emit(crate::reporter::RunEvent::ClaudeOutput {
+                text: format!("{text}\n"),
+            });
+        }
+        JobLogLine::Setup(text) => eprintln!("{text}"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn test_job_creation() {
-        let _config = JobConfig {
+    fn split_job_logs(logs: &str) -> Logs {
+        let mut splitter = JobLogSplitter::default();
+        for line in logs.lines() {
+            splitter.push(line);
+        }
+        splitter.logs
+    }
+
+    fn test_job_config() -> JobConfig {
+        JobConfig {
             name: "test-job".to_string(),
             namespace: "default".to_string(),
             git_repo: "https://github.com/example/repo.git".to_string(),
             git_branch: Some("claude-task/test".to_string()),
+            base_branch: None,
             secret_name: "git-secret".to_string(),
             secret_key: "token".to_string(),
             claude_prompt: "Create a simple hello world script".to_string(),
             claude_permission_tool: None,
             claude_mcp_config: None,
             claude_debug: false,
-            claude_skip_permissions: true,
+            claude_permission_mode: PermissionMode::Skip,
             image: None,
             image_pull_secret: None,
             async_mode: false,
             timeout_seconds: Some(300),
             oauth_token: None,
-        };
+            entrypoint: None,
+            claude_binary: None,
+            resources: None,
+            clone_resources: None,
+            node_selector: None,
+            tolerations: None,
+            workspace_pvc: None,
+            backoff_limit: 0,
+            image_pull_policy: None,
+            service_account_name: None,
+            pod_security_context: None,
+            container_security_context: None,
+            env: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_job_creation() {
+        let _config = test_job_config();
 
         // This test would require a running Kubernetes cluster
         // let runner = KubernetesJobRunner::new().await.unwrap();
         // let result = runner.run_job(config).await.unwrap();
         // println!("Job output: {:?}", result);
     }
+
+    #[test]
+    fn test_job_manifest_scheduling() {
+        let pod_spec = |config: &JobConfig| {
+            KubernetesJobRunner::create_job_manifest(config, false, "git-secret")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+        };
+
+        let mut config = test_job_config();
+        config.node_selector = Some(BTreeMap::new());
+        let spec = pod_spec(&config);
+        assert!(spec.node_selector.is_none());
+        assert!(spec.tolerations.is_none());
+        assert_eq!(spec.active_deadline_seconds, Some(300));
+
+        config.node_selector = Some(BTreeMap::from([(
+            "cloud.google.com/gke-spot".to_string(),
+            "true".to_string(),
+        )]));
+        config.tolerations = Some(vec![Toleration {
+            key: Some("dedicated".to_string()),
+            operator: Some("Equal".to_string()),
+            value: Some("claude-task".to_string()),
+            effect: Some("NoSchedule".to_string()),
+            ..Default::default()
+        }]);
+        let spec = pod_spec(&config);
+        assert_eq!(
+            spec.node_selector.unwrap()["cloud.google.com/gke-spot"],
+            "true"
+        );
+        let tolerations = spec.tolerations.unwrap();
+        assert_eq!(tolerations.len(), 1);
+        assert_eq!(tolerations[0].key.as_deref(), Some("dedicated"));
+        assert_eq!(tolerations[0].effect.as_deref(), Some("NoSchedule"));
+    }
+
+    #[test]
+    fn test_render_job_manifest() {
+        let mut config = test_job_config();
+        config.oauth_token = Some("sk-ant-oat01-secret".to_string());
+
+        let yaml = KubernetesJobRunner::render_job_manifest(&config).unwrap();
+        assert!(!yaml.contains("sk-ant-oat01-secret"));
+        assert!(yaml.contains("<redacted>"));
+
+        let job: Job = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(job.metadata.name.as_deref(), Some("test-job"));
+        let container = &job.spec.unwrap().template.spec.unwrap().containers[0];
+        assert!(container
+            .env
+            .as_ref()
+            .unwrap()
+            .iter()
+            .any(|env| env.name == "GIT_TOKEN"));
+    }
+
+    #[test]
+    fn test_job_manifest_backoff_limit() {
+        let backoff_limit = |config: &JobConfig| {
+            KubernetesJobRunner::create_job_manifest(config, false, "git-secret")
+                .unwrap()
+                .spec
+                .unwrap()
+                .backoff_limit
+        };
+
+        let mut config = test_job_config();
+        assert_eq!(backoff_limit(&config), Some(0));
+
+        config.backoff_limit = 3;
+        assert_eq!(backoff_limit(&config), Some(3));
+    }
+
+    #[test]
+    fn test_job_manifest_image_pull_policy() {
+        let pull_policy = |config: &JobConfig| {
+            KubernetesJobRunner::create_job_manifest(config, false, "git-secret")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+                .containers[0]
+                .image_pull_policy
+                .clone()
+        };
+
+        let mut config = test_job_config();
+        config.image = Some("ghcr.io/example/claude-task:latest".to_string());
+        assert_eq!(pull_policy(&config).as_deref(), Some("Always"));
+
+        config.image = Some("ghcr.io/example/claude-task:1.2.0".to_string());
+        assert_eq!(pull_policy(&config).as_deref(), Some("IfNotPresent"));
+
+        config.image = Some("localhost:5000/claude-task".to_string());
+        assert_eq!(pull_policy(&config).as_deref(), Some("Always"));
+
+        config.image_pull_policy = Some(ImagePullPolicy::Never);
+        assert_eq!(pull_policy(&config).as_deref(), Some("Never"));
+    }
+
+    #[test]
+    fn test_job_manifest_service_account() {
+        let service_account = |config: &JobConfig| {
+            KubernetesJobRunner::create_job_manifest(config, false, "git-secret")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+                .service_account_name
+        };
+
+        let mut config = test_job_config();
+        assert_eq!(service_account(&config), None);
+
+        config.service_account_name = Some("claude-task-runner".to_string());
+        assert_eq!(
+            service_account(&config).as_deref(),
+            Some("claude-task-runner")
+        );
+    }
+
+    #[test]
+    fn test_job_manifest_custom_env() {
+        let mut config = test_job_config();
+        config.oauth_token = Some("oauth".to_string());
+        config.env = vec![
+            ("HTTPS_PROXY".to_string(), "http://proxy:3128".to_string()),
+            // Never replaces a variable claude-task sets
+            (
+                "CLAUDE_CODE_OAUTH_TOKEN".to_string(),
+                "clobbered".to_string(),
+            ),
+        ];
+
+        let env = KubernetesJobRunner::create_job_manifest(&config, true, "git-secret")
+            .unwrap()
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap()
+            .containers[0]
+            .env
+            .clone()
+            .unwrap();
+        let value = |name: &str| {
+            env.iter()
+                .filter(|var| var.name == name)
+                .map(|var| var.value.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            value("HTTPS_PROXY"),
+            vec![Some("http://proxy:3128".to_string())]
+        );
+        assert_eq!(
+            value("CLAUDE_CODE_OAUTH_TOKEN"),
+            vec![Some("oauth".to_string())]
+        );
+        assert_eq!(value("GIT_TOKEN").len(), 1);
+    }
+
+    #[test]
+    fn test_job_manifest_security_context() {
+        let security = crate::config::KubeSecurityContext::default();
+        let mut config = test_job_config();
+        config.pod_security_context = Some(security.pod_security_context());
+        config.container_security_context = Some(security.container_security_context());
+
+        let pod_spec = KubernetesJobRunner::create_job_manifest(&config, false, "git-secret")
+            .unwrap()
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+        let pod_security = pod_spec.security_context.unwrap();
+        assert_eq!(pod_security.run_as_non_root, Some(true));
+        assert_eq!(pod_security.run_as_user, Some(1000));
+        let container_security = pod_spec.containers[0].security_context.clone().unwrap();
+        assert_eq!(container_security.allow_privilege_escalation, Some(false));
+        assert_eq!(
+            container_security.capabilities.unwrap().drop,
+            Some(vec!["ALL".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_job_failed_condition() {
+        use k8s_openapi::api::batch::v1::{JobCondition, JobStatus as K8sJobStatus};
+
+        // A failed pod that will still be retried doesn't fail the job
+        let retrying = K8sJobStatus {
+            failed: Some(1),
+            active: Some(1),
+            ..Default::default()
+        };
+        assert!(!job_failed(&retrying));
+
+        let exhausted = K8sJobStatus {
+            failed: Some(3),
+            conditions: Some(vec![JobCondition {
+                type_: "Failed".to_string(),
+                status: "True".to_string(),
+                reason: Some("BackoffLimitExceeded".to_string()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        assert!(job_failed(&exhausted));
+    }
+
+    #[test]
+    fn test_job_secret_names() {
+        let job = KubernetesJobRunner::create_job_manifest(&test_job_config(), true, "git-secret")
+            .unwrap();
+        let names = job_secret_names(&job);
+        assert!(names.contains(&"git-secret".to_string()));
+        assert!(names.contains(&"claude-credentials".to_string()));
+
+        assert!(job_secret_names(&Job::default()).is_empty());
+    }
+
+    #[test]
+    fn test_job_manifest_clones_in_init_container() {
+        let mut config = test_job_config();
+        config.env = vec![("HTTPS_PROXY".to_string(), "http://proxy:3128".to_string())];
+        config.resources = Some(crate::config::KubeResources::default().requirements());
+        config.clone_resources = Some(crate::config::KubeResources::clone_default().requirements());
+        let spec = KubernetesJobRunner::create_job_manifest(&config, true, "git-secret")
+            .unwrap()
+            .spec
+            .unwrap()
+            .template
+            .spec
+            .unwrap();
+
+        let init = &spec.init_containers.as_ref().unwrap()[0];
+        assert_eq!(init.name, CLONE_CONTAINER);
+        let clone_script = &init.command.as_ref().unwrap()[2];
+        assert!(clone_script.contains("git clone"));
+        assert!(clone_script.contains("git checkout -b 'claude-task/test'"));
+        let init_env: Vec<&str> = init
+            .env
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|var| var.name.as_str())
+            .collect();
+        assert_eq!(init_env, vec!["GIT_TOKEN", "HTTPS_PROXY"]);
+        // The prompt and Claude credentials stay out of the clone
+        assert!(!init_env.contains(&"CLAUDE_PROMPT"));
+
+        let main = &spec.containers[0];
+        assert_eq!(main.name, JOB_CONTAINER);
+        // The clone gets its own, smaller resources rather than reserving the job's twice
+        assert_eq!(init.resources, config.clone_resources);
+        assert_eq!(main.resources, config.resources);
+        assert_ne!(init.resources, main.resources);
+        let run_script = &main.args.as_ref().unwrap()[2];
+        assert!(!run_script.contains("git clone"));
+        assert!(run_script.contains("cd /workspace"));
+        for container in [init, main] {
+            assert!(container
+                .volume_mounts
+                .as_ref()
+                .unwrap()
+                .iter()
+                .any(|m| m.name == "workspace" && m.mount_path == "/workspace"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_base_checkout_script() {
+        assert_eq!(base_checkout_script(None), "");
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let repo = temp.path();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .args(args)
+                .current_dir(repo)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?} failed");
+        };
+        git(&["init", "-q"]);
+        git(&[
+            "-c",
+            "user.name=t",
+            "-c",
+            "user.email=t@t",
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            "init",
+        ]);
+        git(&["tag", "v1"]);
+
+        let run = |base: &str| {
+            std::process::Command::new("sh")
+                .arg("-c")
+                .arg(base_checkout_script(Some(base)))
+                .current_dir(repo)
+                .output()
+                .unwrap()
+        };
+        assert!(run("v1").status.success());
+        let missing = run("no-such-branch");
+        assert!(!missing.status.success());
+        assert!(String::from_utf8_lossy(&missing.stdout).contains("does not exist"));
+    }
+
+    #[test]
+    fn test_job_manifest_workspace_pvc() {
+        let pod_spec = |config: &JobConfig| {
+            KubernetesJobRunner::create_job_manifest(config, false, "git-secret")
+                .unwrap()
+                .spec
+                .unwrap()
+                .template
+                .spec
+                .unwrap()
+        };
+
+        // By default the init container clones into an emptyDir shared with the main container
+        let mut config = test_job_config();
+        let spec = pod_spec(&config);
+        let volume = spec
+            .volumes
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|v| v.name == "workspace")
+            .unwrap();
+        assert!(volume.empty_dir.is_some());
+        let clone_script = |spec: &PodSpec| {
+            spec.init_containers.as_ref().unwrap()[0]
+                .command
+                .as_ref()
+                .unwrap()[2]
+                .clone()
+        };
+        let script = clone_script(&spec);
+        assert!(script.contains(r#"git clone "$CLONE_URL" /workspace"#));
+        assert!(!script.contains("/workspace/.git"));
+        // The token is only read from the environment by the credential helper
+        assert!(!script.contains("GIT_TOKEN}@"));
+        assert!(script.contains(r#"echo "password=$GIT_TOKEN""#));
+        assert!(script.contains(r#"git config credential.helper "$GIT_CREDENTIAL_HELPER""#));
+
+        config.workspace_pvc = Some("repo-cache".to_string());
+        let spec = pod_spec(&config);
+        let volume = spec
+            .volumes
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|v| v.name == "workspace")
+            .unwrap();
+        assert_eq!(
+            volume.persistent_volume_claim.as_ref().unwrap().claim_name,
+            "repo-cache"
+        );
+        let container = &spec.containers[0];
+        let mount = container
+            .volume_mounts
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|m| m.name == "workspace")
+            .unwrap();
+        assert_eq!(mount.mount_path, "/workspace");
+        let script = clone_script(&spec);
+        assert!(script.contains("if [ -d /workspace/.git ]; then"));
+        assert!(!script.contains(r#"git clone "$CLONE_URL" /workspace"#));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prompt_is_passed_verbatim() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let marker = temp.path().join("pwned");
+        let prompt = format!(
+            r#"Fix "quoted" and 'single' text; $(touch {0}) `touch {0}` $HOME \ done"#,
+            marker.display()
+        );
+
+        // A fake claude binary that prints each argument on its own line
+        let fake_claude = temp.path().join("claude");
+        std::fs::write(
+            &fake_claude,
+            "#!/bin/sh\nfor arg in \"$@\"; do printf '%s\\n' \"$arg\"; done\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&fake_claude, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut config = test_job_config();
+        config.claude_prompt = prompt.clone();
+        config.claude_binary = Some(fake_claude.to_string_lossy().to_string());
+
+        let job = KubernetesJobRunner::create_job_manifest(&config, false, "git-secret").unwrap();
+        let container = &job.spec.unwrap().template.spec.unwrap().containers[0];
+        let script = &container.args.as_ref().unwrap()[2];
+        assert!(!script.contains(&prompt));
+        let env = container.env.as_ref().unwrap();
+        let prompt_var = env.iter().find(|var| var.name == "CLAUDE_PROMPT").unwrap();
+        assert_eq!(prompt_var.value.as_deref(), Some(prompt.as_str()));
+
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(claude_invocation_script(&config))
+            .env("CLAUDE_PROMPT", &prompt)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let logs = split_job_logs(&String::from_utf8_lossy(&output.stdout));
+        let args: Vec<&str> = logs.stdout.lines().collect();
+        assert_eq!(args.last(), Some(&prompt.as_str()));
+        assert_eq!(args[args.len() - 2], "-p");
+        assert!(!marker.exists());
+    }
+
+    #[test]
+    fn test_split_job_logs() {
+        let logs = "Cloning repository...\nCreating new branch...\n=== CLAUDE_OUTPUT_START ===\nI fixed the bug.\nAll tests pass.\n=== CLAUDE_OUTPUT_END ===\n✅ Claude task completed successfully\n";
+
+        let split = split_job_logs(logs);
+        assert_eq!(split.stdout, "I fixed the bug.\nAll tests pass.\n");
+        assert_eq!(
+            split.stderr,
+            "Cloning repository...\nCreating new branch...\n✅ Claude task completed successfully\n"
+        );
+
+        // Without markers (e.g. the clone failed) everything is setup output
+        let split = split_job_logs("Failed to clone repository\n");
+        assert!(split.stdout.is_empty());
+        assert_eq!(split.stderr, "Failed to clone repository\n");
+    }
 }
 
 pub async fn run_kubernetes_job(config: JobConfig) -> Result<JobResult> {
@@ -4,4 +4,5 @@ pub mod credential_sync;
 pub mod docker;
 pub mod kube;
 pub mod permission;
+pub mod reporter;
 pub mod worktree;
@@ -9,27 +9,39 @@ include!(concat!(env!("OUT_DIR"), "/mcp_help.rs"));
 include!("generated_constants.rs");
 
 mod assets;
+mod batch;
 mod config;
 mod credential_sync;
 mod credentials;
 mod docker;
+mod doctor;
 mod handle_config;
+mod history;
 mod mcp;
-pub mod permission;
+mod notify;
+mod timing;
 
 use claude_task::kube;
+use claude_task::permission;
+use claude_task::reporter;
 use claude_task::worktree;
 use config::ExecutionEnvironment;
-use permission::ApprovalToolPermission;
+use permission::{ApprovalToolPermission, PermissionMode};
 use std::process::Command;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TaskRunConfig<'a> {
     prompt: &'a str,
     task_id: Option<String>,
     build: bool,
+    no_cache: bool,
+    pull: bool,
     workspace_dir: Option<Option<String>>,
+    base_ref: Option<String>,
+    carry_changes: worktree::CarryChanges,
+    recurse_submodules: bool,
     approval_tool_permission: Option<String>,
+    permission_mode: Option<PermissionMode>,
     debug: bool,
     mcp_config: Option<String>,
     skip_confirmation: bool,
@@ -49,12 +61,69 @@ struct TaskRunConfig<'a> {
     git_secret_name: Option<String>,
     git_secret_key: Option<String>,
     claude_credentials: &'a Option<config::ClaudeCredentials>,
+    id_generator: &'a Option<config::IdGeneratorConfig>,
+    log_file: Option<String>,
+    timeout_seconds: Option<u64>,
+    dry_run: bool,
+    create_pr: bool,
+    ssh_agent: bool,
+    claude_json: bool,
+    notify_webhook: Option<String>,
+    history: &'a config::HistoryConfig,
+    /// `--config-path`, where settings confirmed during the run are saved
+    config_path: Option<&'a PathBuf>,
+}
+
+/// Resolve the prompt for `run` from the positional argument or `--prompt-file`,
+/// where "-" in either place reads it from `stdin`
+fn resolve_prompt(
+    prompt: Option<String>,
+    prompt_file: Option<PathBuf>,
+    mut stdin: impl std::io::Read,
+) -> Result<String> {
+    let read_stdin = |stdin: &mut dyn std::io::Read| -> Result<String> {
+        let mut prompt = String::new();
+        stdin
+            .read_to_string(&mut prompt)
+            .context("Failed to read the prompt from stdin")?;
+        Ok(prompt)
+    };
+
+    // Files and piped input usually end with a newline that isn't part of the prompt
+    let prompt = match (prompt, prompt_file) {
+        (Some(prompt), _) if prompt == "-" => read_stdin(&mut stdin)?.trim().to_string(),
+        (Some(prompt), _) => prompt,
+        (None, Some(path)) if path.as_os_str() == "-" => {
+            read_stdin(&mut stdin)?.trim().to_string()
+        }
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read prompt file: {}", path.display()))?
+            .trim()
+            .to_string(),
+        (None, None) => anyhow::bail!(
+            "No prompt given. Pass it as an argument, with --prompt-file <PATH>, or pipe it in with '-'"
+        ),
+    };
+
+    if prompt.trim().is_empty() {
+        anyhow::bail!("The prompt is empty");
+    }
+    Ok(prompt)
+}
+
+/// Generate a task ID using the configured word lists, if any
+fn generate_task_id(id_generator: &Option<config::IdGeneratorConfig>) -> String {
+    match id_generator {
+        Some(id_generator) => id_generator.generate_id(),
+        None => worktree::generate_short_id(),
+    }
 }
 
 use config::Config;
 use credentials::{setup_credentials_and_config, setup_credentials_and_config_with_cache};
-use docker::{ClaudeTaskConfig, DockerManager};
+use docker::{ClaudeTaskConfig, DockerManager, StopOutcome};
 use handle_config::handle_config_command;
+use history::RunStatus;
 
 #[derive(Subcommand)]
 enum WorktreeCommands {
@@ -63,28 +132,87 @@ enum WorktreeCommands {
     Create {
         /// Task ID for the worktree
         task_id: String,
+        /// Branch to use instead of <branch-prefix><task-id>. Checked out if it already exists
+        #[arg(long, value_name = "NAME")]
+        branch: Option<String>,
+        /// Branch, tag or commit to start the new branch from (default: current HEAD)
+        #[arg(long = "from", value_name = "REF")]
+        base_ref: Option<String>,
+        /// Initialize git submodules in the new worktree (default: worktree.recurseSubmodules)
+        #[arg(long)]
+        recurse_submodules: bool,
     },
     /// List current git worktrees
     #[command(visible_alias = "l")]
-    List,
+    List {
+        /// Print the worktrees and their status as JSON
+        #[arg(long)]
+        json: bool,
+        /// Show how much disk space each worktree uses (slow for large trees)
+        #[arg(long)]
+        with_size: bool,
+        /// Only show worktrees in this state
+        #[arg(long, value_enum)]
+        only: Option<worktree::WorktreeFilter>,
+    },
     /// Remove and clean up a worktree
     #[command(visible_alias = "rm")]
     Remove {
         /// Task ID to remove (will be prefixed with branch_prefix)
         task_id: String,
+        /// Save the worktree's files (including uncommitted changes) to a .tar.gz in this directory before removing it
+        #[arg(long, value_name = "DIR")]
+        archive: Option<String>,
     },
     /// Open a worktree in your IDE
     #[command(visible_alias = "o")]
     Open,
+    /// Show a task's changes against the base branch
+    #[command(visible_alias = "d")]
+    Diff {
+        /// Task ID to diff (will be prefixed with branch_prefix)
+        task_id: String,
+        /// Print the diff directly instead of using git's pager
+        #[arg(long)]
+        no_pager: bool,
+    },
+    /// Lock a worktree so cleanup skips it
+    Lock {
+        /// Task ID to lock (will be prefixed with branch_prefix)
+        task_id: String,
+        /// Why the worktree is locked (shown when cleanup skips it)
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Unlock a previously locked worktree
+    Unlock {
+        /// Task ID to unlock (will be prefixed with branch_prefix)
+        task_id: String,
+    },
+    /// Rename a task's branch and worktree directory
+    #[command(visible_alias = "mv")]
+    Rename {
+        /// Current task ID (will be prefixed with branch_prefix)
+        task_id: String,
+        /// New task ID
+        new_id: String,
+    },
     /// Clean up all claude-task git worktrees
     #[command(visible_alias = "cl")]
     Clean {
         /// Skip confirmation prompt
         #[arg(long, short = 'y')]
         yes: bool,
-        /// Force removal of worktrees even if they have uncommitted changes
+        /// Force removal of worktrees even if they have uncommitted changes or are locked
         #[arg(long, short = 'f')]
         force: bool,
+        /// Also remove worktrees whose merge was only detected heuristically (e.g. squash merges)
+        #[arg(long)]
+        include_heuristic: bool,
+        /// Only remove worktrees inactive for longer than this (e.g. 7d, 48h). Worktrees
+        /// whose last activity can't be determined are kept
+        #[arg(long, value_name = "AGE", value_parser = worktree::parse_age)]
+        older_than: Option<std::time::Duration>,
     },
 }
 
@@ -102,7 +230,38 @@ enum DockerCommands {
     List,
     /// Clean up all shared Docker volumes
     #[command(visible_alias = "c")]
-    Clean,
+    Clean {
+        /// Skip confirmation prompt
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum KubeCommands {
+    /// List claude-task jobs with their pod counts and age
+    #[command(visible_alias = "st")]
+    Status {
+        /// Namespace to list jobs in (defaults to kubeConfig.namespace)
+        #[arg(long, short = 'n')]
+        namespace: Option<String>,
+        /// Print the jobs as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete finished claude-task jobs and their pods
+    #[command(visible_alias = "c")]
+    Clean {
+        /// Namespace to clean (defaults to kubeConfig.namespace)
+        #[arg(long, short = 'n')]
+        namespace: Option<String>,
+        /// Also delete the temporary git/Claude secrets created for jobs
+        #[arg(long)]
+        all: bool,
+        /// Skip confirmation prompt
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
 }
 
 #[derive(Subcommand, Clone)]
@@ -113,6 +272,12 @@ enum ConfigCommands {
         /// Force overwrite if config already exists
         #[arg(long, short)]
         force: bool,
+        /// Don't write config.schema.json or reference it from the new config
+        #[arg(long)]
+        no_schema: bool,
+        /// File format for the new config (default: json, or the --config-path extension)
+        #[arg(long, value_enum)]
+        format: Option<config::ConfigFormat>,
     },
     /// Open config file in editor
     #[command(visible_alias = "e")]
@@ -123,10 +288,20 @@ enum ConfigCommands {
         /// Show config in JSON format (default: pretty print)
         #[arg(long)]
         json: bool,
+        /// Show secrets (OAuth token, registry password, webhook URL, env values) unmasked
+        #[arg(long)]
+        reveal: bool,
+        /// Only show fields that differ from the defaults
+        #[arg(long)]
+        diff: bool,
     },
     /// Check config file validity
     #[command(visible_alias = "v")]
-    Validate,
+    Validate {
+        /// Also check that the configured Docker daemon or Kubernetes cluster is reachable
+        #[arg(long)]
+        check_connectivity: bool,
+    },
     /// Set the task runner (options: docker or kubernetes)
     #[command(visible_alias = "r")]
     Runner {
@@ -137,6 +312,46 @@ enum ConfigCommands {
     /// Set Claude OAuth token for authentication
     #[command(visible_alias = "t")]
     Token,
+    /// Remove the stored Claude OAuth token and go back to keychain credentials
+    UnsetToken,
+    /// Set one config value, e.g. `config set kubeConfig.namespace dev`
+    Set {
+        /// Dotted key, e.g. taskRunner, docker.imageName or kubeConfig.namespace
+        key: String,
+        /// New value. Numbers and true/false are parsed for fields of those types, `null`
+        /// clears an optional field and lists or maps are given as JSON
+        value: String,
+    },
+    /// Manage named config profiles
+    #[command(visible_alias = "p")]
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommands,
+    },
+    /// Upgrade a config file written by an older version (keeps a .bak copy)
+    #[command(visible_alias = "m")]
+    Migrate,
+    /// Print the JSON Schema for the config file, for editor autocomplete and validation
+    Schema {
+        /// Write the schema to this file instead of stdout
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum ProfileCommands {
+    /// List profiles from the config file and the profiles directory
+    #[command(visible_alias = "l")]
+    List,
+    /// Make a profile the default for commands run without --profile
+    #[command(visible_alias = "u")]
+    Use {
+        /// Profile name
+        name: String,
+    },
+    /// Go back to using the base config when --profile isn't given
+    Clear,
 }
 
 #[derive(Subcommand)]
@@ -173,15 +388,41 @@ struct Cli {
     #[arg(long, global = true)]
     require_ht_mcp: bool,
 
-    /// Path to the configuration file (defaults to ~/.claude-task/config.json)
+    /// Path to the configuration file, JSON or YAML by extension (defaults to
+    /// $CLAUDE_TASK_CONFIG, then ~/.claude-task/config.json, or config.yaml / config.yml
+    /// if that exists instead)
     #[arg(long, global = true, value_name = "PATH", help = "Path to config file")]
     config_path: Option<PathBuf>,
 
+    /// Skip the Touch ID/Face ID prompt before reading credentials from the keychain
+    #[arg(long, global = true)]
+    no_biometrics: bool,
+
+    /// Ignore the repository's .claude-task.json
+    #[arg(long, global = true)]
+    no_repo_config: bool,
+
+    /// Named profile to merge over the base config, from the config file's `profiles` or
+    /// profiles/<NAME>.json next to it (default: the profile set with `config profile use`)
+    #[arg(
+        long,
+        global = true,
+        visible_alias = "config-profile",
+        value_name = "NAME"
+    )]
+    profile: Option<String>,
+
+    /// Progress format for `run`, so it can be given before the subcommand
+    /// (`ct --output json run ...`); `run --output` takes precedence
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    output: Option<reporter::OutputFormat>,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Setup claude-task with your current environment
     #[command(visible_alias = "s")]
@@ -201,23 +442,84 @@ enum Commands {
         #[command(subcommand)]
         command: DockerCommands,
     },
+    /// Kubernetes job management commands
+    #[command(visible_alias = "k")]
+    Kube {
+        #[command(subcommand)]
+        command: KubeCommands,
+    },
     /// Run a Claude task in a local docker container or Kubernetes
     #[command(visible_alias = "r")]
     Run {
-        /// The prompt to pass to Claude
-        prompt: String,
+        /// The prompt to pass to Claude, or "-" to read it from stdin
+        #[arg(required_unless_present_any = ["from_file", "prompt_file"])]
+        prompt: Option<String>,
+        /// Read the prompt from a file ("-" for stdin) instead of the command line
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["prompt", "from_file"])]
+        prompt_file: Option<PathBuf>,
         /// Optional task ID (generates short ID if not provided)
         #[arg(short = 't', long)]
         task_id: Option<String>,
+        /// Run every task in a JSONL file, one task per line (e.g. {"prompt": "...", "taskId": "..."})
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["prompt", "task_id"])]
+        from_file: Option<PathBuf>,
+        /// Number of tasks from --from-file to run at the same time
+        #[arg(long, default_value_t = 1, requires = "from_file", value_parser = clap::value_parser!(u16).range(1..))]
+        concurrency: u16,
         /// Build the image before running (default: false)
         #[arg(long)]
         build: bool,
+        /// Build the image without using cached layers (implies --build)
+        #[arg(long)]
+        no_cache: bool,
+        /// Pull a newer base image while building (implies --build)
+        #[arg(long)]
+        pull: bool,
+        /// Bind-mount a host path into the task container as SOURCE:TARGET[:ro]; repeatable
+        /// (added to docker.extraMounts)
+        #[arg(long = "mount", value_name = "SOURCE:TARGET[:ro]", value_parser = config::parse_mount_spec)]
+        mounts: Vec<config::ExtraMount>,
+        /// Set an environment variable in the task container as KEY=VALUE; repeatable
+        /// (added to docker.environmentVariables or kubeConfig.environmentVariables)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = config::parse_env_var)]
+        env: Vec<(String, String)>,
+        /// Forward the host's SSH agent ($SSH_AUTH_SOCK) into the container so git can push.
+        /// Anything in the container can then use your loaded keys (Docker only)
+        #[arg(long)]
+        ssh_agent: bool,
+        /// Network for the task container, e.g. a user-defined network shared with an MCP
+        /// approval server (overrides docker.networkMode)
+        #[arg(long, value_name = "NETWORK")]
+        network: Option<String>,
+        /// Platform to build and run the image for, e.g. linux/amd64 to match cluster nodes
+        /// (overrides docker.platform)
+        #[arg(long, value_name = "OS/ARCH")]
+        platform: Option<String>,
         /// Custom workspace directory to mount (overrides worktree creation). If provided without value, uses current directory
         #[arg(long, value_name = "DIR")]
         workspace_dir: Option<Option<String>>,
+        /// Run against the current directory without creating a worktree
+        #[arg(long, visible_alias = "here", conflicts_with = "workspace_dir")]
+        no_worktree: bool,
+        /// Branch, tag or commit to start the task's worktree from (default: current HEAD). For
+        /// Kubernetes jobs it must exist on the remote (default: the remote's default branch)
+        #[arg(long = "from", value_name = "REF", conflicts_with_all = ["workspace_dir", "no_worktree"])]
+        base_ref: Option<String>,
+        /// Copy uncommitted changes to tracked files from the current repo into the task's worktree
+        #[arg(long, conflicts_with_all = ["workspace_dir", "no_worktree"])]
+        carry_changes: bool,
+        /// With --carry-changes, also copy untracked files
+        #[arg(long, requires = "carry_changes")]
+        carry_untracked: bool,
+        /// Initialize git submodules in the task's worktree (default: worktree.recurseSubmodules)
+        #[arg(long, conflicts_with_all = ["workspace_dir", "no_worktree"])]
+        recurse_submodules: bool,
         /// Claude Code permission statement to pass for approval tool. Example: "mcp__approval_server__tool_name"
         #[arg(short = 'a', long, value_name = "PERMISSION_STATEMENT")]
         approval_tool_permission: Option<String>,
+        /// How Claude handles tool permissions (approve, skip, plan, ask). Defaults to approve when an approval tool is given, otherwise skip
+        #[arg(long, value_enum)]
+        permission_mode: Option<PermissionMode>,
         /// Optional MCP config file path that will be mounted to the container and passed to Claude
         #[arg(short = 'c', long, value_name = "MCP_CONFIG_FILEPATH")]
         mcp_config: Option<String>,
@@ -236,6 +538,34 @@ enum Commands {
         /// Run task in background mode (returns immediately with container ID)
         #[arg(short = 'b', long = "background")]
         async_mode: bool,
+        /// Append a timestamped copy of the task's output to this file; {taskId} is replaced with the task ID (default: globalOptionDefaults.logFile)
+        #[arg(long, value_name = "PATH")]
+        log_file: Option<String>,
+        /// Stop a task that runs longer than this many seconds (default: docker.timeoutSeconds, or kubeConfig.defaultTimeoutSeconds / 600 for Kubernetes)
+        #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+        timeout: Option<u64>,
+        /// Show what the task would do without doing it: the container (Docker) or the Job
+        /// manifest as YAML (Kubernetes). Nothing is created
+        #[arg(long)]
+        dry_run: bool,
+        /// After a successful task, push the worktree branch and open a pull request with `gh`
+        /// (Docker worktree mode only)
+        #[arg(long, conflicts_with_all = ["async_mode", "workspace_dir", "no_worktree", "dry_run"])]
+        create_pr: bool,
+        /// Run Claude with --output-format json and report its token usage, cost and tool use
+        /// after its response (Docker foreground runs only)
+        #[arg(long, conflicts_with = "async_mode")]
+        claude_json: bool,
+        /// POST to notifications.webhookUrl when the task completes or fails
+        /// (default: globalOptionDefaults.notify)
+        #[arg(long)]
+        notify: bool,
+        /// Memory limit for the task container, e.g. 512m or 2g (overrides docker.resources.memory)
+        #[arg(long, value_name = "SIZE", value_parser = config::parse_memory_size)]
+        memory: Option<i64>,
+        /// Number of CPUs the task container may use, e.g. 1.5 (overrides docker.resources.cpus)
+        #[arg(long)]
+        cpus: Option<f64>,
         /// Execution environment (docker or kubernetes). Overrides config setting
         #[arg(long, value_enum)]
         execution_env: Option<ExecutionEnvironment>,
@@ -245,12 +575,29 @@ enum Commands {
         /// Kubernetes context to use (overrides config)
         #[arg(long)]
         kube_context: Option<String>,
+        /// CPU limit for the Kubernetes job, e.g. 500m or 2 (overrides kubeConfig.resources.limits.cpu)
+        #[arg(long, value_name = "CPU")]
+        kube_cpu: Option<String>,
+        /// Memory limit for the Kubernetes job, e.g. 2Gi (overrides kubeConfig.resources.limits.memory)
+        #[arg(long, value_name = "MEMORY")]
+        kube_memory: Option<String>,
+        /// Retry a failed Kubernetes job pod this many times (overrides kubeConfig.backoffLimit)
+        #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(i32).range(0..))]
+        retries: Option<i32>,
+        /// When nodes pull the job image (overrides kubeConfig.imagePullPolicy; default: Always
+        /// for :latest images, IfNotPresent otherwise)
+        #[arg(long, value_enum, value_name = "POLICY")]
+        pull_policy: Option<config::ImagePullPolicy>,
         /// Name of existing Kubernetes secret containing git credentials (default: git-credentials)
         #[arg(long, value_name = "SECRET_NAME")]
         git_secret_name: Option<String>,
         /// Key within the secret containing the token (default: token)
         #[arg(long, value_name = "KEY")]
         git_secret_key: Option<String>,
+        /// Progress format: emoji log lines (default), or newline-delimited JSON events on
+        /// stdout with all other output moved to stderr
+        #[arg(long, value_enum)]
+        output: Option<reporter::OutputFormat>,
     },
     /// Clean up both claude-task git worktrees and docker volumes
     #[command(visible_alias = "c")]
@@ -258,9 +605,57 @@ enum Commands {
         /// Skip confirmation prompt
         #[arg(long, short = 'y')]
         yes: bool,
-        /// Force removal of worktrees even if they have uncommitted changes
+        /// Force removal of worktrees even if they have uncommitted changes or are locked
         #[arg(long, short = 'f')]
         force: bool,
+        /// Also remove worktrees whose merge was only detected heuristically (e.g. squash merges)
+        #[arg(long)]
+        include_heuristic: bool,
+        /// Which runner's resources to clean (defaults to the configured task runner)
+        #[arg(long, value_enum)]
+        runner: Option<CleanRunner>,
+    },
+    /// List running claude-task containers or Kubernetes jobs
+    Ps {
+        /// Also show stopped containers (Docker only)
+        #[arg(long, short = 'a')]
+        all: bool,
+        /// Print the tasks as JSON
+        #[arg(long)]
+        json: bool,
+        /// Execution environment to list (defaults to the configured task runner)
+        #[arg(long, value_enum)]
+        execution_env: Option<ExecutionEnvironment>,
+    },
+    /// Show the output of a task started with --background
+    Logs {
+        /// Task ID to show logs for
+        task_id: String,
+        /// Keep streaming new output until the task exits
+        #[arg(long, short = 'f')]
+        follow: bool,
+        /// Execution environment the task runs in (defaults to the configured task runner)
+        #[arg(long, value_enum)]
+        execution_env: Option<ExecutionEnvironment>,
+    },
+    /// Run a command (default: /bin/bash) inside a running background task's container
+    Exec {
+        /// Task ID whose container to enter
+        task_id: String,
+        /// Command to run, after `--` (e.g. `claude-task exec my-task -- git status`)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        /// Execution environment the task runs in (defaults to the configured task runner)
+        #[arg(long, value_enum)]
+        execution_env: Option<ExecutionEnvironment>,
+    },
+    /// Stop a task started with --background
+    Stop {
+        /// Task ID to stop
+        task_id: String,
+        /// Execution environment the task runs in (defaults to the configured task runner)
+        #[arg(long, value_enum)]
+        execution_env: Option<ExecutionEnvironment>,
     },
     /// Configuration management commands
     #[command(visible_alias = "cf")]
@@ -271,16 +666,323 @@ enum Commands {
     /// Launch MCP server on stdio
     #[command(after_help = MCP_HELP_TEXT)]
     Mcp,
+    /// Show locally recorded task runs
+    #[command(visible_alias = "h")]
+    History {
+        /// Number of recent runs to show
+        #[arg(long, short = 'n', default_value_t = 20)]
+        limit: usize,
+        /// Only runs started since a date (YYYY-MM-DD) or within an age like 48h or 7d
+        #[arg(long, value_name = "DATE|AGE")]
+        since: Option<String>,
+        /// Only runs that ended with this status
+        #[arg(long, value_enum)]
+        status: Option<RunStatus>,
+        /// Only runs whose prompt contains this text (case-insensitive). Only the first
+        /// 60 characters of each prompt are searched unless history.storeFullPrompts is set
+        #[arg(long, value_name = "TEXT")]
+        grep: Option<String>,
+        /// Print matching runs as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(subcommand)]
+        command: Option<HistoryCommands>,
+    },
+    /// Check Docker, Kubernetes, tools and credentials for common setup problems
+    Doctor,
+    /// Print a shell completion script to stdout
+    #[command(after_help = "Examples:\n  \
+        claude-task completion bash > ~/.local/share/bash-completion/completions/claude-task\n  \
+        claude-task completion zsh > \"${fpath[1]}/_claude-task\"\n  \
+        claude-task completion fish > ~/.config/fish/completions/claude-task.fish")]
+    Completion {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
     /// Print version information
     #[command(visible_alias = "v")]
     Version,
 }
 
-async fn run_claude_task(config: TaskRunConfig<'_>) -> Result<()> {
-    match config.task_runner {
+/// Runner whose resources `clean` removes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum CleanRunner {
+    Docker,
+    Kubernetes,
+    All,
+}
+
+impl std::fmt::Display for CleanRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CleanRunner::Docker => write!(f, "Docker"),
+            CleanRunner::Kubernetes => write!(f, "Kubernetes"),
+            CleanRunner::All => write!(f, "Docker and Kubernetes"),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum HistoryCommands {
+    /// Show details of the most recent run of a task
+    Show {
+        /// Task ID to show
+        task_id: String,
+    },
+}
+
+async fn run_claude_task(mut config: TaskRunConfig<'_>) -> Result<RunStatus> {
+    // Resolve the task ID up front so the run can be recorded in the history
+    let task_id = config
+        .task_id
+        .clone()
+        .unwrap_or_else(|| generate_task_id(config.id_generator));
+    config.task_id = Some(task_id.clone());
+
+    let branch = match config.task_runner {
+        ExecutionEnvironment::Docker if config.workspace_dir.is_some() => None,
+        ExecutionEnvironment::Docker => Some(format!(
+            "{}{}",
+            config.branch_prefix,
+            worktree::sanitize_branch_name(&task_id)
+        )),
+        ExecutionEnvironment::Kubernetes => Some(format!("{}{}", config.branch_prefix, task_id)),
+    };
+    if let Some(warning) = config::runner_image_warning(
+        config.task_runner,
+        &config.docker_config.image_name,
+        config.kube_config.as_ref().map(|kc| kc.image.as_str()),
+    ) {
+        println!("⚠️  {warning}");
+        println!();
+    }
+
+    let prompt = config.prompt.to_string();
+    let runner = config.task_runner.clone();
+    let debug = config.debug;
+    let started_at = std::time::Instant::now();
+    let dry_run = config.dry_run;
+    let notify_webhook = config.notify_webhook.clone();
+    let history_config = config.history.clone();
+    // Only sync Docker runs write the --log-file
+    let log_path = match config.task_runner {
+        ExecutionEnvironment::Docker if !config.async_mode => config
+            .log_file
+            .as_deref()
+            .map(|log_file| task_log_path(log_file, &task_id))
+            .transpose()?,
+        _ => None,
+    };
+
+    let result = match config.task_runner {
         ExecutionEnvironment::Docker => run_docker_task(config).await,
         ExecutionEnvironment::Kubernetes => run_kube_task(config).await,
+    };
+
+    let status = match result {
+        Ok(status) => status,
+        Err(_) => RunStatus::Failed,
+    };
+    let duration_ms = started_at.elapsed().as_millis();
+    match &result {
+        Ok(status) => reporter::emit(reporter::RunEvent::Completed {
+            task_id: task_id.clone(),
+            status: serde_json::to_value(status)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            duration_ms,
+        }),
+        Err(e) => reporter::emit(reporter::RunEvent::Failed {
+            task_id: task_id.clone(),
+            error: format!("{e:#}"),
+            duration_ms,
+        }),
+    }
+    if let Some(url) = notify_webhook
+        .filter(|_| matches!(status, RunStatus::Succeeded | RunStatus::Failed) && !dry_run)
+    {
+        let notification = notify::TaskNotification::new(
+            &task_id,
+            status,
+            runner.clone(),
+            branch.clone(),
+            started_at.elapsed(),
+            result.as_ref().err().map(|e| format!("{e:#}")),
+        );
+        if let Err(e) = notify::send_webhook(&url, &notification).await {
+            println!("⚠️  Warning: Failed to send completion notification: {e:#}");
+        }
+    }
+
+    let history_path = history::default_history_path();
+    if let Some(history_path) = history_path
+        .as_ref()
+        .filter(|_| history_config.enabled && status != RunStatus::Cancelled && !dry_run)
+    {
+        let mut record = history::HistoryRecord::new(
+            &task_id,
+            &prompt,
+            runner,
+            branch,
+            status,
+            started_at.elapsed(),
+        );
+        record.exit_code = match &result {
+            Ok(RunStatus::Succeeded) => Some(0),
+            Ok(_) => None,
+            Err(e) => task_exit_code(e),
+        };
+        record.log_path = log_path.map(|path| path.display().to_string());
+        if history_config.store_full_prompts {
+            record.prompt = Some(prompt);
+        }
+        record.error = result.as_ref().err().map(|e| e.to_string());
+
+        if let Err(e) = history::append_record(history_path, &record) {
+            if debug {
+                println!("⚠️  Warning: Failed to record task history: {e}");
+            }
+        }
+    }
+
+    result
+}
+
+/// Where a sync Docker task's output is saved with `--log-file`
+fn task_log_path(log_file: &str, task_id: &str) -> Result<PathBuf> {
+    Config::expand_tilde(&log_file.replace("{taskId}", task_id))
+}
+
+/// The exit status of Claude's container or job, if that is why the task failed
+fn task_exit_code(error: &anyhow::Error) -> Option<i64> {
+    if let Some(exit) = error.downcast_ref::<docker::ContainerExitError>() {
+        return Some(exit.status_code);
+    }
+    error
+        .downcast_ref::<kube::JobExitError>()
+        .and_then(|exit| exit.exit_code)
+        .map(i64::from)
+}
+
+/// One-line tally of batch results; tasks left running in the background or cancelled
+/// are counted on their own rather than as successes
+fn batch_summary(statuses: &[RunStatus]) -> String {
+    let count = |wanted: RunStatus| statuses.iter().filter(|s| **s == wanted).count();
+    let mut summary = format!(
+        "Batch summary: {} tasks, {} succeeded, {} failed",
+        statuses.len(),
+        count(RunStatus::Succeeded),
+        count(RunStatus::Failed)
+    );
+    let background = count(RunStatus::Background);
+    if background > 0 {
+        summary.push_str(&format!(", {background} running in the background"));
+    }
+    let cancelled = count(RunStatus::Cancelled);
+    if cancelled > 0 {
+        summary.push_str(&format!(", {cancelled} cancelled"));
+    }
+    summary
+}
+
+/// Run each task from a JSONL file, reusing the CLI options as defaults for every entry
+async fn run_batch_tasks(path: &Path, base: TaskRunConfig<'_>, concurrency: u16) -> Result<()> {
+    use futures::StreamExt;
+
+    let entries = batch::load_batch_file(path)?;
+
+    if concurrency > 1 && !base.skip_confirmation && !base.async_mode {
+        anyhow::bail!(
+            "Running tasks concurrently requires --yes, since confirmation prompts can't be answered in parallel"
+        );
+    }
+
+    println!(
+        "📋 Running {} tasks from {} (concurrency: {})",
+        entries.len(),
+        path.display(),
+        concurrency
+    );
+    println!();
+
+    let configs: Vec<TaskRunConfig<'_>> = entries
+        .iter()
+        .map(|entry| {
+            let mut config = base.clone();
+            config.prompt = &entry.prompt;
+            config.task_id = Some(
+                entry
+                    .task_id
+                    .clone()
+                    .unwrap_or_else(|| generate_task_id(base.id_generator)),
+            );
+            if let Some(mode) = entry.permission_mode {
+                config.permission_mode = Some(mode);
+            }
+            if let Some(ref tool) = entry.approval_tool_permission {
+                config.approval_tool_permission = Some(tool.clone());
+            }
+            if let Some(ref mcp_config) = entry.mcp_config {
+                config.mcp_config = Some(mcp_config.clone());
+            }
+            if let Some(ref env) = entry.execution_env {
+                config.task_runner = env;
+            }
+            if let Some(ref dir) = entry.workspace_dir {
+                config.workspace_dir = Some(Some(dir.clone()));
+            }
+            config
+        })
+        .collect();
+
+    let results: Vec<(String, Result<RunStatus>, std::time::Duration)> =
+        futures::stream::iter(configs.into_iter().map(|config| async move {
+            let task_id = config.task_id.clone().unwrap_or_default();
+            let started_at = std::time::Instant::now();
+            let result = run_claude_task(config).await;
+            (task_id, result, started_at.elapsed())
+        }))
+        .buffered(concurrency as usize)
+        .collect()
+        .await;
+
+    let statuses: Vec<RunStatus> = results
+        .iter()
+        .map(|(_, result, _)| *result.as_ref().unwrap_or(&RunStatus::Failed))
+        .collect();
+    let failed = statuses
+        .iter()
+        .filter(|status| **status == RunStatus::Failed)
+        .count();
+
+    println!();
+    println!("📊 {}", batch_summary(&statuses));
+    for (task_id, result, duration) in &results {
+        match result {
+            Ok(status) => println!(
+                "   {} {:<24} {:>8.1}s  {:?}",
+                status.icon(),
+                task_id,
+                duration.as_secs_f64(),
+                status
+            ),
+            Err(e) => println!(
+                "   {} {:<24} {:>8.1}s  {}",
+                RunStatus::Failed.icon(),
+                task_id,
+                duration.as_secs_f64(),
+                e
+            ),
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} tasks failed", failed, results.len());
     }
+
+    Ok(())
 }
 
 async fn validate_kubernetes_access(context: &str) -> Result<()> {
@@ -324,6 +1026,26 @@ async fn validate_kubernetes_access(context: &str) -> Result<()> {
     Ok(())
 }
 
+/// Whether the credentials copied into the task home have expired or are about to
+fn stored_credentials_expiring(task_base_home_dir: &str) -> bool {
+    let Ok(home_dir) = Config::expand_tilde(task_base_home_dir) else {
+        return false;
+    };
+    let path = home_dir.join(".claude").join(".credentials.json");
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return false;
+    };
+    let now_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|now| now.as_millis() as u64)
+        .unwrap_or_default();
+    credential_sync::credentials_need_refresh(
+        &contents,
+        now_ms,
+        std::time::Duration::from_secs(credential_sync::EXPIRY_REFRESH_MARGIN_SECS),
+    )
+}
+
 fn get_git_remote_url(path: &Path) -> Result<String> {
     let output = Command::new("git")
         .args(["config", "--get", "remote.origin.url"])
@@ -366,38 +1088,150 @@ fn get_github_token() -> Option<String> {
     None
 }
 
-async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
-    let kube_config = config.kube_config.as_ref().ok_or_else(|| {
-        anyhow::anyhow!("Kubernetes execution environment requires a kube_config")
-    })?;
+/// Login used to create an image pull secret
+struct RegistryLogin {
+    server: String,
+    username: String,
+    password: String,
+    source: &'static str,
+}
 
-    // Determine context and namespace (similar logic to setup)
-    let context = kube_config
-        .context
-        .clone()
-        .or_else(config::Config::get_current_kube_context)
-        .ok_or_else(|| {
-            anyhow::anyhow!(
-                "No Kubernetes context specified and could not detect current context. \
-        Please specify a context in config.json or ensure kubectl is configured."
-            )
-        })?;
+/// Find credentials for the registry hosting the Kubernetes image: REGISTRY_USERNAME and
+/// REGISTRY_PASSWORD, then `kubeConfig.registryCredentials`, then a GitHub token for GHCR
+fn registry_login(kube_config: &config::KubeConfig) -> Option<RegistryLogin> {
+    let host = config::image_registry_host(&kube_config.image);
+    let configured_server = kube_config
+        .registry_credentials
+        .as_ref()
+        .and_then(|c| c.server.clone());
+
+    if let (Ok(username), Ok(password)) = (
+        std::env::var("REGISTRY_USERNAME"),
+        std::env::var("REGISTRY_PASSWORD"),
+    ) {
+        return Some(RegistryLogin {
+            server: configured_server.unwrap_or_else(|| host.to_string()),
+            username,
+            password,
+            source: "REGISTRY_USERNAME/REGISTRY_PASSWORD env vars",
+        });
+    }
 
-    let namespace = kube_config.namespace.clone().unwrap_or_else(|| {
-        let suffix = config::Config::generate_namespace_suffix();
-        format!("claude-task-{suffix}")
-    });
+    if let Some(ref credentials) = kube_config.registry_credentials {
+        return Some(RegistryLogin {
+            server: configured_server.unwrap_or_else(|| host.to_string()),
+            username: credentials.username.clone(),
+            password: credentials.password.clone(),
+            source: "kubeConfig.registryCredentials",
+        });
+    }
 
-    let task_id = config
-        .task_id
-        .clone()
-        .unwrap_or_else(worktree::generate_short_id);
+    if host == "ghcr.io" {
+        let github_token = get_github_token()?;
+        // Try to get username from GITHUB_USERNAME or fall back to system username
+        let github_username = std::env::var("GITHUB_USERNAME")
+            .or_else(|_| std::env::var("USER"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        return Some(RegistryLogin {
+            server: host.to_string(),
+            username: github_username,
+            password: github_token,
+            source: if std::env::var("GITHUB_TOKEN").is_ok() {
+                "GITHUB_TOKEN env var"
+            } else {
+                "gh CLI"
+            },
+        });
+    }
 
-    println!("Running Claude task in Kubernetes with ID: {task_id}");
+    None
+}
+
+/// Pull secret jobs should reference: the configured one, or the default name when the
+/// image is on GHCR or registry credentials are available. `None` for public images
+fn image_pull_secret_name(kube_config: &config::KubeConfig) -> Option<String> {
+    let host = config::image_registry_host(&kube_config.image);
+    kube_config.image_pull_secret.clone().or_else(|| {
+        (host == "ghcr.io"
+            || kube_config.registry_credentials.is_some()
+            || std::env::var("REGISTRY_USERNAME").is_ok())
+        .then(|| config::default_pull_secret_name(host).to_string())
+    })
+}
+
+/// Create the image pull secret for the Kubernetes image's registry if credentials are
+/// available, or explain how to create it. Returns the secret's name, if the image needs one
+async fn ensure_image_pull_secret(
+    k8s_runner: &kube::KubernetesJobRunner,
+    kube_config: &config::KubeConfig,
+    namespace: &str,
+) -> Result<Option<String>> {
+    let Some(pull_secret_name) = image_pull_secret_name(kube_config) else {
+        return Ok(None);
+    };
+    let host = config::image_registry_host(&kube_config.image);
+
+    println!("🔐 Ensuring image pull secret '{pull_secret_name}' exists for {host}...");
+
+    if let Some(login) = registry_login(kube_config) {
+        println!("   Using username: {}", login.username);
+        println!("   Credentials source: {}", login.source);
+        k8s_runner
+            .create_docker_registry_secret(
+                namespace,
+                &pull_secret_name,
+                config::docker_config_server(&login.server),
+                &login.username,
+                &login.password,
+            )
+            .await?;
+    } else {
+        println!("   ⚠️  No registry credentials found");
+        println!("   To pull from {host}, you need to either:");
+        println!("   1. Set REGISTRY_USERNAME and REGISTRY_PASSWORD environment variables");
+        println!("   2. Add kubeConfig.registryCredentials to your config");
+        if host == "ghcr.io" {
+            println!("   3. Set GITHUB_TOKEN or login with gh CLI: gh auth login");
+        }
+        println!(
+            "   Or create the secret manually: kubectl create secret docker-registry {pull_secret_name} \\"
+        );
+        println!(
+            "     --docker-server={} \\",
+            config::docker_config_server(host)
+        );
+        println!("     --docker-username=YOUR_USERNAME \\");
+        println!("     --docker-password=YOUR_PASSWORD \\");
+        println!("     -n {namespace}");
+    }
+
+    Ok(Some(pull_secret_name))
+}
+
+/// The library's `ImagePullPolicy` for a configured one. The binary compiles its own
+/// copy of the `config` module, so the two enums are distinct types.
+fn job_image_pull_policy(policy: config::ImagePullPolicy) -> claude_task::config::ImagePullPolicy {
+    use claude_task::config::ImagePullPolicy as JobPullPolicy;
+    match policy {
+        config::ImagePullPolicy::Always => JobPullPolicy::Always,
+        config::ImagePullPolicy::IfNotPresent => JobPullPolicy::IfNotPresent,
+        config::ImagePullPolicy::Never => JobPullPolicy::Never,
+    }
+}
 
+/// Check cluster access and create the namespace and secrets a job needs.
+/// Returns `None` if the user declines to run in an auto-generated namespace.
+async fn prepare_kube_cluster(
+    kube_config: &config::KubeConfig,
+    context: &str,
+    namespace: &str,
+    config_path: Option<&PathBuf>,
+    timer: &mut timing::PhaseTimer,
+) -> Result<Option<kube::KubernetesJobRunner>> {
     // Validate Kubernetes connectivity
+    timer.start("preflight");
     println!("🔍 Checking Kubernetes cluster connectivity...");
-    if let Err(e) = validate_kubernetes_access(&context).await {
+    if let Err(e) = validate_kubernetes_access(context).await {
         return Err(anyhow::anyhow!("Failed to connect to Kubernetes cluster: {}\n\nPlease ensure:\n1. kubectl is installed\n2. You have a valid kubeconfig\n3. The context '{}' exists\n4. You have permissions to create jobs in namespace '{}'", 
             e, context, namespace));
     }
@@ -423,15 +1257,15 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
 
         if !confirmed {
             println!("Task cancelled.");
-            return Ok(());
+            return Ok(None);
         }
 
         // Update the config to remember this confirmation
-        let config_path = Config::default_config_path();
-        let mut full_config = Config::load(Some(&config_path))?;
+        let config_path = Config::resolve_path(config_path)?;
+        let mut full_config = Config::load_for_update(Some(&config_path))?;
         if let Some(ref mut kc) = full_config.kube_config {
-            kc.context = Some(context.clone());
-            kc.namespace = Some(namespace.clone());
+            kc.context = Some(context.to_string());
+            kc.namespace = Some(namespace.to_string());
             kc.namespace_confirmed = true;
         }
         full_config.save(&config_path)?;
@@ -440,6 +1274,7 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
     }
 
     // Create Kubernetes client and ensure namespace exists
+    timer.start("cluster setup");
     println!("🔧 Creating Kubernetes runner...");
     let k8s_runner = kube::KubernetesJobRunner::new()
         .await
@@ -447,67 +1282,23 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
 
     // Ensure namespace exists before any operations
     println!("📁 Ensuring namespace '{namespace}' exists...");
-    k8s_runner.create_namespace(&namespace).await?;
-
-    // Ensure image pull secret exists for GHCR images
-    if kube_config.image.contains("ghcr.io") {
-        let pull_secret_name = kube_config
-            .image_pull_secret
-            .clone()
-            .unwrap_or_else(|| "ghcr-pull-secret".to_string());
-
-        println!("🔐 Ensuring image pull secret '{pull_secret_name}' exists for GHCR...");
+    k8s_runner.create_namespace(namespace).await?;
 
-        // Check if we have GitHub token from environment or gh CLI
-        if let Some(github_token) = get_github_token() {
-            // Try to get username from GITHUB_USERNAME or fall back to system username
-            let github_username = std::env::var("GITHUB_USERNAME")
-                .or_else(|_| std::env::var("USER"))
-                .unwrap_or_else(|_| "unknown".to_string());
-
-            println!("   Using GitHub username: {github_username}");
-            println!(
-                "   Token source: {}",
-                if std::env::var("GITHUB_TOKEN").is_ok() {
-                    "GITHUB_TOKEN env var"
-                } else {
-                    "gh CLI"
-                }
-            );
-            k8s_runner
-                .create_docker_registry_secret(
-                    &namespace,
-                    &pull_secret_name,
-                    "ghcr.io",
-                    &github_username,
-                    &github_token,
-                )
-                .await?;
-        } else {
-            println!("   ⚠️  No GitHub token found");
-            println!("   To pull from GHCR, you need to either:");
-            println!("   1. Set environment variable: export GITHUB_TOKEN=your-token");
-            println!("   2. Login with gh CLI: gh auth login");
-            println!(
-                "   Or create the secret manually: kubectl create secret docker-registry {pull_secret_name} \\"
-            );
-            println!("     --docker-server=ghcr.io \\");
-            println!("     --docker-username=YOUR_GITHUB_USERNAME \\");
-            println!("     --docker-password=YOUR_GITHUB_TOKEN \\");
-            println!("     -n {namespace}");
-        }
-
-        // Also update the saved config if it wasn't set
-        if kube_config.image_pull_secret.is_none() {
-            let config_path = Config::default_config_path();
-            let mut full_config = Config::load(Some(&config_path))?;
-            if let Some(ref mut kc) = full_config.kube_config {
-                kc.image_pull_secret = Some(pull_secret_name.clone());
-            }
-            full_config.save(&config_path)?;
-            println!("   ✓ Updated config with image pull secret name");
-        }
-    }
+    // Ensure the image pull secret exists for private registries
+    if let Some(pull_secret_name) =
+        ensure_image_pull_secret(&k8s_runner, kube_config, namespace).await?
+    {
+        // Also update the saved config if it wasn't set
+        if kube_config.image_pull_secret.is_none() {
+            let config_path = Config::resolve_path(config_path)?;
+            let mut full_config = Config::load_for_update(Some(&config_path))?;
+            if let Some(ref mut kc) = full_config.kube_config {
+                kc.image_pull_secret = Some(pull_secret_name);
+            }
+            full_config.save(&config_path)?;
+            println!("   ✓ Updated config with image pull secret name");
+        }
+    }
 
     // Ensure git credentials secret exists
     println!(
@@ -525,7 +1316,7 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
         );
         k8s_runner
             .create_git_secret(
-                &namespace,
+                namespace,
                 &kube_config.git_secret_name,
                 &kube_config.git_secret_key,
                 &github_token,
@@ -549,6 +1340,126 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
         println!("        -n {namespace}");
     }
 
+    Ok(Some(k8s_runner))
+}
+
+/// Recreate the Claude credentials secret if the local credentials changed since they were
+/// last pushed to this namespace, so jobs don't keep running with a stale token
+async fn refresh_kube_credentials_secret(
+    k8s_runner: &kube::KubernetesJobRunner,
+    task_base_home_dir: &str,
+    context: &str,
+    namespace: &str,
+    task_id: &str,
+) -> Result<()> {
+    let home_volume_path = Config::expand_tilde(task_base_home_dir)?;
+    let Ok(credentials) =
+        std::fs::read_to_string(home_volume_path.join(".claude/.credentials.json"))
+    else {
+        return Ok(());
+    };
+
+    let sync_manager = credential_sync::CredentialSyncManager::new(task_base_home_dir, task_id)?;
+    let target = format!("{context}/{namespace}");
+    if !sync_manager.secret_needs_update(&target, &credentials)? {
+        return Ok(());
+    }
+
+    println!("🔄 Local Claude credentials changed, updating the 'claude-credentials' secret...");
+    k8s_runner
+        .recreate_claude_credentials_secret(namespace, "claude-credentials", &home_volume_path)
+        .await?;
+    sync_manager.record_secret_pushed(&target, &credentials)?;
+
+    Ok(())
+}
+
+async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<RunStatus> {
+    let dry_run = config.dry_run;
+    let mut timer = timing::PhaseTimer::new();
+    let result = run_kube_job(config, &mut timer).await;
+
+    // Failed and background runs get the breakdown too, to show where the time went
+    if !dry_run && !matches!(result, Ok(RunStatus::Cancelled)) {
+        timer.print_summary();
+    }
+    result
+}
+
+async fn run_kube_job(
+    config: TaskRunConfig<'_>,
+    timer: &mut timing::PhaseTimer,
+) -> Result<RunStatus> {
+    let kube_config = config.kube_config.as_ref().ok_or_else(|| {
+        anyhow::anyhow!("Kubernetes execution environment requires a kube_config")
+    })?;
+
+    // Determine context and namespace (similar logic to setup)
+    let context = kube_config
+        .context
+        .clone()
+        .or_else(config::Config::get_current_kube_context)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No Kubernetes context specified and could not detect current context. \
+        Please specify a context in config.json or ensure kubectl is configured."
+            )
+        })?;
+
+    let namespace = kube_config.namespace.clone().unwrap_or_else(|| {
+        let suffix = config::Config::generate_namespace_suffix();
+        format!("claude-task-{suffix}")
+    });
+
+    let task_id = config
+        .task_id
+        .clone()
+        .unwrap_or_else(|| generate_task_id(config.id_generator));
+
+    println!("Running Claude task in Kubernetes with ID: {task_id}");
+    if config.carry_changes != worktree::CarryChanges::None {
+        println!(
+            "⚠️  --carry-changes is ignored for Kubernetes tasks; the job works from a fresh clone"
+        );
+    }
+    if config.create_pr {
+        println!("⚠️  --create-pr is ignored for Kubernetes tasks");
+    }
+    if config.ssh_agent {
+        println!("⚠️  --ssh-agent is ignored for Kubernetes tasks; use a git credentials secret");
+    }
+    if config.claude_json {
+        println!("⚠️  --claude-json is ignored for Kubernetes tasks");
+    }
+    if config.log_file.is_some() {
+        println!("⚠️  --log-file is ignored for Kubernetes tasks");
+    }
+
+    // Dry runs only render the manifest, so they never touch the cluster
+    let k8s_runner = if config.dry_run {
+        None
+    } else {
+        match prepare_kube_cluster(kube_config, &context, &namespace, config.config_path, timer)
+            .await?
+        {
+            Some(k8s_runner) => Some(k8s_runner),
+            None => return Ok(RunStatus::Cancelled),
+        }
+    };
+
+    if let Some(ref k8s_runner) = k8s_runner {
+        if config.claude_credentials.is_none() {
+            refresh_kube_credentials_secret(
+                k8s_runner,
+                config.task_base_home_dir,
+                &context,
+                &namespace,
+                &task_id,
+            )
+            .await?;
+        }
+    }
+
     // Note features not available in K8s mode
     if config.workspace_dir.is_some() {
         println!("⚠️  Note: Custom workspace directory is not supported in Kubernetes mode");
@@ -577,6 +1488,9 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
 
     // Prepare approval tool permission
     let approval_permission = config.approval_tool_permission.clone();
+    let Some(permission_mode) = resolve_permission_mode(&config)? else {
+        return Ok(RunStatus::Cancelled);
+    };
 
     // Always use the configured git credentials secret
     let secret_name = config
@@ -595,14 +1509,13 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
     // JobConfig will validate it exists before running
 
     // Determine the image pull secret to use
-    let image_pull_secret = if kube_config.image.contains("ghcr.io") {
-        kube_config
-            .image_pull_secret
-            .clone()
-            .or_else(|| Some("ghcr-pull-secret".to_string()))
-    } else {
-        kube_config.image_pull_secret.clone()
-    };
+    let image_pull_secret = image_pull_secret_name(kube_config);
+
+    // The deadline is enforced by the cluster too, so it also applies to async jobs
+    let job_timeout_seconds = config
+        .timeout_seconds
+        .or(kube_config.default_timeout_seconds)
+        .unwrap_or(kube::DEFAULT_JOB_TIMEOUT_SECONDS);
 
     // Create Kubernetes job configuration
     let job_config = kube::JobConfig {
@@ -610,18 +1523,77 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
         namespace: namespace.clone(),
         git_repo: git_remote_url,
         git_branch: Some(branch_name.clone()),
+        base_branch: config.base_ref.clone(),
         secret_name: secret_name.clone(),
         secret_key: secret_key.clone(),
         claude_prompt: config.prompt.to_string(),
         claude_permission_tool: approval_permission.clone(),
         claude_mcp_config: config.mcp_config.clone(),
         claude_debug: config.debug,
-        claude_skip_permissions: approval_permission.is_none() && config.skip_confirmation,
+        claude_permission_mode: permission_mode,
         image: Some(kube_config.image.clone()),
         image_pull_secret,
         async_mode: config.async_mode,
-        timeout_seconds: Some(600), // 10 minutes default
+        timeout_seconds: Some(job_timeout_seconds),
         oauth_token: config.claude_credentials.as_ref().map(|c| c.token.clone()),
+        entrypoint: kube_config.entrypoint.clone(),
+        claude_binary: kube_config.claude_binary.clone(),
+        resources: Some(kube_config.resources.requirements()),
+        clone_resources: Some(
+            kube_config
+                .clone_resources
+                .clone()
+                .unwrap_or_else(config::KubeResources::clone_default)
+                .requirements(),
+        ),
+        node_selector: kube_config.node_selector.clone(),
+        tolerations: kube_config.tolerations.clone(),
+        workspace_pvc: kube_config.workspace_pvc.clone(),
+        backoff_limit: kube_config.backoff_limit.unwrap_or(0),
+        image_pull_policy: kube_config.image_pull_policy.map(job_image_pull_policy),
+        service_account_name: kube_config.service_account_name.clone(),
+        pod_security_context: Some(kube_config.security_context.pod_security_context()),
+        container_security_context: Some(kube_config.security_context.container_security_context()),
+        env: {
+            let mut env: Vec<(String, String)> = kube_config
+                .environment_variables
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            env.sort();
+            env
+        },
+    };
+
+    let Some(k8s_runner) = k8s_runner else {
+        // Dry run: show what would be submitted and stop
+        println!("   Context: {context}");
+        println!("   Namespace: {}", job_config.namespace);
+        println!("   Branch: {branch_name}");
+        if let Some(ref base) = job_config.base_branch {
+            println!("   Based on: {base}");
+        }
+        println!(
+            "   Git credentials secret: {} (key {})",
+            job_config.secret_name, job_config.secret_key
+        );
+        println!(
+            "   Image pull policy: {}",
+            job_config.resolved_image_pull_policy().as_str()
+        );
+        if let Some(ref service_account) = job_config.service_account_name {
+            println!("   Service account: {service_account}");
+        }
+        if let Some(ref pull_secret) = job_config.image_pull_secret {
+            println!("   Image pull secret: {pull_secret}");
+        }
+        println!();
+        println!("📄 Job manifest (not submitted):");
+        println!("---");
+        reporter::print_manifest(&kube::KubernetesJobRunner::render_job_manifest(
+            &job_config,
+        )?);
+        return Ok(RunStatus::Succeeded);
     };
 
     // Run the job
@@ -638,36 +1610,22 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
     println!("   Namespace: {}", job_config.namespace);
     println!();
 
-    match k8s_runner.run_job(job_config).await {
+    timer.start("job execution");
+    let status = match k8s_runner.run_job(job_config).await {
         Ok(result) => {
             match result {
-                kube::JobResult::Sync {
-                    stdout,
-                    stderr,
-                    exit_code,
-                } => {
-                    // Print the output
-                    if !stdout.is_empty() {
-                        println!("\n=== JOB OUTPUT ===");
-                        println!("{stdout}");
-                    }
-
-                    if !stderr.is_empty() {
-                        eprintln!("\n=== STDERR ===");
-                        eprintln!("{stderr}");
-                    }
-
+                kube::JobResult::Sync { exit_code, .. } => {
+                    // The job's output has already been streamed by the kube module
                     if exit_code == Some(0) {
                         println!("\n✨ Claude task completed successfully in Kubernetes!");
                         println!("   Branch created: {branch_name}");
                         println!(
                             "   You can check out the branch with: git fetch && git checkout {branch_name}"
                         );
+                        println!();
+                        RunStatus::Succeeded
                     } else {
-                        return Err(anyhow::anyhow!(
-                            "Claude task failed with exit code: {:?}",
-                            exit_code
-                        ));
+                        return Err(kube::JobExitError { exit_code }.into());
                     }
                 }
                 kube::JobResult::Async {
@@ -679,6 +1637,7 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
                     println!("   Namespace: {namespace}");
                     println!("   Branch: {branch_name}");
                     // The monitoring commands are already printed by the kube module
+                    RunStatus::Background
                 }
             }
         }
@@ -686,12 +1645,87 @@ async fn run_kube_task(config: TaskRunConfig<'_>) -> Result<()> {
             eprintln!("\n❌ Kubernetes job failed: {e:#}");
             return Err(e);
         }
+    };
+
+    Ok(status)
+}
+
+/// Resolve the permission mode for a task, validating the approval tool and
+/// confirming with the user before permissions are skipped.
+/// Returns `None` if the user cancelled the task.
+fn resolve_permission_mode(config: &TaskRunConfig<'_>) -> Result<Option<PermissionMode>> {
+    let permission_mode = PermissionMode::resolve(
+        config.permission_mode,
+        config.approval_tool_permission.as_deref(),
+    )?;
+
+    if permission_mode == PermissionMode::Skip {
+        // Show warning and request confirmation
+        println!("⚠️  WARNING: Permission mode is 'skip'!");
+        println!("   This will run Claude with --dangerously-skip-permissions");
+        println!(
+            "   Claude will have unrestricted access to execute commands without user approval."
+        );
+
+        // Extra warning if HT-MCP is enabled
+        if config.ht_mcp_port.is_some() {
+            println!();
+            println!("🚨 ADDITIONAL WARNING: HT-MCP mode is enabled!");
+            println!("   Skipping permissions defeats the purpose of HT-MCP integration.");
+            println!("   Claude will be able to use built-in tools instead of HT-MCP,");
+            println!("   making the web interface monitoring ineffective.");
+            println!("   Consider providing an approval tool permission instead.");
+        }
+
+        println!();
+        println!("   This is DANGEROUS and should only be used in trusted environments.");
+        println!();
+
+        if !config.skip_confirmation {
+            print!("❓ Are you sure you want to proceed without permission prompts? [y/N]: ");
+            use std::io::{self, Write};
+            io::stdout().flush().context("Failed to flush stdout")?;
+
+            let mut input = String::new();
+            io::stdin()
+                .read_line(&mut input)
+                .context("Failed to read input")?;
+
+            let input = input.trim().to_lowercase();
+            if input != "y" && input != "yes" {
+                println!("❌ Task cancelled for safety.");
+                return Ok(None);
+            }
+        } else {
+            println!("✓ Skipping confirmation (--yes flag provided)");
+        }
+
+        println!("⚠️  Proceeding with dangerous permissions disabled...");
+        println!();
     }
 
-    Ok(())
+    // Validate approval tool permission format if provided
+    if let Some(ref permission_tool) = config.approval_tool_permission {
+        if let Err(e) = ApprovalToolPermission::parse(permission_tool) {
+            return Err(anyhow::anyhow!(
+                "Invalid approval tool permission format: {}\n\nExpected format: mcp__<server_name>__<tool_name>\nExample: mcp__approval_server__approve_command", 
+                e
+            ));
+        }
+
+        if config.debug {
+            println!("✓ Approval tool permission format validated: {permission_tool}");
+        }
+    }
+
+    if config.debug {
+        println!("✓ Permission mode: {permission_mode:?}");
+    }
+
+    Ok(Some(permission_mode))
 }
 
-async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
+async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<RunStatus> {
     if config.debug {
         println!("🔍 Debug mode enabled");
         println!("📝 Task parameters:");
@@ -709,6 +1743,14 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
         println!();
     }
 
+    // Handle permission configuration FIRST, before any setup
+    let Some(permission_mode) = resolve_permission_mode(&config)? else {
+        return Ok(RunStatus::Cancelled);
+    };
+
+    let mut timer = timing::PhaseTimer::new();
+    timer.start("setup");
+
     let current_dir = std::env::current_dir().context("Could not get current directory")?;
 
     // Validate MCP config file if provided
@@ -735,79 +1777,19 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
         None
     };
 
-    // Handle approval tool permission configuration FIRST, before any setup
-    let (permission_tool_arg, skip_permissions) = match config.approval_tool_permission {
-        Some(tool) => (tool.clone(), false),
-        None => {
-            // Show warning and request confirmation
-            println!("⚠️  WARNING: No approval tool permission specified!");
-            println!("   This will run Claude with --dangerously-skip-permissions");
-            println!("   Claude will have unrestricted access to execute commands without user approval.");
-
-            // Extra warning if HT-MCP is enabled
-            if config.ht_mcp_port.is_some() {
-                println!();
-                println!("🚨 ADDITIONAL WARNING: HT-MCP mode is enabled!");
-                println!("   Skipping permissions defeats the purpose of HT-MCP integration.");
-                println!("   Claude will be able to use built-in tools instead of HT-MCP,");
-                println!("   making the web interface monitoring ineffective.");
-                println!("   Consider providing an approval tool permission instead.");
-            }
-
-            println!();
-            println!("   This is DANGEROUS and should only be used in trusted environments.");
-            println!();
-
-            if !config.skip_confirmation {
-                print!("❓ Are you sure you want to proceed without permission prompts? [y/N]: ");
-                use std::io::{self, Write};
-                io::stdout().flush().context("Failed to flush stdout")?;
-
-                let mut input = String::new();
-                io::stdin()
-                    .read_line(&mut input)
-                    .context("Failed to read input")?;
-
-                let input = input.trim().to_lowercase();
-                if input != "y" && input != "yes" {
-                    println!("❌ Task cancelled for safety.");
-                    return Ok(());
-                }
-            } else {
-                println!("✓ Skipping confirmation (--yes flag provided)");
-            }
-
-            println!("⚠️  Proceeding with dangerous permissions disabled...");
-            println!();
-
-            (String::new(), true)
-        }
-    };
-
-    // Validate approval tool permission format if not empty
-    if !permission_tool_arg.is_empty() {
-        if let Err(e) = ApprovalToolPermission::parse(&permission_tool_arg) {
-            return Err(anyhow::anyhow!(
-                "Invalid approval tool permission format: {}\n\nExpected format: mcp__<server_name>__<tool_name>\nExample: mcp__approval_server__approve_command", 
-                e
-            ));
-        }
-
-        if config.debug {
-            println!("✓ Approval tool permission format validated: {permission_tool_arg}");
-        }
-    }
-
     // Generate or use provided task ID
-    let task_id = match config.task_id {
-        Some(id) => id,
-        None => worktree::generate_short_id(),
-    };
+    let task_id = config
+        .task_id
+        .clone()
+        .unwrap_or_else(|| generate_task_id(config.id_generator));
 
     println!("Running Claude task with ID: {task_id}");
     println!("Prompt: {}", config.prompt);
     println!();
 
+    // Set when a worktree is created, with the commit it started from, for --create-pr
+    let mut created_worktree = None;
+
     // Determine workspace directory
     let workspace_path = match config.workspace_dir {
         Some(Some(custom_dir)) => {
@@ -827,12 +1809,53 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
             println!("📁 Using current directory as workspace");
             current_dir.to_string_lossy().to_string()
         }
+        None if config.dry_run => {
+            let branch_name = format!(
+                "{}{}",
+                config.branch_prefix,
+                worktree::sanitize_branch_name(&task_id)
+            );
+            let worktree_path = worktree::resolve_worktree_directory(config.worktree_base_dir)?
+                .join(format!(
+                    "{}_<timestamp>",
+                    worktree::sanitize_branch_name(&task_id)
+                ));
+            println!("🌿 Would create git worktree (skipped in dry run):");
+            println!("   Path: {}", worktree_path.display());
+            println!("   Branch: {branch_name}");
+            if let Some(ref base_ref) = config.base_ref {
+                println!("   Based on: {base_ref}");
+            }
+            worktree_path.to_string_lossy().to_string()
+        }
         None => {
             // Default: Create worktree
             println!("🌿 Creating git worktree for task...");
-            let (worktree_path, branch_name) =
-                worktree::create_git_worktree(&task_id, "claude-task/", config.worktree_base_dir)?;
+            let (worktree_path, branch_name) = worktree::create_git_worktree(
+                &task_id,
+                config.branch_prefix,
+                config.worktree_base_dir,
+                &worktree::WorktreeCreateOptions {
+                    base_ref: config.base_ref.as_deref(),
+                    carry_changes: config.carry_changes,
+                    recurse_submodules: config.recurse_submodules
+                        || config.worktree_config.recurse_submodules,
+                    post_create_hook: config.worktree_config.post_create_hook.as_deref(),
+                    ..Default::default()
+                },
+            )?;
             println!("✓ Worktree created: {worktree_path:?} (branch: {branch_name})");
+            reporter::emit(reporter::RunEvent::WorktreeCreated {
+                task_id: task_id.clone(),
+                path: worktree_path.to_string_lossy().to_string(),
+                branch: branch_name.clone(),
+            });
+            if config.create_pr {
+                created_worktree = Some((
+                    worktree_path.clone(),
+                    worktree::head_commit(&worktree_path)?,
+                ));
+            }
 
             // Open IDE if requested
             if config.open_editor {
@@ -851,22 +1874,56 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
     println!();
 
     // Create Docker manager
-    let docker_manager = DockerManager::new(config.docker_config.clone())
+    let mut docker_manager = DockerManager::new(config.docker_config.clone())
         .context("Failed to create Docker manager")?;
 
-    // Check if home volume exists, run setup if it doesn't
+    // Look up the volumes and image in one go; the image isn't needed when building
+    let preflight_started = std::time::Instant::now();
+    let preflight = match docker_manager.preflight(!config.build).await {
+        Ok(preflight) => preflight,
+        // A dry run should still describe the task when Docker isn't reachable
+        Err(e) if config.dry_run => {
+            println!("⚠️  Could not inspect Docker volumes and image: {e:#}");
+            docker::Preflight::default()
+        }
+        Err(e) => return Err(e),
+    };
     if config.debug {
         println!(
-            "🔍 Checking if {} volume exists...",
-            config.docker_config.volumes.home
+            "🔍 Pre-flight checks took {:.0?}",
+            preflight_started.elapsed()
         );
     }
-    let home_volume_exists = docker_manager.check_home_volume_exists().await?;
+
+    // Run setup if the home volume doesn't exist
+    timer.start("credential sync");
+    let home_volume_exists = preflight.home_volume_exists;
+
+    if config.claude_credentials.is_some() {
+        println!("💡 Using the long-lived token from config; remember to rotate it periodically");
+    }
     if config.debug {
-        println!("   Volume exists: {home_volume_exists}");
+        println!(
+            "   {} volume exists: {home_volume_exists}",
+            config.docker_config.volumes.home
+        );
     }
 
-    if !home_volume_exists {
+    if config.dry_run {
+        // Nothing is set up or synced; only describe what a real run would do
+        if !home_volume_exists {
+            println!(
+                "🔧 {} volume not found; a real run would set it up first",
+                config.docker_config.volumes.home
+            );
+        } else if config.claude_credentials.is_some() {
+            println!("🔑 A real run would use the token from config without syncing credentials");
+        } else if stored_credentials_expiring(config.task_base_home_dir) {
+            println!("⏰ Stored Claude credentials have expired or are about to; a real run would refresh them from the keychain");
+        } else {
+            println!("🔄 A real run would sync Claude credentials from the keychain unless they were validated recently");
+        }
+    } else if !home_volume_exists {
         println!(
             "🔧 {} volume not found, running setup...",
             config.docker_config.volumes.home
@@ -908,6 +1965,13 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
             let sync_manager =
                 credential_sync::CredentialSyncManager::new(config.task_base_home_dir, &task_id)?;
 
+            // Don't wait for Claude to fail on an expired token: refresh it up front
+            let credentials_expiring = stored_credentials_expiring(config.task_base_home_dir);
+            if credentials_expiring {
+                println!("⏰ Stored Claude credentials have expired or are about to, refreshing from the keychain...");
+                sync_manager.clear_validation()?;
+            }
+
             // Sync credentials if needed with lock mechanism
             let synced = sync_manager
                 .sync_credentials_if_needed(
@@ -918,7 +1982,9 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
 
                         async move {
                             // Extract credentials directly
-                            let credentials = credentials::extract_keychain_credentials().await?;
+                            let credentials =
+                                credentials::extract_keychain_credentials(&claude_user_config)
+                                    .await?;
 
                             // Setup the full configuration (including writing the credentials)
                             setup_credentials_and_config_with_cache(
@@ -939,6 +2005,9 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
 
             if synced {
                 println!("🔄 Credentials synchronized successfully");
+                if credentials_expiring && stored_credentials_expiring(config.task_base_home_dir) {
+                    println!("⚠️  The keychain credentials are expired too. Run `claude` on this machine to refresh them");
+                }
                 println!();
             } else if config.debug {
                 println!("✓ Credentials recently validated, skipping sync");
@@ -983,6 +2052,13 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
         workspace_path: workspace_path.clone(),
         ht_mcp_port: config.ht_mcp_port,
         web_view_proxy_port: config.web_view_proxy_port,
+        no_cache: config.no_cache,
+        pull: config.pull,
+        timezone: config
+            .docker_config
+            .timezone
+            .clone()
+            .unwrap_or_else(docker::host_timezone),
         ..ClaudeTaskConfig::default()
     };
 
@@ -1003,9 +2079,27 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
     }
 
     // Create volumes (npm and node cache)
-    docker_manager.create_volumes(&claude_config).await?;
+    if config.dry_run {
+        for (name, exists) in [
+            (
+                &config.docker_config.volumes.npm_cache,
+                preflight.npm_cache_volume_exists,
+            ),
+            (
+                &config.docker_config.volumes.node_cache,
+                preflight.node_cache_volume_exists,
+            ),
+        ] {
+            if !exists {
+                println!("📦 Would create volume '{name}'");
+            }
+        }
+    } else {
+        docker_manager.create_volumes(&preflight).await?;
+    }
 
     // Build image if requested, otherwise check if image exists
+    timer.start("image");
     if config.build {
         // Only validate Dockerfile paths when building
         if current_dir.join("claude-task/Dockerfile").exists() {
@@ -1034,50 +2128,141 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
                 "Dockerfile not found in ./claude-task/ or ../claude-task/\nMake sure you're running this from the correct directory."
             ));
         }
-        docker_manager.build_image(&claude_config).await?;
-    } else {
-        // Check if the image exists, if not suggest using --build
-        if docker_manager
-            .check_image_exists(&config.docker_config.image_name)
-            .await
-            .is_err()
+        if let Some(warning) = config
+            .docker_config
+            .platform
+            .as_deref()
+            .and_then(docker::platform_mismatch_warning)
         {
-            println!("⚠️  Image '{}' not found.", config.docker_config.image_name);
-            println!("   Use '--build' flag to build the image first, or build it manually:");
+            println!("⚠️  {warning}");
+        }
+        if config.debug {
+            println!("🔍 Build settings:");
+            println!("   - Dockerfile: {}", claude_config.dockerfile_path);
+            println!("   - Context: {}", claude_config.context_path);
+            println!("   - Tag: {}", config.docker_config.image_name);
+            println!("   - No cache: {}", claude_config.no_cache);
+            println!("   - Pull base image: {}", claude_config.pull);
+            if let Some(platform) = &config.docker_config.platform {
+                println!("   - Platform: {platform}");
+            }
+        }
+        if config.dry_run {
             println!(
-                "   docker build -t {} ./claude-task/",
+                "🔨 Would build image {} from {}",
+                config.docker_config.image_name, claude_config.context_path
+            );
+        } else {
+            docker_manager.build_image(&claude_config).await?;
+            reporter::emit(reporter::RunEvent::ImageBuilt {
+                image: config.docker_config.image_name.clone(),
+            });
+        }
+    } else {
+        // Check if the image exists (preferring the version-pinned tag), if not suggest using --build
+        if let Some(image) = &preflight.image {
+            println!("✓ Using existing image: {image}");
+            docker_manager.use_image(image);
+        } else if config.dry_run {
+            println!(
+                "⚠️  Image '{}' not found; a real run would fail until it is built or pulled",
                 config.docker_config.image_name
             );
+        } else {
+            println!("⚠️  Image '{}' not found.", config.docker_config.image_name);
+            if config::image_has_registry_host(&config.docker_config.image_name) {
+                println!("   Pull the image first:");
+                match &config.docker_config.platform {
+                    Some(platform) => println!(
+                        "   docker pull --platform {platform} {}",
+                        config.docker_config.image_name
+                    ),
+                    None => println!("   docker pull {}", config.docker_config.image_name),
+                }
+                println!("   Or use '--build' flag to build it locally.");
+            } else {
+                println!("   Use '--build' flag to build the image first, or build it manually:");
+                println!(
+                    "   docker build -t {} ./claude-task/",
+                    config.docker_config.image_name
+                );
+            }
             return Err(anyhow::anyhow!(
                 "Image '{}' not found. Use --build flag to build it.",
                 config.docker_config.image_name
             ));
         }
-        println!(
-            "✓ Using existing image: {}",
-            config.docker_config.image_name
-        );
+    }
+
+    if config.async_mode && config.log_file.is_some() {
+        println!("⚠️  --log-file is ignored for background tasks");
     }
 
     // Run Claude task
     let run_options = docker::RunTaskOptions {
         prompt: config.prompt.to_string(),
-        permission_prompt_tool: permission_tool_arg,
+        permission_mode,
+        permission_prompt_tool: config.approval_tool_permission.clone(),
         debug: config.debug,
         mcp_config: validated_mcp_config.clone(),
-        skip_permissions,
         async_mode: config.async_mode,
         oauth_token: config.claude_credentials.as_ref().map(|c| c.token.clone()),
+        log_file: config
+            .log_file
+            .as_deref()
+            .map(|log_file| task_log_path(log_file, &task_id))
+            .transpose()?,
+        timeout_seconds: config
+            .timeout_seconds
+            .or(config.docker_config.timeout_seconds),
+        claude_json: config.claude_json,
+        ssh_agent_socket: if config.ssh_agent {
+            match docker::ssh_agent_socket() {
+                Ok(socket) => {
+                    println!("🔑 Forwarding SSH agent into the container");
+                    Some(socket)
+                }
+                Err(e) => {
+                    println!("⚠️  --ssh-agent: {e:#}");
+                    println!("   Continuing without SSH agent forwarding");
+                    None
+                }
+            }
+        } else {
+            None
+        },
     };
 
+    if config.dry_run {
+        println!();
+        docker_manager
+            .print_dry_run(&claude_config, &run_options)
+            .await?;
+        return Ok(RunStatus::Succeeded);
+    }
+
+    timer.finish();
+    let run_started_at = std::time::Instant::now();
     let result = docker_manager
         .run_claude_task(&claude_config, &run_options)
         .await;
 
-    match result {
-        Ok(docker::TaskRunResult::Sync { output }) => {
+    let status = match result {
+        Ok(docker::TaskRunResult::Sync {
+            output,
+            container_start,
+            claude_result,
+        }) => {
             // Output was already streamed during execution
             let _ = output;
+            if let Some(ref result) = claude_result {
+                print_claude_result(result);
+            }
+            timer.record("container start", container_start);
+            timer.record(
+                "claude execution",
+                run_started_at.elapsed().saturating_sub(container_start),
+            );
 
             // Update validation timestamp on successful completion
             let sync_manager =
@@ -1092,6 +2277,9 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
             println!("✅ Claude task completed successfully!");
             println!("   Task ID: {task_id}");
             println!("   Shared volume: {}", config.docker_config.volumes.home);
+            println!();
+            timer.print_summary();
+            RunStatus::Succeeded
         }
         Ok(docker::TaskRunResult::Async {
             task_id: async_task_id,
@@ -1107,8 +2295,20 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
 
             // Note: For async tasks, we cannot update validation timestamp
             // as we don't know when/if they complete successfully
+            RunStatus::Background
         }
         Err(e) => {
+            if let Some(timeout) = e.downcast_ref::<docker::TaskTimeoutError>() {
+                if !timeout.output.is_empty() {
+                    println!(
+                        "   Claude's partial output ({} bytes) is shown above",
+                        timeout.output.len()
+                    );
+                }
+                println!("   Task ID: {task_id}");
+                return Err(e);
+            }
+
             // Check if this is a credential error
             let error_msg = e.to_string();
             if credential_sync::CredentialSyncManager::is_credential_error(&error_msg) {
@@ -1120,6 +2320,7 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
                     config.task_base_home_dir,
                     &task_id,
                 )?;
+                sync_manager.clear_validation()?;
 
                 sync_manager
                     .sync_credentials_if_needed(
@@ -1128,91 +2329,630 @@ async fn run_docker_task(config: TaskRunConfig<'_>) -> Result<()> {
                             let debug = config.debug;
                             let claude_user_config = config.claude_user_config.clone();
 
-                            async move {
-                                // Extract credentials directly
-                                let credentials =
-                                    credentials::extract_keychain_credentials().await?;
+                            async move {
+                                // Extract credentials directly
+                                let credentials =
+                                    credentials::extract_keychain_credentials(&claude_user_config)
+                                        .await?;
+
+                                // Setup the full configuration (including writing the credentials)
+                                setup_credentials_and_config_with_cache(
+                                    &task_base_home_dir,
+                                    debug,
+                                    &claude_user_config,
+                                    true,
+                                )
+                                .await?;
+
+                                // Return the credentials string for hashing
+                                Ok(credentials)
+                            }
+                        },
+                        config.debug,
+                    )
+                    .await?;
+
+                // Retry the task once
+                println!("🔄 Retrying task with refreshed credentials...");
+                let retry_result = docker_manager
+                    .run_claude_task(&claude_config, &run_options)
+                    .await?;
+
+                match retry_result {
+                    docker::TaskRunResult::Sync {
+                        output,
+                        container_start,
+                        claude_result,
+                    } => {
+                        let _ = output;
+                        if let Some(ref result) = claude_result {
+                            print_claude_result(result);
+                        }
+                        timer.record("container start", container_start);
+                        timer.record(
+                            "claude execution",
+                            run_started_at.elapsed().saturating_sub(container_start),
+                        );
+
+                        // Update validation timestamp on successful retry
+                        if let Err(e) = sync_manager.update_validation_timestamp() {
+                            if config.debug {
+                                println!("⚠️  Warning: Failed to update validation timestamp: {e}");
+                            }
+                        }
+
+                        println!("✅ Claude task completed successfully after retry!");
+                        println!("   Task ID: {task_id}");
+                        println!("   Shared volume: {}", config.docker_config.volumes.home);
+                        println!();
+                        timer.print_summary();
+                        RunStatus::Succeeded
+                    }
+                    docker::TaskRunResult::Async {
+                        task_id: async_task_id,
+                        container_id,
+                    } => {
+                        let _ = async_task_id;
+                        println!("\n📋 Task is running in background (after retry)");
+                        println!("   Container ID: {container_id}");
+                        println!("   Monitor logs: docker logs -f {container_id}");
+                        println!("   Stop task: docker stop {container_id}");
+                        println!("   Clean up: docker rm {container_id}");
+                        RunStatus::Background
+                    }
+                }
+            } else {
+                // Not a credential error, propagate it
+                return Err(e);
+            }
+        }
+    };
+
+    if let Some((worktree_path, base_commit)) = created_worktree {
+        if status == RunStatus::Succeeded {
+            match create_pull_request(&worktree_path, &base_commit, config.prompt, &task_id) {
+                Ok(Some(url)) => println!("🔀 Pull request: {url}"),
+                Ok(None) => {}
+                Err(e) => println!("⚠️  Warning: Failed to create pull request: {e:#}"),
+            }
+        }
+    }
+
+    Ok(status)
+}
+
+/// Summarize what Claude reported in its JSON result
+fn print_claude_result(result: &docker::ClaudeResult) {
+    println!("📊 Claude's result:");
+    if result.is_error {
+        println!("   ⚠️  Claude reported an error");
+    }
+    if let Some(turns) = result.num_turns {
+        println!("   Turns: {turns}");
+    }
+    println!(
+        "   Tokens: {} in, {} out ({} cache write, {} cache read)",
+        result.usage.input_tokens,
+        result.usage.output_tokens,
+        result.usage.cache_creation_input_tokens,
+        result.usage.cache_read_input_tokens
+    );
+    if let Some(cost) = result.total_cost_usd {
+        println!("   Cost: ${cost:.4}");
+    }
+    if !result.tool_uses.is_empty() {
+        let tools: Vec<String> = result
+            .tool_uses
+            .iter()
+            .map(|(name, count)| format!("{name} ×{count}"))
+            .collect();
+        println!("   Tools: {}", tools.join(", "));
+    }
+    if !result.permission_denials.is_empty() {
+        println!("   Denied: {}", result.permission_denials.join(", "));
+    }
+    if let Some(ref session_id) = result.session_id {
+        println!("   Session: {session_id}");
+    }
+}
+
+/// Title for a pull request opened with `--create-pr`: the prompt's first line,
+/// shortened to fit a title
+fn pr_title_from_prompt(prompt: &str) -> String {
+    const MAX_TITLE_CHARS: usize = 72;
+
+    let first_line = prompt
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("Claude task");
+    if first_line.chars().count() <= MAX_TITLE_CHARS {
+        return first_line.to_string();
+    }
+    let shortened: String = first_line.chars().take(MAX_TITLE_CHARS - 1).collect();
+    format!("{}…", shortened.trim_end())
+}
+
+/// Push the task's branch and open a pull request for it with `gh`.
+/// Returns the PR URL, or `None` if there's nothing to propose or `gh` isn't installed.
+fn create_pull_request(
+    worktree_path: &Path,
+    base_commit: &str,
+    prompt: &str,
+    task_id: &str,
+) -> Result<Option<String>> {
+    let status = worktree::check_worktree_status(worktree_path)?;
+    let branch = &status.current_branch;
+    if worktree::count_commits_since(worktree_path, base_commit)? == 0 {
+        println!("ℹ️  No commits on {branch}, skipping pull request");
+        return Ok(None);
+    }
+    if status.has_uncommitted_changes {
+        println!(
+            "⚠️  The worktree has uncommitted changes; they won't be part of the pull request"
+        );
+    }
+
+    if Command::new("gh").arg("--version").output().is_err() {
+        println!("⚠️  gh CLI not found, skipping pull request");
+        println!("   Install it from https://cli.github.com, then run:");
+        println!("   git push -u origin {branch} && gh pr create --head {branch}");
+        return Ok(None);
+    }
+
+    // Claude may have pushed the branch itself already
+    if status.has_no_remote || status.has_unpushed_commits {
+        println!("⬆️  Pushing {branch}...");
+        let push = Command::new("git")
+            .args(["push", "--set-upstream", "origin", branch])
+            .current_dir(worktree_path)
+            .output()
+            .context("Failed to execute git push")?;
+        if !push.status.success() {
+            anyhow::bail!(
+                "git push failed: {}",
+                String::from_utf8_lossy(&push.stderr).trim()
+            );
+        }
+    }
+
+    let quoted_prompt = prompt
+        .lines()
+        .map(|line| format!("> {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let body =
+        format!("Opened by claude-task for task `{task_id}`.\n\n## Prompt\n\n{quoted_prompt}\n");
+    let output = Command::new("gh")
+        .args([
+            "pr",
+            "create",
+            "--head",
+            branch,
+            "--title",
+            &pr_title_from_prompt(prompt),
+            "--body",
+            &body,
+        ])
+        .current_dir(worktree_path)
+        .output()
+        .context("Failed to execute gh pr create")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "gh pr create failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    // gh prints the new PR's URL as its last line
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout.lines().last().map(|line| line.trim().to_string()))
+}
+
+async fn clean_all_worktrees_and_volumes(
+    config: &Config,
+    runner: CleanRunner,
+    skip_confirmation: bool,
+    force: bool,
+    include_heuristic: bool,
+) -> Result<()> {
+    println!("🧹 Cleaning up all claude-task git worktrees and {runner} resources...");
+
+    // Clean worktrees
+    worktree::clean_all_worktrees(
+        &config.paths.branch_prefix,
+        skip_confirmation,
+        force,
+        config.worktree.auto_clean_on_remove,
+        include_heuristic,
+        None,
+    )
+    .await?;
+
+    match runner {
+        CleanRunner::Docker => clean_docker_resources(&config.docker, skip_confirmation).await?,
+        CleanRunner::Kubernetes => clean_kube_resources(config, skip_confirmation).await?,
+        CleanRunner::All => {
+            // Don't let an unavailable runner block cleaning the other one
+            if let Err(e) = clean_docker_resources(&config.docker, skip_confirmation).await {
+                println!("⚠️  Skipping Docker cleanup: {e}");
+            }
+            if let Err(e) = clean_kube_resources(config, skip_confirmation).await {
+                println!("⚠️  Skipping Kubernetes cleanup: {e}");
+            }
+        }
+    }
+
+    println!("\n✅ All clean up operations completed.");
+    Ok(())
+}
+
+async fn clean_docker_resources(
+    docker_config: &config::DockerConfig,
+    skip_confirmation: bool,
+) -> Result<()> {
+    println!("\n🐳 Removing Docker cache volumes...");
+    let docker_manager =
+        DockerManager::new(docker_config.clone()).context("Failed to create Docker manager")?;
+    docker_manager.ensure_daemon_available().await?;
+    let removed = docker_manager
+        .clean_shared_volumes(skip_confirmation, false)
+        .await?;
+    if removed.is_empty() {
+        println!("   No Docker volumes removed");
+    }
+    Ok(())
+}
+
+async fn clean_shared_volumes(
+    docker_config: &config::DockerConfig,
+    skip_confirmation: bool,
+) -> Result<()> {
+    println!("🐳 Removing all shared Docker volumes for Claude tasks...");
+    let docker_manager =
+        DockerManager::new(docker_config.clone()).context("Failed to create Docker manager")?;
+    docker_manager.ensure_daemon_available().await?;
+    let removed = docker_manager
+        .clean_shared_volumes(skip_confirmation, true)
+        .await?;
+    if removed.is_empty() {
+        println!("   No Docker volumes removed");
+    } else {
+        println!("\n✅ Removed {} volume(s)", removed.len());
+        println!("   Run 'claude-task setup' before the next task to restore credentials.");
+    }
+    Ok(())
+}
+
+async fn list_docker_volumes(docker_config: &config::DockerConfig) -> Result<()> {
+    let docker_manager =
+        DockerManager::new(docker_config.clone()).context("Failed to create Docker manager")?;
+    docker_manager.ensure_daemon_available().await?;
+
+    let volumes = docker_manager.list_claude_volumes().await?;
+    if volumes.is_empty() {
+        println!("No claude-task Docker volumes found.");
+        println!("   Run 'claude-task docker init' to create the shared volumes.");
+        return Ok(());
+    }
+
+    println!("🐳 Claude task Docker volumes:");
+    for volume in &volumes {
+        println!("\n📦 {}", volume.name);
+        println!("   Size: {}", volume.size);
+        if volume.labels.is_empty() {
+            println!("   Labels: (none)");
+        } else {
+            let labels: Vec<String> = volume
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            println!("   Labels: {}", labels.join(", "));
+        }
+    }
+    println!("\n{} volume(s) total", volumes.len());
+
+    Ok(())
+}
+
+async fn list_tasks(
+    config: &Config,
+    runner: &ExecutionEnvironment,
+    all: bool,
+    json: bool,
+) -> Result<()> {
+    let uptime = |seconds: Option<u64>| {
+        seconds
+            .map(|s| worktree::format_age(std::time::Duration::from_secs(s)))
+            .unwrap_or_else(|| "-".to_string())
+    };
+
+    match runner {
+        ExecutionEnvironment::Docker => {
+            let docker_manager = DockerManager::new(config.docker.clone())
+                .context("Failed to create Docker manager")?;
+            docker_manager.ensure_daemon_available().await?;
+            let containers = docker_manager.list_task_containers(all).await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&containers)?);
+            } else if containers.is_empty() {
+                println!("No claude-task containers running.");
+            } else {
+                println!("{:<24} {:<10} {:<8} STATUS", "TASK ID", "STATE", "UPTIME");
+                for container in &containers {
+                    println!(
+                        "{:<24} {:<10} {:<8} {}",
+                        container.task_id,
+                        container.state,
+                        uptime(container.uptime_seconds),
+                        container.status
+                    );
+                }
+            }
+        }
+        ExecutionEnvironment::Kubernetes => {
+            let namespace = config
+                .kube_config
+                .as_ref()
+                .and_then(|kc| kc.namespace.clone())
+                .ok_or_else(|| anyhow::anyhow!("No Kubernetes namespace configured"))?;
+            let k8s_runner = kube::KubernetesJobRunner::new()
+                .await
+                .context("Failed to connect to Kubernetes cluster")?;
+            let jobs = k8s_runner.list_task_jobs(&namespace).await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&jobs)?);
+            } else if jobs.is_empty() {
+                println!("No claude-task jobs in namespace '{namespace}'.");
+            } else {
+                println!("{:<24} {:<10} AGE", "TASK ID", "STATUS");
+                for job in &jobs {
+                    println!(
+                        "{:<24} {:<10} {}",
+                        job.task_id,
+                        job.status,
+                        uptime(job.uptime_seconds)
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Namespace for `kube` subcommands: the --namespace override or the configured one
+fn kube_command_namespace(config: &Config, namespace: Option<String>) -> Result<String> {
+    namespace
+        .or_else(|| {
+            config
+                .kube_config
+                .as_ref()
+                .and_then(|kc| kc.namespace.clone())
+        })
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No Kubernetes namespace configured. Pass --namespace or run 'ct setup kubernetes'"
+            )
+        })
+}
+
+async fn kube_status(config: &Config, namespace: Option<String>, json: bool) -> Result<()> {
+    let namespace = kube_command_namespace(config, namespace)?;
+    let k8s_runner = kube::KubernetesJobRunner::new()
+        .await
+        .context("Failed to connect to Kubernetes cluster")?;
+    let jobs = k8s_runner.list_task_jobs(&namespace).await?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&jobs)?);
+    } else if jobs.is_empty() {
+        println!("No claude-task jobs in namespace '{namespace}'.");
+    } else {
+        println!(
+            "{:<40} {:<8} {:<10} {:<8} AGE",
+            "NAME", "ACTIVE", "SUCCEEDED", "FAILED"
+        );
+        for job in &jobs {
+            let age = job
+                .uptime_seconds
+                .map(|s| worktree::format_age(std::time::Duration::from_secs(s)))
+                .unwrap_or_else(|| "-".to_string());
+            println!(
+                "{:<40} {:<8} {:<10} {:<8} {}",
+                job.name, job.active, job.succeeded, job.failed, age
+            );
+        }
+    }
+    Ok(())
+}
 
-                                // Setup the full configuration (including writing the credentials)
-                                setup_credentials_and_config_with_cache(
-                                    &task_base_home_dir,
-                                    debug,
-                                    &claude_user_config,
-                                    true,
-                                )
-                                .await?;
+async fn kube_clean(
+    config: &Config,
+    namespace: Option<String>,
+    all: bool,
+    skip_confirmation: bool,
+) -> Result<()> {
+    let namespace = kube_command_namespace(config, namespace)?;
+    let k8s_runner = kube::KubernetesJobRunner::new()
+        .await
+        .context("Failed to connect to Kubernetes cluster")?;
 
-                                // Return the credentials string for hashing
-                                Ok(credentials)
-                            }
-                        },
-                        config.debug,
-                    )
-                    .await?;
+    let finished: Vec<_> = k8s_runner
+        .list_task_jobs(&namespace)
+        .await?
+        .into_iter()
+        .filter(|job| job.status == "succeeded" || job.status == "failed")
+        .collect();
 
-                // Retry the task once
-                println!("🔄 Retrying task with refreshed credentials...");
-                let retry_result = docker_manager
-                    .run_claude_task(&claude_config, &run_options)
-                    .await?;
+    if finished.is_empty() && !all {
+        println!("No finished claude-task jobs in namespace '{namespace}'.");
+        return Ok(());
+    }
 
-                match retry_result {
-                    docker::TaskRunResult::Sync { output } => {
-                        let _ = output;
+    if !finished.is_empty() {
+        println!("☸️  Finished jobs in namespace '{namespace}':");
+        for job in &finished {
+            println!("   • {} ({})", job.name, job.status);
+        }
+    }
+    if all {
+        println!("   Temporary git/Claude secrets in the namespace will also be deleted.");
+    }
 
-                        // Update validation timestamp on successful retry
-                        if let Err(e) = sync_manager.update_validation_timestamp() {
-                            if config.debug {
-                                println!("⚠️  Warning: Failed to update validation timestamp: {e}");
-                            }
-                        }
+    if !skip_confirmation {
+        print!("   Are you sure you want to delete these resources? [y/N]: ");
+        use std::io::{self, Write};
+        io::stdout().flush().context("Failed to flush stdout")?;
+
+        let mut input = String::new();
+        io::stdin()
+            .read_line(&mut input)
+            .context("Failed to read input")?;
+        if input.trim().to_lowercase() != "y" {
+            println!("   Cleanup cancelled.");
+            return Ok(());
+        }
+    }
 
-                        println!("✅ Claude task completed successfully after retry!");
-                        println!("   Task ID: {task_id}");
-                        println!("   Shared volume: {}", config.docker_config.volumes.home);
-                    }
-                    docker::TaskRunResult::Async {
-                        task_id: async_task_id,
-                        container_id,
-                    } => {
-                        let _ = async_task_id;
-                        println!("\n📋 Task is running in background (after retry)");
-                        println!("   Container ID: {container_id}");
-                        println!("   Monitor logs: docker logs -f {container_id}");
-                        println!("   Stop task: docker stop {container_id}");
-                        println!("   Clean up: docker rm {container_id}");
-                    }
-                }
-            } else {
-                // Not a credential error, propagate it
-                return Err(e);
+    let mut deleted_jobs = 0;
+    for job in &finished {
+        match k8s_runner.cleanup_job(&namespace, &job.name).await {
+            Ok(()) => {
+                println!("   ✓ Deleted job '{}'", job.name);
+                deleted_jobs += 1;
             }
+            Err(e) => eprintln!("   ⚠️  {e:#}"),
         }
     }
 
+    let deleted_secrets = if all {
+        k8s_runner.delete_temporary_secrets(&namespace).await?.len()
+    } else {
+        0
+    };
+
+    println!(
+        "✅ Deleted {deleted_jobs} job(s){}",
+        if all {
+            format!(" and {deleted_secrets} secret(s)")
+        } else {
+            String::new()
+        }
+    );
     Ok(())
 }
 
-async fn clean_all_worktrees_and_volumes(
-    branch_prefix: &str,
-    skip_confirmation: bool,
-    force: bool,
-    _docker_config: &config::DockerConfig,
-    auto_clean_branch: bool,
+async fn show_task_logs(
+    config: &Config,
+    task_id: &str,
+    runner: &ExecutionEnvironment,
+    follow: bool,
+    debug: bool,
 ) -> Result<()> {
-    println!("🧹 Cleaning up all claude-task git worktrees and Docker volumes...");
+    match runner {
+        ExecutionEnvironment::Docker => {
+            let docker_manager = DockerManager::new(config.docker.clone())
+                .context("Failed to create Docker manager")?;
+            docker_manager.ensure_daemon_available().await?;
+            docker_manager
+                .print_task_logs(task_id, follow, debug)
+                .await?;
+        }
+        ExecutionEnvironment::Kubernetes => {
+            let namespace = config
+                .kube_config
+                .as_ref()
+                .and_then(|kc| kc.namespace.clone())
+                .ok_or_else(|| anyhow::anyhow!("No Kubernetes namespace configured"))?;
+            let k8s_runner = kube::KubernetesJobRunner::new()
+                .await
+                .context("Failed to connect to Kubernetes cluster")?;
+            k8s_runner
+                .print_job_logs(&namespace, &format!("claude-task-{task_id}"), follow, debug)
+                .await?;
+        }
+    }
+    Ok(())
+}
 
-    // Clean worktrees
-    worktree::clean_all_worktrees(branch_prefix, skip_confirmation, force, auto_clean_branch)
-        .await?;
+async fn exec_in_task(
+    config: &Config,
+    task_id: &str,
+    command: Vec<String>,
+    runner: &ExecutionEnvironment,
+) -> Result<()> {
+    if runner == &ExecutionEnvironment::Kubernetes {
+        let namespace = config
+            .kube_config
+            .as_ref()
+            .and_then(|kc| kc.namespace.clone())
+            .unwrap_or_else(|| "<namespace>".to_string());
+        anyhow::bail!(
+            "exec is only supported for Docker tasks. For Kubernetes jobs use:\n   kubectl exec -it job/claude-task-{task_id} -n {namespace} -- /bin/bash"
+        );
+    }
 
-    // Clean Docker volumes TODO: add this back in
-    // docker::clean_shared_volumes(false, docker_config).await?;
+    let command = if command.is_empty() {
+        vec!["/bin/bash".to_string()]
+    } else {
+        command
+    };
 
-    println!("\n✅ All clean up operations completed.");
+    let docker_manager =
+        DockerManager::new(config.docker.clone()).context("Failed to create Docker manager")?;
+    docker_manager.ensure_daemon_available().await?;
+    let exit_code = docker_manager.exec_in_task(task_id, command).await?;
+    if exit_code != 0 {
+        anyhow::bail!("Command exited with status {exit_code}");
+    }
+    Ok(())
+}
+
+async fn stop_task(config: &Config, task_id: &str, runner: &ExecutionEnvironment) -> Result<()> {
+    match runner {
+        ExecutionEnvironment::Docker => {
+            let docker_manager = DockerManager::new(config.docker.clone())
+                .context("Failed to create Docker manager")?;
+            docker_manager.ensure_daemon_available().await?;
+            let container_name = docker_manager.container_name(task_id);
+            match docker_manager.stop_task(task_id).await? {
+                StopOutcome::Stopped => println!("🛑 Stopped container '{container_name}'"),
+                StopOutcome::NotRunning => {
+                    println!("ℹ️  Container '{container_name}' is not running")
+                }
+                StopOutcome::NotFound => {
+                    anyhow::bail!("No such container '{container_name}' for task '{task_id}'")
+                }
+            }
+        }
+        ExecutionEnvironment::Kubernetes => {
+            let namespace = config
+                .kube_config
+                .as_ref()
+                .and_then(|kc| kc.namespace.clone())
+                .ok_or_else(|| anyhow::anyhow!("No Kubernetes namespace configured"))?;
+            let job_name = format!("claude-task-{task_id}");
+            let k8s_runner = kube::KubernetesJobRunner::new()
+                .await
+                .context("Failed to connect to Kubernetes cluster")?;
+            if k8s_runner.delete_job(&namespace, &job_name).await? {
+                println!("🛑 Deleted job '{job_name}' in namespace '{namespace}'");
+            } else {
+                anyhow::bail!("No such job '{job_name}' in namespace '{namespace}'");
+            }
+        }
+    }
     Ok(())
 }
 
+async fn clean_kube_resources(config: &Config, skip_confirmation: bool) -> Result<()> {
+    println!("\n☸️  Removing finished claude-task jobs and temporary secrets...");
+    kube_clean(config, None, true, skip_confirmation).await
+}
+
 async fn handle_docker_setup(
     task_base_home_dir: &str,
     debug: bool,
@@ -1229,7 +2969,7 @@ async fn handle_docker_setup(
         println!("   Then add it to your config.json under claudeCredentials.token");
 
         // Create minimal directory structure for token auth
-        let base_dir = Config::expand_tilde(task_base_home_dir);
+        let base_dir = Config::expand_tilde(task_base_home_dir)?;
         let claude_dir = base_dir.join(".claude");
         std::fs::create_dir_all(&claude_dir)?;
 
@@ -1237,7 +2977,7 @@ async fn handle_docker_setup(
         std::fs::write(claude_dir.join(".credentials.json"), "{}")?;
 
         // Copy user memory if it exists
-        let user_memory_path = Config::expand_tilde(&claude_user_config.user_memory_path);
+        let user_memory_path = Config::expand_tilde(&claude_user_config.user_memory_path)?;
         if user_memory_path.exists() {
             let dest_path = claude_dir.join("CLAUDE.md");
             std::fs::copy(&user_memory_path, &dest_path)?;
@@ -1271,6 +3011,7 @@ async fn handle_kubernetes_setup(
     claude_user_config: &config::ClaudeUserConfig,
     claude_credentials: &Option<config::ClaudeCredentials>,
     kube_config: &Option<config::KubeConfig>,
+    config_path: Option<&PathBuf>,
 ) -> Result<()> {
     use dialoguer::Confirm;
 
@@ -1322,7 +3063,10 @@ async fn handle_kubernetes_setup(
         println!("The following resources will be created:");
         println!("   - Namespace (if needed)");
         println!("   - Secrets for Git and Claude credentials");
-        println!("   - Image pull secret for ghcr.io");
+        println!(
+            "   - Image pull secret for {}",
+            config::image_registry_host(&kube_config.image)
+        );
         println!();
         println!("⚠️  Please ensure you have appropriate permissions in this cluster.");
         println!();
@@ -1341,8 +3085,8 @@ async fn handle_kubernetes_setup(
         kube_config.namespace_confirmed = true;
 
         // Save the updated config
-        let config_path = Config::default_config_path();
-        let mut full_config = Config::load(Some(&config_path))?;
+        let config_path = Config::resolve_path(config_path)?;
+        let mut full_config = Config::load_for_update(Some(&config_path))?;
         if let Some(ref mut kc) = full_config.kube_config {
             kc.context = Some(final_context.clone());
             kc.namespace = Some(final_namespace.clone());
@@ -1359,7 +3103,7 @@ async fn handle_kubernetes_setup(
 
     // First, ensure credentials are available (either token or extracted)
     println!("📋 Ensuring Claude credentials are available...");
-    let home_volume_path = Config::expand_tilde(task_base_home_dir);
+    let home_volume_path = Config::expand_tilde(task_base_home_dir)?;
 
     if let Some(_credentials) = claude_credentials {
         println!("   ✓ Using long-lived token from config");
@@ -1402,46 +3146,9 @@ async fn handle_kubernetes_setup(
     println!("\n📁 Ensuring namespace '{final_namespace}' exists...");
     k8s_runner.create_namespace(&final_namespace).await?;
 
-    // Create ghcr-pull-secret if it doesn't exist
-    if let Some(ref pull_secret_name) = kube_config.image_pull_secret {
-        println!("\n🔐 Setting up image pull secret '{pull_secret_name}'...");
-
-        // Check if we have GitHub token from environment or gh CLI
-        if let Some(github_token) = get_github_token() {
-            // Try to get username from GITHUB_USERNAME or fall back to system username
-            let github_username = std::env::var("GITHUB_USERNAME")
-                .or_else(|_| std::env::var("USER"))
-                .unwrap_or_else(|_| "unknown".to_string());
-
-            println!("   Using GitHub username: {github_username}");
-            println!(
-                "   Token source: {}",
-                if std::env::var("GITHUB_TOKEN").is_ok() {
-                    "GITHUB_TOKEN env var"
-                } else {
-                    "gh CLI"
-                }
-            );
-            k8s_runner
-                .create_docker_registry_secret(
-                    &final_namespace,
-                    pull_secret_name,
-                    "ghcr.io",
-                    &github_username,
-                    &github_token,
-                )
-                .await?;
-        } else {
-            println!(
-                "   ⚠️  Please ensure '{pull_secret_name}' secret exists for pulling images from GHCR"
-            );
-            println!("   Create with: kubectl create secret docker-registry {pull_secret_name} \\");
-            println!("     --docker-server=ghcr.io \\");
-            println!("     --docker-username=YOUR_GITHUB_USERNAME \\");
-            println!("     --docker-password=YOUR_GITHUB_TOKEN \\");
-            println!("     -n {final_namespace}");
-        }
-    }
+    // Create the image pull secret if it doesn't exist
+    println!();
+    ensure_image_pull_secret(&k8s_runner, &kube_config, &final_namespace).await?;
 
     // Create git credentials secret
     println!("\n🔑 Git credentials secret...");
@@ -1499,16 +3206,56 @@ async fn handle_kubernetes_setup(
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // Switch before anything is printed so stdout only carries events
+    let run_output = match &cli.command {
+        Some(Commands::Run { output, .. }) => output.or(cli.output),
+        _ if cli.output.is_some() => anyhow::bail!("--output only applies to the run command"),
+        _ => None,
+    };
+    if run_output == Some(reporter::OutputFormat::Json) {
+        reporter::enable_json_output()?;
+    }
+
     // Handle config command first as it doesn't need the config loaded
     if let Some(Commands::Config { command }) = &cli.command {
         handle_config_command(command.clone(), cli.config_path.as_ref()).await?;
         return Ok(());
     }
 
+    // Completion scripts go to stdout, so nothing else may be printed
+    if let Some(Commands::Completion { shell }) = &cli.command {
+        let mut command = Cli::command();
+        let bin_name = command.get_name().to_string();
+        clap_complete::generate(*shell, &mut command, bin_name, &mut std::io::stdout());
+        return Ok(());
+    }
+
     // Load configuration for other commands
-    let config = Config::load(cli.config_path.as_ref())?;
+    let repo_dir = if cli.no_repo_config {
+        None
+    } else {
+        Some(std::env::current_dir().context("Could not get current directory")?)
+    };
+    let effective = Config::load_effective(
+        cli.config_path.as_ref(),
+        cli.profile.as_deref(),
+        repo_dir.as_deref(),
+    )?;
+    if cli.debug {
+        eprintln!("🔍 Config sources: {}", effective.sources.join(" < "));
+        if !effective.env_overrides.is_empty() {
+            eprintln!(
+                "🔍 Config overridden by environment: {}",
+                effective.env_overrides.join(", ")
+            );
+        }
+    }
+    let mut config = effective.config;
 
     // Override config with CLI args if provided
+    if cli.no_biometrics {
+        config.claude_user_config.use_biometrics = false;
+    }
     let debug = if cli.debug {
         true
     } else {
@@ -1523,28 +3270,72 @@ async fn main() -> Result<()> {
     match cli.command {
         Some(Commands::Run {
             prompt,
+            prompt_file,
             task_id,
+            from_file,
+            concurrency,
             build,
+            no_cache,
+            pull,
+            mounts,
+            env,
+            ssh_agent,
+            network,
+            platform,
             workspace_dir,
+            no_worktree,
+            base_ref,
+            carry_changes,
+            carry_untracked,
+            recurse_submodules,
             approval_tool_permission,
+            permission_mode,
             mcp_config,
             yes,
             open_editor,
             ht_mcp_port,
             web_view_proxy_port,
             async_mode,
+            log_file,
+            timeout,
+            dry_run,
+            create_pr,
+            claude_json,
+            notify,
+            memory,
+            cpus,
             execution_env,
             kube_namespace,
             kube_context,
+            kube_cpu,
+            kube_memory,
+            retries,
+            pull_policy,
             git_secret_name,
             git_secret_key,
+            output: _,
         }) => {
+            let prompt = match from_file {
+                Some(_) => String::new(),
+                None => resolve_prompt(prompt, prompt_file, std::io::stdin().lock())?,
+            };
+
             // Override execution environment if specified
             let exec_env = execution_env.as_ref().unwrap_or(&config.task_runner);
 
+            // Only the manifest goes to stdout, so it can be piped into `kubectl apply -f -`
+            if dry_run && exec_env == &ExecutionEnvironment::Kubernetes {
+                reporter::enable_manifest_output()?;
+            }
+
             // Override kubernetes config if needed
             let mut kube_config_override = config.kube_config.clone();
             if exec_env == &ExecutionEnvironment::Kubernetes {
+                if memory.is_some() || cpus.is_some() {
+                    println!(
+                        "⚠️  --memory and --cpus are ignored for Kubernetes tasks; use --kube-memory and --kube-cpu"
+                    );
+                }
                 if let Some(ref mut kube_cfg) = kube_config_override {
                     if let Some(ref namespace) = kube_namespace {
                         kube_cfg.namespace = Some(namespace.clone());
@@ -1562,16 +3353,98 @@ async fn main() -> Result<()> {
                         git_secret_key: "token".to_string(),
                         image_pull_secret: Some("ghcr-pull-secret".to_string()),
                         namespace_confirmed: false,
+                        entrypoint: None,
+                        claude_binary: None,
+                        resources: config::KubeResources::default(),
+                        clone_resources: None,
+                        node_selector: None,
+                        tolerations: None,
+                        default_timeout_seconds: None,
+                        workspace_pvc: None,
+                        backoff_limit: None,
+                        image_pull_policy: None,
+                        service_account_name: None,
+                        security_context: config::KubeSecurityContext::default(),
+                        environment_variables: std::collections::HashMap::new(),
+                        registry_credentials: None,
                     });
                 }
+
+                if let Some(ref mut kube_cfg) = kube_config_override {
+                    if let Some(cpu) = kube_cpu {
+                        kube_cfg.resources.set_cpu_limit(cpu)?;
+                    }
+                    if let Some(memory) = kube_memory {
+                        kube_cfg.resources.set_memory_limit(memory)?;
+                    }
+                    if retries.is_some() {
+                        kube_cfg.backoff_limit = retries;
+                    }
+                    if pull_policy.is_some() {
+                        kube_cfg.image_pull_policy = pull_policy;
+                    }
+                    kube_cfg.environment_variables.extend(env.iter().cloned());
+                    kube_cfg.validate_environment_variables()?;
+                    kube_cfg.resources.validate()?;
+                }
+            }
+
+            // Override container resource limits if specified
+            let mut docker_config_override = config.docker.clone();
+            if let Some(memory) = memory {
+                docker_config_override.resources.memory = Some(config::MemoryLimit::Bytes(memory));
+            }
+            if let Some(cpus) = cpus {
+                docker_config_override.resources.cpus = Some(cpus);
+            }
+            if let Some(platform) = platform {
+                config::validate_platform(&platform)?;
+                docker_config_override.platform = Some(platform);
+            }
+            docker_config_override.extra_mounts.extend(mounts);
+            docker_config_override.environment_variables.extend(env);
+            if network.is_some() {
+                docker_config_override.network_mode = network;
             }
+            docker_config_override.resources.validate()?;
+
+            let notify_webhook = if notify || config.global_option_defaults.notify {
+                match &config.notifications {
+                    Some(notifications) => Some(notifications.webhook_url.clone()),
+                    None => {
+                        println!(
+                            "⚠️  --notify is set but notifications.webhookUrl is not configured"
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            // --no-worktree is shorthand for --workspace-dir without a value
+            let workspace_dir = if no_worktree {
+                Some(None)
+            } else {
+                workspace_dir
+            };
 
             let task_config = TaskRunConfig {
                 prompt: &prompt,
                 task_id: task_id.clone(),
-                build,
+                build: build || no_cache || pull,
+                no_cache,
+                pull,
                 workspace_dir: workspace_dir.clone(),
+                base_ref: base_ref.clone(),
+                carry_changes: match (carry_changes, carry_untracked) {
+                    (false, _) => worktree::CarryChanges::None,
+                    (true, false) => worktree::CarryChanges::Tracked,
+                    (true, true) => worktree::CarryChanges::All,
+                },
+                recurse_submodules,
                 approval_tool_permission: approval_tool_permission.clone(),
+                permission_mode,
                 debug,
                 mcp_config: mcp_config.clone(),
                 skip_confirmation: yes,
@@ -1582,7 +3455,7 @@ async fn main() -> Result<()> {
                 ht_mcp_port,
                 web_view_proxy_port,
                 require_ht_mcp,
-                docker_config: &config.docker,
+                docker_config: &docker_config_override,
                 claude_user_config: &config.claude_user_config,
                 worktree_config: &config.worktree,
                 async_mode,
@@ -1591,9 +3464,24 @@ async fn main() -> Result<()> {
                 git_secret_name: git_secret_name.clone(),
                 git_secret_key: git_secret_key.clone(),
                 claude_credentials: &config.claude_credentials,
+                id_generator: &config.id_generator,
+                log_file: log_file.or_else(|| config.global_option_defaults.log_file.clone()),
+                timeout_seconds: timeout,
+                dry_run,
+                create_pr,
+                ssh_agent,
+                claude_json,
+                notify_webhook,
+                history: &config.history,
+                config_path: cli.config_path.as_ref(),
+            };
+
+            let result = match from_file {
+                Some(ref path) => run_batch_tasks(path, task_config, concurrency).await,
+                None => run_claude_task(task_config).await.map(|_| ()),
             };
 
-            if let Err(e) = run_claude_task(task_config).await {
+            if let Err(e) = result {
                 eprintln!("❌ Error running task: {e:#?}");
                 // Print the full error chain
                 let mut source = e.source();
@@ -1605,21 +3493,40 @@ async fn main() -> Result<()> {
             }
         }
         Some(Commands::Worktree { command }) => match command {
-            WorktreeCommands::Create { task_id } => {
+            WorktreeCommands::Create {
+                task_id,
+                branch,
+                base_ref,
+                recurse_submodules,
+            } => {
                 worktree::create_git_worktree(
                     &task_id,
                     &config.paths.branch_prefix,
                     &config.paths.worktree_base_dir,
+                    &worktree::WorktreeCreateOptions {
+                        branch: branch.as_deref(),
+                        base_ref: base_ref.as_deref(),
+                        recurse_submodules: recurse_submodules
+                            || config.worktree.recurse_submodules,
+                        post_create_hook: config.worktree.post_create_hook.as_deref(),
+                        ..Default::default()
+                    },
                 )?;
             }
-            WorktreeCommands::List => {
-                worktree::list_git_worktrees(&config.paths.branch_prefix)?;
+            WorktreeCommands::List {
+                json,
+                with_size,
+                only,
+            } => {
+                worktree::list_git_worktrees(&config.paths.branch_prefix, json, with_size, only)?;
             }
-            WorktreeCommands::Remove { task_id } => {
+            WorktreeCommands::Remove { task_id, archive } => {
+                let archive_dir = archive.as_deref().map(Config::expand_tilde).transpose()?;
                 worktree::remove_git_worktree(
                     &task_id,
                     &config.paths.branch_prefix,
                     config.worktree.auto_clean_on_remove,
+                    archive_dir.as_deref(),
                 )?;
             }
             WorktreeCommands::Open => {
@@ -1628,12 +3535,36 @@ async fn main() -> Result<()> {
                     config.worktree.default_open_command.as_deref(),
                 )?;
             }
-            WorktreeCommands::Clean { yes, force } => {
+            WorktreeCommands::Diff { task_id, no_pager } => {
+                worktree::diff_git_worktree(&task_id, &config.paths.branch_prefix, no_pager)?;
+            }
+            WorktreeCommands::Lock { task_id, reason } => {
+                worktree::lock_git_worktree(
+                    &task_id,
+                    &config.paths.branch_prefix,
+                    true,
+                    reason.as_deref(),
+                )?;
+            }
+            WorktreeCommands::Unlock { task_id } => {
+                worktree::lock_git_worktree(&task_id, &config.paths.branch_prefix, false, None)?;
+            }
+            WorktreeCommands::Rename { task_id, new_id } => {
+                worktree::rename_git_worktree(&task_id, &new_id, &config.paths.branch_prefix)?;
+            }
+            WorktreeCommands::Clean {
+                yes,
+                force,
+                include_heuristic,
+                older_than,
+            } => {
                 worktree::clean_all_worktrees(
                     &config.paths.branch_prefix,
                     yes,
                     force,
                     config.worktree.auto_clean_on_remove,
+                    include_heuristic,
+                    older_than,
                 )
                 .await?;
             }
@@ -1652,10 +3583,10 @@ async fn main() -> Result<()> {
                 // .await?;
             }
             DockerCommands::List => {
-                // list_docker_volumes(&config.docker).await?;
+                list_docker_volumes(&config.docker).await?;
             }
-            DockerCommands::Clean => {
-                // clean_shared_volumes(debug, &config.docker).await?;
+            DockerCommands::Clean { yes } => {
+                clean_shared_volumes(&config.docker, yes).await?;
             }
         },
         Some(Commands::Config { .. }) => {
@@ -1679,22 +3610,105 @@ async fn main() -> Result<()> {
                     &config.claude_user_config,
                     &config.claude_credentials,
                     &config.kube_config,
+                    cli.config_path.as_ref(),
                 )
                 .await?;
             }
         },
-        Some(Commands::Clean { yes, force }) => {
-            clean_all_worktrees_and_volumes(
-                &config.paths.branch_prefix,
+        Some(Commands::Clean {
+            yes,
+            force,
+            include_heuristic,
+            runner,
+        }) => {
+            let runner = runner.unwrap_or(match config.task_runner {
+                ExecutionEnvironment::Docker => CleanRunner::Docker,
+                ExecutionEnvironment::Kubernetes => CleanRunner::Kubernetes,
+            });
+            clean_all_worktrees_and_volumes(&config, runner, yes, force, include_heuristic).await?;
+        }
+        Some(Commands::Ps {
+            all,
+            json,
+            execution_env,
+        }) => {
+            let exec_env = execution_env.as_ref().unwrap_or(&config.task_runner);
+            list_tasks(&config, exec_env, all, json).await?;
+        }
+        Some(Commands::Logs {
+            task_id,
+            follow,
+            execution_env,
+        }) => {
+            let exec_env = execution_env.as_ref().unwrap_or(&config.task_runner);
+            show_task_logs(&config, &task_id, exec_env, follow, debug).await?;
+        }
+        Some(Commands::Exec {
+            task_id,
+            command,
+            execution_env,
+        }) => {
+            let exec_env = execution_env.as_ref().unwrap_or(&config.task_runner);
+            exec_in_task(&config, &task_id, command, exec_env).await?;
+        }
+        Some(Commands::Kube { command }) => match command {
+            KubeCommands::Status { namespace, json } => {
+                kube_status(&config, namespace, json).await?;
+            }
+            KubeCommands::Clean {
+                namespace,
+                all,
                 yes,
-                force,
-                &config.docker,
-                config.worktree.auto_clean_on_remove,
-            )
-            .await?;
+            } => {
+                kube_clean(&config, namespace, all, yes).await?;
+            }
+        },
+        Some(Commands::Stop {
+            task_id,
+            execution_env,
+        }) => {
+            let exec_env = execution_env.as_ref().unwrap_or(&config.task_runner);
+            stop_task(&config, &task_id, exec_env).await?;
         }
         Some(Commands::Mcp) => {
-            mcp::run_mcp_server().await?;
+            mcp::run_mcp_server(mcp::ConfigOptions {
+                config_path: cli.config_path.clone(),
+                profile: cli.profile.clone(),
+                repo_dir,
+            })
+            .await?;
+        }
+        Some(Commands::History {
+            limit,
+            since,
+            status,
+            grep,
+            json,
+            command,
+        }) => match command {
+            Some(HistoryCommands::Show { task_id }) => {
+                history::print_record_details(&task_id)?;
+            }
+            None => {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_secs();
+                let filter = history::HistoryFilter {
+                    since: since
+                        .map(|since| history::parse_since(&since, now))
+                        .transpose()?,
+                    status,
+                    prompt_contains: grep,
+                };
+                history::print_history(limit, &filter, json)?;
+            }
+        },
+        Some(Commands::Completion { .. }) => {
+            // Already handled above
+            unreachable!("Completion command should have been handled earlier");
+        }
+        Some(Commands::Doctor) => {
+            doctor::run_doctor(&config).await?;
         }
         Some(Commands::Version) => {
             println!("claude-task version: {}", config.version);
@@ -1707,3 +3721,125 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_prompt() {
+        let no_stdin = std::io::empty();
+
+        assert_eq!(
+            resolve_prompt(Some("Fix the bug".to_string()), None, no_stdin).unwrap(),
+            "Fix the bug"
+        );
+
+        // "-" reads stdin, from either the argument or --prompt-file
+        let piped = "Refactor the parser.\n\nKeep the public API.\n".as_bytes();
+        assert_eq!(
+            resolve_prompt(Some("-".to_string()), None, piped).unwrap(),
+            "Refactor the parser.\n\nKeep the public API."
+        );
+        assert_eq!(
+            resolve_prompt(None, Some(PathBuf::from("-")), "from stdin".as_bytes()).unwrap(),
+            "from stdin"
+        );
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let prompt_path = temp_dir.path().join("prompt.md");
+        std::fs::write(&prompt_path, "# Task\nUpgrade dependencies\n").unwrap();
+        assert_eq!(
+            resolve_prompt(None, Some(prompt_path), std::io::empty()).unwrap(),
+            "# Task\nUpgrade dependencies"
+        );
+
+        assert!(resolve_prompt(None, None, std::io::empty()).is_err());
+        assert!(resolve_prompt(
+            None,
+            Some(temp_dir.path().join("missing.md")),
+            std::io::empty()
+        )
+        .is_err());
+        assert!(resolve_prompt(Some("-".to_string()), None, "  \n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_batch_summary_counts_each_status() {
+        use RunStatus::*;
+
+        assert_eq!(
+            batch_summary(&[Succeeded, Failed, Succeeded]),
+            "Batch summary: 3 tasks, 2 succeeded, 1 failed"
+        );
+        assert_eq!(
+            batch_summary(&[Succeeded, Background, Background, Cancelled, Failed]),
+            "Batch summary: 5 tasks, 1 succeeded, 1 failed, 2 running in the background, 1 cancelled"
+        );
+    }
+
+    #[test]
+    fn test_completion_scripts() {
+        for shell in [
+            clap_complete::Shell::Bash,
+            clap_complete::Shell::Zsh,
+            clap_complete::Shell::Fish,
+            clap_complete::Shell::PowerShell,
+        ] {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "claude-task", &mut script);
+            let script = String::from_utf8(script).unwrap();
+            assert!(
+                script.contains("worktree"),
+                "{shell} script lacks subcommands"
+            );
+            assert!(
+                script.contains("kubernetes"),
+                "{shell} script lacks runner values"
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_before_subcommand() {
+        use reporter::OutputFormat;
+
+        let cli = Cli::try_parse_from(["ct", "--output", "json", "run", "Fix it"]).unwrap();
+        assert_eq!(cli.output, Some(OutputFormat::Json));
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Run { output: None, .. })
+        ));
+
+        let cli = Cli::try_parse_from(["ct", "run", "Fix it", "--output", "json"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Run {
+                output: Some(OutputFormat::Json),
+                ..
+            })
+        ));
+
+        // `config schema --output` still takes a path
+        let cli =
+            Cli::try_parse_from(["ct", "config", "schema", "--output", "schema.json"]).unwrap();
+        assert_eq!(cli.output, None);
+    }
+
+    #[test]
+    fn test_pr_title_from_prompt() {
+        assert_eq!(
+            pr_title_from_prompt("Fix the login bug"),
+            "Fix the login bug"
+        );
+        assert_eq!(
+            pr_title_from_prompt("\n  Add retries  \nwith more detail below"),
+            "Add retries"
+        );
+        assert_eq!(pr_title_from_prompt("   "), "Claude task");
+
+        let title = pr_title_from_prompt(&"word ".repeat(30));
+        assert_eq!(title.chars().count(), 72);
+        assert!(title.ends_with('…'));
+    }
+}
@@ -4,9 +4,11 @@ use anyhow::Result;
 use rmcp::{
     handler::server::{router::tool::ToolRouter, tool::Parameters},
     model::*,
-    schemars, tool, tool_handler, tool_router,
+    schemars,
+    service::RequestContext,
+    tool, tool_handler, tool_router,
     transport::stdio,
-    Error as McpError, ServerHandler, ServiceExt,
+    Error as McpError, RoleServer, ServerHandler, ServiceExt,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -15,9 +17,15 @@ use tracing_subscriber::{self, EnvFilter};
 
 use claude_task::permission::ApprovalToolPermission;
 
+use crate::config::{Config, ExecutionEnvironment, RedactedConfig};
+
+/// URI of the resource holding the effective config
+const CONFIG_RESOURCE_URI: &str = "claude-task://config";
+
 // Import internal functions from the main module
 use crate::worktree::{
-    check_worktree_status, clean_all_worktrees, create_git_worktree, remove_git_worktree,
+    check_worktree_status, clean_all_worktrees, create_git_worktree, get_worktree_infos, parse_age,
+    remove_git_worktree, WorktreeCreateOptions, WorktreeInfo,
 };
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
@@ -59,6 +67,9 @@ pub struct CleanWorktreeOptions {
     #[serde(flatten)]
     pub global_options: GlobalOptions,
     pub force: Option<bool>,
+    pub include_heuristic: Option<bool>,
+    /// Only remove worktrees inactive for longer than this (e.g. "7d", "48h")
+    pub older_than: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
@@ -94,6 +105,12 @@ pub struct RunTaskOptions {
     pub web_view_proxy_port: Option<u16>,
     pub ht_mcp_port: Option<u16>,
     pub async_mode: Option<bool>,
+    /// Where to run the task: "docker" or "kubernetes" (default: the configured task runner)
+    pub execution_env: Option<String>,
+    /// Kubernetes namespace to run the job in (overrides config)
+    pub kube_namespace: Option<String>,
+    /// Kubernetes context to use (overrides config)
+    pub kube_context: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
@@ -101,8 +118,71 @@ pub struct CleanOptions {
     #[serde(flatten)]
     pub global_options: GlobalOptions,
     pub force: Option<bool>,
+    pub include_heuristic: Option<bool>,
+    pub runner: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetConfigOptions {}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetTaskStatusOptions {
+    /// Task ID returned by run_task
+    pub task_id: String,
+    /// Where the task runs: "docker" or "kubernetes" (default: the configured task runner)
+    pub execution_env: Option<String>,
+    /// Kubernetes namespace of the job (default: kubeConfig.namespace)
+    pub kube_namespace: Option<String>,
+    /// Number of log lines to return from the end (default: 200)
+    pub tail: Option<usize>,
+}
+
+/// State of a task started by run_task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum TaskState {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    NotFound,
+}
+
+impl TaskState {
+    fn as_str(self) -> &'static str {
+        match self {
+            TaskState::Pending => "pending",
+            TaskState::Running => "running",
+            TaskState::Succeeded => "succeeded",
+            TaskState::Failed => "failed",
+            TaskState::NotFound => "not_found",
+        }
+    }
+
+    /// Map a Docker container state and exit code to a task state
+    fn from_container(state: &str, exit_code: Option<i64>) -> Self {
+        match state {
+            "created" => TaskState::Pending,
+            "running" | "restarting" | "paused" => TaskState::Running,
+            "exited" if exit_code == Some(0) => TaskState::Succeeded,
+            _ => TaskState::Failed,
+        }
+    }
+
+    /// Map a Kubernetes job status from `list_task_jobs` to a task state
+    fn from_job_status(status: &str) -> Self {
+        match status {
+            "running" => TaskState::Running,
+            "succeeded" => TaskState::Succeeded,
+            "failed" => TaskState::Failed,
+            _ => TaskState::Pending,
+        }
+    }
+}
+
+/// Default number of log lines returned by get_task_status
+const DEFAULT_STATUS_LOG_LINES: usize = 200;
+
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct CheckWorktreeStatusOptions {
     #[serde(flatten)]
@@ -112,16 +192,28 @@ pub struct CheckWorktreeStatusOptions {
 
 // Individual tool input structs for each subcommand use the Options structs directly
 
+/// The global options the server was started with, used to load the config the same
+/// way the CLI does
+#[derive(Clone, Debug, Default)]
+pub struct ConfigOptions {
+    pub config_path: Option<std::path::PathBuf>,
+    pub profile: Option<String>,
+    /// Where to look for a repository `.claude-task.json`; `None` skips it
+    pub repo_dir: Option<std::path::PathBuf>,
+}
+
 #[derive(Clone)]
 pub struct ClaudeTaskMcpServer {
     tool_router: ToolRouter<Self>,
+    config_options: ConfigOptions,
 }
 
 #[tool_router]
 impl ClaudeTaskMcpServer {
-    fn new() -> Self {
+    fn new(config_options: ConfigOptions) -> Self {
         Self {
             tool_router: Self::tool_router(),
+            config_options,
         }
     }
 
@@ -156,9 +248,13 @@ impl ClaudeTaskMcpServer {
             .worktree_base_dir
             .unwrap_or_else(|| "~/.claude-task/worktrees".to_string());
 
-        let (worktree_path, branch_name) =
-            create_git_worktree(&args.task_id, &branch_prefix, &worktree_base_dir)
-                .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let (worktree_path, branch_name) = create_git_worktree(
+            &args.task_id,
+            &branch_prefix,
+            &worktree_base_dir,
+            &WorktreeCreateOptions::default(),
+        )
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
         let output = format!(
             "Git worktree created successfully\nBranch: {branch_name}\nPath: {worktree_path:?}"
@@ -171,18 +267,14 @@ impl ClaudeTaskMcpServer {
         &self,
         Parameters(args): Parameters<ListWorktreeOptions>,
     ) -> Result<CallToolResult, McpError> {
-        // We need to capture the output instead of printing directly
-        // For now, we'll use the subprocess approach but this could be improved
-        // by refactoring list_git_worktrees to return output instead of printing
-        let mut cmd_args = vec!["worktree".to_string(), "list".to_string()];
-        self.add_global_options(&mut cmd_args, &args.global_options);
-
-        let output = self
-            .execute_claude_task_command(&cmd_args)
-            .await
+        let branch_prefix = self.resolve_branch_prefix(&args.global_options);
+        let worktrees = get_worktree_infos(&branch_prefix, false)
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
-        Ok(CallToolResult::success(vec![Content::text(output)]))
+        Ok(CallToolResult::success(vec![
+            Content::text(self.summarize_worktrees(&worktrees)),
+            Content::json(json!({ "worktrees": worktrees }))?,
+        ]))
     }
 
     #[tool(description = "Remove a git worktree")]
@@ -196,7 +288,7 @@ impl ClaudeTaskMcpServer {
             .unwrap_or_else(|| "claude-task/".to_string());
 
         // Use false for auto_clean_branch since we don't have config access in MCP
-        remove_git_worktree(&args.task_id, &branch_prefix, false)
+        remove_git_worktree(&args.task_id, &branch_prefix, false, None)
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
         let output = format!("Cleanup complete for task '{}'", args.task_id);
@@ -213,12 +305,26 @@ impl ClaudeTaskMcpServer {
             .branch_prefix
             .unwrap_or_else(|| "claude-task/".to_string());
         let force = args.force.unwrap_or(false);
+        let include_heuristic = args.include_heuristic.unwrap_or(false);
+        let older_than = args
+            .older_than
+            .as_deref()
+            .map(parse_age)
+            .transpose()
+            .map_err(|e| McpError::invalid_params(e.to_string(), None))?;
 
         // Always skip confirmation in MCP mode
         // Use false for auto_clean_branch since we don't have config access in MCP
-        clean_all_worktrees(&branch_prefix, true, force, false)
-            .await
-            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        clean_all_worktrees(
+            &branch_prefix,
+            true,
+            force,
+            false,
+            include_heuristic,
+            older_than,
+        )
+        .await
+        .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
         Ok(CallToolResult::success(vec![Content::text(
             "Worktree cleanup completed successfully".to_string(),
@@ -270,6 +376,7 @@ impl ClaudeTaskMcpServer {
     ) -> Result<CallToolResult, McpError> {
         // Use subprocess since we need docker config which isn't available in MCP context
         let mut cmd_args = vec!["docker".to_string(), "clean".to_string()];
+        cmd_args.push("--yes".to_string()); // Skip confirmation in MCP
         self.add_global_options(&mut cmd_args, &args.global_options);
 
         let output = self
@@ -280,7 +387,9 @@ impl ClaudeTaskMcpServer {
         Ok(CallToolResult::success(vec![Content::text(output)]))
     }
 
-    #[tool(description = "Run a Claude task in a local docker container")]
+    #[tool(
+        description = "Run a Claude task in a local docker container or, with execution_env \"kubernetes\", as a Kubernetes job"
+    )]
     async fn run_task(
         &self,
         Parameters(args): Parameters<RunTaskOptions>,
@@ -295,6 +404,12 @@ impl ClaudeTaskMcpServer {
             ));
         }
 
+        let execution_env = args
+            .execution_env
+            .as_deref()
+            .map(|env| self.parse_execution_env(env))
+            .transpose()?;
+
         // Use subprocess since we need docker config which isn't available in MCP context
         let mut cmd_args = vec!["run".to_string(), args.prompt.clone()];
 
@@ -331,6 +446,24 @@ impl ClaudeTaskMcpServer {
         if args.async_mode.unwrap_or(false) {
             cmd_args.push("--background".to_string());
         }
+        if let Some(ref env) = execution_env {
+            cmd_args.push("--execution-env".to_string());
+            cmd_args.push(
+                match env {
+                    ExecutionEnvironment::Docker => "docker",
+                    ExecutionEnvironment::Kubernetes => "kubernetes",
+                }
+                .to_string(),
+            );
+        }
+        if let Some(namespace) = args.kube_namespace {
+            cmd_args.push("--kube-namespace".to_string());
+            cmd_args.push(namespace);
+        }
+        if let Some(context) = args.kube_context {
+            cmd_args.push("--kube-context".to_string());
+            cmd_args.push(context);
+        }
         self.add_global_options(&mut cmd_args, &args.global_options);
 
         let output = self
@@ -338,6 +471,25 @@ impl ClaudeTaskMcpServer {
             .await
             .map_err(|e| McpError::internal_error(e.to_string(), None))?;
 
+        // Kubernetes background jobs report a job instead of a container
+        if args.async_mode.unwrap_or(false) && output.contains("Claude task started in Kubernetes")
+        {
+            let (job_name, namespace) = self.extract_kube_async_info(&output)?;
+            let structured_output = json!({
+                "status": "running",
+                "task_id": self.extract_task_id(&output),
+                "job_name": job_name,
+                "namespace": namespace,
+            });
+
+            return Ok(CallToolResult::success(vec![
+                Content::text(format!(
+                    "Kubernetes job {job_name} is running in namespace {namespace}"
+                )),
+                Content::json(structured_output)?,
+            ]));
+        }
+
         // For async mode, return structured output with task and container info
         if args.async_mode.unwrap_or(false) {
             // Extract task ID and container ID from the output
@@ -345,22 +497,31 @@ impl ClaudeTaskMcpServer {
 
             // Return structured content
             let structured_output = json!({
+                "status": "running",
                 "task_id": task_id,
                 "container_id": container_id,
                 // TODO: The resource is not yet created and updated yet, future feature
                 "result_uri": format!("toolcall://run_task/{}", task_id)
             });
 
-            Ok(CallToolResult::success(vec![Content::text(
-                serde_json::to_string_pretty(&structured_output)
-                    .unwrap_or_else(|_| "Failed to serialize response".to_string()),
-            )]))
+            Ok(CallToolResult::success(vec![
+                Content::text(format!(
+                    "Task {task_id} is running in the background (container {container_id})"
+                )),
+                Content::json(structured_output)?,
+            ]))
         } else {
             // For sync mode, extract only Claude's response between the markers
             let claude_response = self.extract_claude_response(&output)?;
-            Ok(CallToolResult::success(vec![Content::text(
-                claude_response,
-            )]))
+            let structured_output = json!({
+                "status": "completed",
+                "task_id": self.extract_task_id(&output),
+                "response": claude_response,
+            });
+            Ok(CallToolResult::success(vec![
+                Content::text(claude_response),
+                Content::json(structured_output)?,
+            ]))
         }
     }
 
@@ -376,6 +537,13 @@ impl ClaudeTaskMcpServer {
         if force {
             cmd_args.push("--force".to_string());
         }
+        if args.include_heuristic.unwrap_or(false) {
+            cmd_args.push("--include-heuristic".to_string());
+        }
+        if let Some(runner) = args.runner {
+            cmd_args.push("--runner".to_string());
+            cmd_args.push(runner);
+        }
         cmd_args.push("--yes".to_string()); // Always skip confirmation in MCP
         self.add_global_options(&mut cmd_args, &args.global_options);
 
@@ -387,6 +555,127 @@ impl ClaudeTaskMcpServer {
         Ok(CallToolResult::success(vec![Content::text(output)]))
     }
 
+    #[tool(
+        description = "Get the effective claude-task config (task runner, images, Kubernetes namespace, paths) with the OAuth token redacted"
+    )]
+    async fn get_config(
+        &self,
+        Parameters(_args): Parameters<GetConfigOptions>,
+    ) -> Result<CallToolResult, McpError> {
+        let config = self.load_config()?;
+        Ok(CallToolResult::success(vec![Content::json(
+            RedactedConfig(&config),
+        )?]))
+    }
+
+    #[tool(
+        description = "Get the status and recent logs of a task started with run_task in background mode"
+    )]
+    async fn get_task_status(
+        &self,
+        Parameters(args): Parameters<GetTaskStatusOptions>,
+    ) -> Result<CallToolResult, McpError> {
+        let config = self.load_config()?;
+        let execution_env = match args.execution_env.as_deref() {
+            Some(env) => self.parse_execution_env(env)?,
+            None => config.task_runner.clone(),
+        };
+        let tail = args.tail.unwrap_or(DEFAULT_STATUS_LOG_LINES);
+        let internal_error = |e: anyhow::Error| McpError::internal_error(format!("{e:#}"), None);
+
+        let (state, logs, details) = match execution_env {
+            ExecutionEnvironment::Docker => {
+                let docker_manager = crate::docker::DockerManager::new(config.docker.clone())
+                    .map_err(internal_error)?;
+                docker_manager
+                    .ensure_daemon_available()
+                    .await
+                    .map_err(internal_error)?;
+                match docker_manager
+                    .task_container_state(&args.task_id)
+                    .await
+                    .map_err(internal_error)?
+                {
+                    Some((container_state, exit_code)) => {
+                        let logs = docker_manager
+                            .task_logs(&args.task_id, tail)
+                            .await
+                            .map_err(internal_error)?;
+                        (
+                            TaskState::from_container(&container_state, exit_code),
+                            logs,
+                            json!({
+                                "container": docker_manager.container_name(&args.task_id),
+                                "container_state": container_state,
+                                "exit_code": exit_code,
+                            }),
+                        )
+                    }
+                    None => (TaskState::NotFound, String::new(), json!({})),
+                }
+            }
+            ExecutionEnvironment::Kubernetes => {
+                let namespace = args
+                    .kube_namespace
+                    .clone()
+                    .or_else(|| {
+                        config
+                            .kube_config
+                            .as_ref()
+                            .and_then(|kc| kc.namespace.clone())
+                    })
+                    .ok_or_else(|| {
+                        McpError::invalid_params(
+                            "No Kubernetes namespace configured; pass kube_namespace",
+                            None,
+                        )
+                    })?;
+                let k8s_runner = crate::kube::KubernetesJobRunner::new()
+                    .await
+                    .map_err(internal_error)?;
+                match k8s_runner
+                    .find_task_job(&namespace, &args.task_id)
+                    .await
+                    .map_err(internal_error)?
+                {
+                    Some(job) => {
+                        // A pending job may not have a pod to read logs from yet
+                        let logs = k8s_runner
+                            .get_job_logs(&namespace, &job.name)
+                            .await
+                            .map(|logs| last_lines(&logs, tail))
+                            .unwrap_or_default();
+                        (
+                            TaskState::from_job_status(&job.status),
+                            logs,
+                            json!({ "namespace": namespace, "job": job }),
+                        )
+                    }
+                    None => (
+                        TaskState::NotFound,
+                        String::new(),
+                        json!({ "namespace": namespace }),
+                    ),
+                }
+            }
+        };
+
+        let summary = match state {
+            TaskState::NotFound => format!("Task {} was not found", args.task_id),
+            _ => format!("Task {} is {}", args.task_id, state.as_str()),
+        };
+
+        Ok(CallToolResult::success(vec![
+            Content::text(summary),
+            Content::json(json!({
+                "task_id": args.task_id,
+                "status": state,
+                "logs": logs,
+                "details": details,
+            }))?,
+        ]))
+    }
+
     #[tool(description = "Check git worktree status for uncommitted changes and unpushed commits")]
     async fn check_worktree_status(
         &self,
@@ -471,7 +760,14 @@ impl ClaudeTaskMcpServer {
             ));
         }
 
-        Ok(CallToolResult::success(vec![Content::text(output)]))
+        Ok(CallToolResult::success(vec![
+            Content::text(output),
+            Content::json(json!({
+                "path": worktree_path,
+                "isClean": status.is_clean(),
+                "status": status,
+            }))?,
+        ]))
     }
 
     fn add_global_options(&self, cmd_args: &mut Vec<String>, global_options: &GlobalOptions) {
@@ -531,6 +827,89 @@ impl ClaudeTaskMcpServer {
         }
     }
 
+    fn extract_kube_async_info(&self, output: &str) -> Result<(String, String), McpError> {
+        let mut job_name = None;
+        let mut namespace = None;
+
+        for line in output.lines() {
+            if let Some(job) = line.strip_prefix("   Job: ") {
+                job_name = Some(job.trim().to_string());
+            } else if let Some(ns) = line.strip_prefix("   Namespace: ") {
+                namespace = Some(ns.trim().to_string());
+            }
+        }
+
+        match (job_name, namespace) {
+            (Some(job), Some(ns)) => Ok((job, ns)),
+            _ => Err(McpError::internal_error(
+                "Could not extract job name and namespace from async output",
+                None,
+            )),
+        }
+    }
+
+    /// The task ID announced at the start of a run, if any
+    fn extract_task_id(&self, output: &str) -> Option<String> {
+        output.lines().find_map(|line| {
+            line.strip_prefix("Running Claude task with ID: ")
+                .or_else(|| line.strip_prefix("Running Claude task in Kubernetes with ID: "))
+                .map(|task_id| task_id.trim().to_string())
+        })
+    }
+
+    /// The effective config, loaded the same way as for CLI commands
+    fn load_config(&self) -> Result<Config, McpError> {
+        let options = &self.config_options;
+        Config::load_effective(
+            options.config_path.as_ref(),
+            options.profile.as_deref(),
+            options.repo_dir.as_deref(),
+        )
+        .map(|effective| effective.config)
+        .map_err(|e| McpError::internal_error(format!("{e:#}"), None))
+    }
+
+    fn parse_execution_env(&self, env: &str) -> Result<ExecutionEnvironment, McpError> {
+        <ExecutionEnvironment as clap::ValueEnum>::from_str(env, true).map_err(|_| {
+            McpError::invalid_params(
+                format!("Invalid execution_env '{env}': expected \"docker\" or \"kubernetes\""),
+                None,
+            )
+        })
+    }
+
+    /// The branch prefix from the tool arguments, falling back to the config
+    fn resolve_branch_prefix(&self, global_options: &GlobalOptions) -> String {
+        if let Some(branch_prefix) = &global_options.branch_prefix {
+            return branch_prefix.clone();
+        }
+
+        self.load_config().unwrap_or_default().paths.branch_prefix
+    }
+
+    /// One line per worktree, for human display
+    fn summarize_worktrees(&self, worktrees: &[WorktreeInfo]) -> String {
+        if worktrees.is_empty() {
+            return "No claude-task worktrees found".to_string();
+        }
+
+        let mut summary = format!("{} worktree(s):\n", worktrees.len());
+        for worktree in worktrees {
+            let state = match &worktree.status {
+                None => "status unknown",
+                Some(status) if status.has_uncommitted_changes => "uncommitted changes",
+                Some(status) if status.is_likely_merged => "merged",
+                Some(status) if !status.is_clean() => "unpushed commits",
+                Some(_) => "clean",
+            };
+            summary.push_str(&format!(
+                "  - {} ({}): {state}\n",
+                worktree.branch, worktree.path
+            ));
+        }
+        summary
+    }
+
     fn extract_async_info(&self, output: &str) -> Result<(String, String), McpError> {
         // Look for patterns in the output to extract task ID and container ID
         let mut task_id = None;
@@ -555,6 +934,12 @@ impl ClaudeTaskMcpServer {
     }
 }
 
+/// The last `count` lines of `text`
+fn last_lines(text: &str, count: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    lines[lines.len().saturating_sub(count)..].join("\n")
+}
+
 #[tool_handler]
 impl ServerHandler for ClaudeTaskMcpServer {
     fn get_info(&self) -> ServerInfo {
@@ -564,6 +949,7 @@ impl ServerHandler for ClaudeTaskMcpServer {
                 tools: Some(ToolsCapability {
                     list_changed: Some(true),
                 }),
+                resources: Some(ResourcesCapability::default()),
                 ..Default::default()
             },
             server_info: Implementation {
@@ -573,9 +959,51 @@ impl ServerHandler for ClaudeTaskMcpServer {
             instructions: Some("MCP server for claude-task CLI tool".to_string()),
         }
     }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        let mut resource = RawResource::new(CONFIG_RESOURCE_URI, "config");
+        resource.description = Some(
+            "Effective claude-task config, including environment overrides, with the OAuth token redacted"
+                .to_string(),
+        );
+        resource.mime_type = Some("application/json".to_string());
+
+        Ok(ListResourcesResult::with_all_items(vec![
+            resource.no_annotation()
+        ]))
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, McpError> {
+        if request.uri != CONFIG_RESOURCE_URI {
+            return Err(McpError::resource_not_found(
+                format!("Unknown resource: {}", request.uri),
+                None,
+            ));
+        }
+
+        let config = self.load_config()?;
+        let text = serde_json::to_string_pretty(&RedactedConfig(&config))
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: CONFIG_RESOURCE_URI.to_string(),
+                mime_type: Some("application/json".to_string()),
+                text,
+            }],
+        })
+    }
 }
 
-pub async fn run_mcp_server() -> Result<()> {
+pub async fn run_mcp_server(config_options: ConfigOptions) -> Result<()> {
     // Initialize the tracing subscriber with file and stdout logging
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive(tracing::Level::DEBUG.into()))
@@ -586,7 +1014,7 @@ pub async fn run_mcp_server() -> Result<()> {
     tracing::info!("Starting claude-task MCP server");
 
     // Create an instance of our claude-task server
-    let service = ClaudeTaskMcpServer::new()
+    let service = ClaudeTaskMcpServer::new(config_options)
         .serve(stdio())
         .await
         .inspect_err(|e| {
@@ -596,3 +1024,49 @@ pub async fn run_mcp_server() -> Result<()> {
     service.waiting().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_state_mapping() {
+        assert_eq!(
+            TaskState::from_container("created", None),
+            TaskState::Pending
+        );
+        assert_eq!(
+            TaskState::from_container("running", None),
+            TaskState::Running
+        );
+        assert_eq!(
+            TaskState::from_container("exited", Some(0)),
+            TaskState::Succeeded
+        );
+        assert_eq!(
+            TaskState::from_container("exited", Some(1)),
+            TaskState::Failed
+        );
+        assert_eq!(TaskState::from_container("dead", None), TaskState::Failed);
+
+        assert_eq!(TaskState::from_job_status("running"), TaskState::Running);
+        assert_eq!(
+            TaskState::from_job_status("succeeded"),
+            TaskState::Succeeded
+        );
+        assert_eq!(TaskState::from_job_status("failed"), TaskState::Failed);
+        assert_eq!(TaskState::from_job_status("pending"), TaskState::Pending);
+
+        assert_eq!(
+            serde_json::to_value(TaskState::NotFound).unwrap(),
+            "not_found"
+        );
+    }
+
+    #[test]
+    fn test_last_lines() {
+        assert_eq!(last_lines("a\nb\nc", 2), "b\nc");
+        assert_eq!(last_lines("a\nb", 5), "a\nb");
+        assert_eq!(last_lines("", 3), "");
+    }
+}
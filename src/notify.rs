@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::config::ExecutionEnvironment;
+use crate::history::RunStatus;
+
+/// How long a webhook may take before the run stops waiting for it
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// JSON body posted to `notifications.webhookUrl` when a task finishes
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskNotification {
+    /// One-line summary, so chat webhooks like Slack's show something readable
+    pub text: String,
+    pub task_id: String,
+    pub status: RunStatus,
+    pub runner: ExecutionEnvironment,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    pub duration_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TaskNotification {
+    pub fn new(
+        task_id: &str,
+        status: RunStatus,
+        runner: ExecutionEnvironment,
+        branch: Option<String>,
+        duration: Duration,
+        error: Option<String>,
+    ) -> Self {
+        let outcome = match status {
+            RunStatus::Succeeded => "succeeded",
+            RunStatus::Failed => "failed",
+            RunStatus::Background => "started in the background",
+            RunStatus::Cancelled => "was cancelled",
+        };
+        let mut text = format!(
+            "{} claude-task {task_id} {outcome} after {:.1}s",
+            status.icon(),
+            duration.as_secs_f64()
+        );
+        if let Some(branch) = &branch {
+            text.push_str(&format!(" (branch {branch})"));
+        }
+
+        Self {
+            text,
+            task_id: task_id.to_string(),
+            status,
+            runner,
+            branch,
+            duration_seconds: duration.as_secs(),
+            error,
+        }
+    }
+}
+
+/// POST `notification` to `url`. Gives up after a few seconds so a slow or
+/// unreachable webhook never holds up the run; the request itself keeps going
+/// in the background until the process exits.
+pub async fn send_webhook(url: &str, notification: &TaskNotification) -> Result<()> {
+    let url = url.to_string();
+    let body = serde_json::to_value(notification).context("Failed to serialize notification")?;
+    let request = tokio::task::spawn_blocking(move || {
+        ureq::post(&url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .send_json(body)
+            .map(|_| ())
+            .map_err(|e| anyhow::anyhow!(e))
+    });
+
+    match tokio::time::timeout(WEBHOOK_TIMEOUT, request).await {
+        Ok(Ok(result)) => result.context("Webhook request failed"),
+        Ok(Err(e)) => Err(e).context("Webhook request panicked"),
+        Err(_) => anyhow::bail!(
+            "Webhook did not respond within {}s",
+            WEBHOOK_TIMEOUT.as_secs()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_notification_payload() {
+        let notification = TaskNotification::new(
+            "brave-otter-1234",
+            RunStatus::Succeeded,
+            ExecutionEnvironment::Docker,
+            Some("claude-task/brave-otter-1234".to_string()),
+            Duration::from_secs(125),
+            None,
+        );
+        let payload = serde_json::to_value(&notification).unwrap();
+
+        assert_eq!(payload["taskId"], "brave-otter-1234");
+        assert_eq!(payload["status"], "succeeded");
+        assert_eq!(payload["runner"], "docker");
+        assert_eq!(payload["branch"], "claude-task/brave-otter-1234");
+        assert_eq!(payload["durationSeconds"], 125);
+        assert!(payload.get("error").is_none());
+        let text = payload["text"].as_str().unwrap();
+        assert!(text.contains("brave-otter-1234 succeeded"));
+        assert!(text.contains("claude-task/brave-otter-1234"));
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 /// Represents a valid approval tool permission format: mcp__server_name__tool_name
 #[derive(Debug, Clone)]
@@ -91,3 +92,114 @@ impl std::fmt::Display for ApprovalToolPermission {
         write!(f, "mcp__{}__{}", self.server_name, self.tool_name)
     }
 }
+
+/// How Claude should handle tool permissions inside the task container
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum PermissionMode {
+    /// Route permission prompts to an MCP approval tool
+    Approve,
+    /// Run with --dangerously-skip-permissions
+    Skip,
+    /// Run Claude in plan mode (no edits or commands are executed)
+    Plan,
+    /// Use Claude's default permission handling
+    Ask,
+}
+
+impl PermissionMode {
+    /// Resolve the effective mode from an explicit mode and an optional approval tool.
+    /// Without an explicit mode, an approval tool implies `Approve` and its absence implies `Skip`.
+    pub fn resolve(mode: Option<PermissionMode>, approval_tool: Option<&str>) -> Result<Self> {
+        match (mode, approval_tool) {
+            (None, Some(_)) => Ok(PermissionMode::Approve),
+            (None, None) => Ok(PermissionMode::Skip),
+            (Some(PermissionMode::Approve), None) => Err(anyhow::anyhow!(
+                "Permission mode 'approve' requires an approval tool permission (--approval-tool-permission)"
+            )),
+            (Some(PermissionMode::Skip), Some(tool)) => Err(anyhow::anyhow!(
+                "Permission mode 'skip' cannot be combined with an approval tool permission: {}",
+                tool
+            )),
+            (Some(mode), _) => Ok(mode),
+        }
+    }
+
+    /// Build the Claude CLI flags for this mode
+    pub fn claude_args(&self, approval_tool: Option<&str>) -> Vec<String> {
+        let mut args = Vec::new();
+
+        match self {
+            PermissionMode::Skip => {
+                args.push("--dangerously-skip-permissions".to_string());
+                return args;
+            }
+            PermissionMode::Plan => {
+                args.push("--permission-mode".to_string());
+                args.push("plan".to_string());
+            }
+            PermissionMode::Ask => {
+                args.push("--permission-mode".to_string());
+                args.push("default".to_string());
+            }
+            PermissionMode::Approve => {}
+        }
+
+        if let Some(tool) = approval_tool {
+            args.push("--permission-prompt-tool".to_string());
+            args.push(tool.to_string());
+        }
+
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_implicit_modes() {
+        assert_eq!(
+            PermissionMode::resolve(None, Some("mcp__server__tool")).unwrap(),
+            PermissionMode::Approve
+        );
+        assert_eq!(
+            PermissionMode::resolve(None, None).unwrap(),
+            PermissionMode::Skip
+        );
+    }
+
+    #[test]
+    fn test_resolve_conflicting_modes() {
+        assert!(PermissionMode::resolve(Some(PermissionMode::Approve), None).is_err());
+        assert!(
+            PermissionMode::resolve(Some(PermissionMode::Skip), Some("mcp__server__tool")).is_err()
+        );
+    }
+
+    #[test]
+    fn test_claude_args() {
+        assert_eq!(
+            PermissionMode::Skip.claude_args(None),
+            vec!["--dangerously-skip-permissions"]
+        );
+        assert_eq!(
+            PermissionMode::Approve.claude_args(Some("mcp__server__tool")),
+            vec!["--permission-prompt-tool", "mcp__server__tool"]
+        );
+        assert_eq!(
+            PermissionMode::Plan.claude_args(Some("mcp__server__tool")),
+            vec![
+                "--permission-mode",
+                "plan",
+                "--permission-prompt-tool",
+                "mcp__server__tool"
+            ]
+        );
+        assert_eq!(
+            PermissionMode::Ask.claude_args(None),
+            vec!["--permission-mode", "default"]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Progress reporting for `run`.
+//!
+//! The human format prints the usual emoji log lines. The JSON format writes
+//! one [`RunEvent`] per line to stdout and moves every other print to stderr,
+//! so stdout can be piped straight into a CI dashboard.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+
+/// How `run` reports progress on stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Emoji log lines meant for a terminal
+    #[default]
+    Human,
+    /// Newline-delimited JSON events
+    Json,
+}
+
+/// A structured progress event, serialized as `{"event": "<name>", ...}`
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    WorktreeCreated {
+        task_id: String,
+        path: String,
+        branch: String,
+    },
+    ImageBuilt {
+        image: String,
+    },
+    ContainerStarted {
+        task_id: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        container_id: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        job_name: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        namespace: Option<String>,
+    },
+    ClaudeOutput {
+        text: String,
+    },
+    /// Claude's parsed result (a serialized `docker::ClaudeResult`), with `run --claude-json`
+    ClaudeResult {
+        result: serde_json::Value,
+    },
+    Completed {
+        task_id: String,
+        status: String,
+        duration_ms: u128,
+    },
+    Failed {
+        task_id: String,
+        error: String,
+        duration_ms: u128,
+    },
+}
+
+/// Where JSON events go once [`enable_json_output`] has run
+static EVENT_SINK: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Where [`print_manifest`] writes once [`enable_manifest_output`] has run
+static MANIFEST_SINK: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Switch the process to JSON event output.
+///
+/// Keeps a handle to the real stdout for events, then points file descriptor 1
+/// at stderr so the decorative `println!`s throughout the run flow (and those
+/// of child processes like git) no longer mix with the event stream.
+pub fn enable_json_output() -> Result<()> {
+    let events = redirect_stdout_to_stderr()?;
+    let _ = EVENT_SINK.set(Mutex::new(events));
+    Ok(())
+}
+
+/// Reserve stdout for the manifest of a Kubernetes `run --dry-run`, moving every
+/// other print to stderr so the output can be piped into `kubectl apply -f -`
+pub fn enable_manifest_output() -> Result<()> {
+    let manifest = redirect_stdout_to_stderr()?;
+    let _ = MANIFEST_SINK.set(Mutex::new(manifest));
+    Ok(())
+}
+
+/// Print a rendered manifest to the real stdout
+pub fn print_manifest(manifest: &str) {
+    match MANIFEST_SINK.get() {
+        Some(sink) => {
+            let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = sink
+                .write_all(manifest.as_bytes())
+                .and_then(|_| sink.flush());
+        }
+        None => print!("{manifest}"),
+    }
+}
+
+/// Point file descriptor 1 at stderr, returning a handle to the original stdout.
+/// Later calls return another handle to that same original stdout.
+#[cfg(unix)]
+fn redirect_stdout_to_stderr() -> Result<File> {
+    use anyhow::Context;
+    use std::os::fd::AsFd;
+
+    static ORIGINAL_STDOUT: OnceLock<File> = OnceLock::new();
+    if let Some(stdout) = ORIGINAL_STDOUT.get() {
+        return stdout
+            .try_clone()
+            .context("Failed to duplicate the original stdout");
+    }
+
+    std::io::stdout().flush()?;
+    let original = File::from(
+        std::io::stdout()
+            .as_fd()
+            .try_clone_to_owned()
+            .context("Failed to duplicate stdout")?,
+    );
+    // SAFETY: dup2 on the process's own standard descriptors; both are valid
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to redirect stdout to stderr");
+    }
+    let handle = original
+        .try_clone()
+        .context("Failed to duplicate the original stdout")?;
+    let _ = ORIGINAL_STDOUT.set(original);
+    Ok(handle)
+}
+
+#[cfg(not(unix))]
+fn redirect_stdout_to_stderr() -> Result<File> {
+    anyhow::bail!("Separating progress output from stdout is only supported on Unix-like systems")
+}
+
+/// Write an event to the JSON stream. Does nothing in the human format.
+pub fn emit(event: RunEvent) {
+    let Some(sink) = EVENT_SINK.get() else {
+        return;
+    };
+    let Ok(line) = serde_json::to_string(&event) else {
+        return;
+    };
+    let mut sink = sink.lock().unwrap_or_else(|e| e.into_inner());
+    // A closed pipe shouldn't abort the task itself
+    let _ = writeln!(sink, "{line}").and_then(|_| sink.flush());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_event_json_shape() {
+        let event = RunEvent::ContainerStarted {
+            task_id: "abc".to_string(),
+            container_id: Some("c0ffee".to_string()),
+            job_name: None,
+            namespace: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "container_started",
+                "task_id": "abc",
+                "container_id": "c0ffee"
+            })
+        );
+
+        let event = RunEvent::Completed {
+            task_id: "abc".to_string(),
+            status: "succeeded".to_string(),
+            duration_ms: 1500,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"completed","task_id":"abc","status":"succeeded","duration_ms":1500}"#
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::time::{Duration, Instant};
+
+/// Records how long each phase of a task run takes
+#[derive(Debug)]
+pub struct PhaseTimer {
+    started_at: Instant,
+    current: Option<(String, Instant)>,
+    phases: Vec<(String, Duration)>,
+}
+
+impl PhaseTimer {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            current: None,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Start timing a new phase, finishing the current one if any
+    pub fn start(&mut self, phase: &str) {
+        self.finish();
+        self.current = Some((phase.to_string(), Instant::now()));
+    }
+
+    /// Finish the current phase
+    pub fn finish(&mut self) {
+        if let Some((phase, started_at)) = self.current.take() {
+            self.phases.push((phase, started_at.elapsed()));
+        }
+    }
+
+    /// Record a phase that was measured elsewhere, finishing the current one if any
+    pub fn record(&mut self, phase: &str, duration: Duration) {
+        self.finish();
+        self.phases.push((phase.to_string(), duration));
+    }
+
+    pub fn phases(&self) -> &[(String, Duration)] {
+        &self.phases
+    }
+
+    pub fn total(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn print_summary(&mut self) {
+        self.finish();
+
+        println!("⏱️  Timing breakdown:");
+        for (phase, duration) in self.phases() {
+            println!("   {phase:<20} {:>8.2}s", duration.as_secs_f64());
+        }
+        println!("   {:<20} {:>8.2}s", "total", self.total().as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_are_recorded_in_order() {
+        let mut timer = PhaseTimer::new();
+        timer.start("setup");
+        timer.start("image");
+        timer.record("container start", Duration::from_millis(5));
+        timer.finish();
+
+        let names: Vec<&str> = timer.phases().iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(names, vec!["setup", "image", "container start"]);
+    }
+}
@@ -2,10 +2,14 @@ use anyhow::{Context, Result};
 use dialoguer::{theme::ColorfulTheme, Select};
 use rand::Rng;
 use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::fs;
+use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 const ADJECTIVES: &[&str] = &[
     "autumn",
@@ -141,10 +145,19 @@ const NOUNS: &[&str] = &[
 ];
 
 pub fn generate_short_id() -> String {
+    generate_short_id_from(ADJECTIVES, NOUNS, 1000..=9999)
+}
+
+/// Generate an `<adjective>-<noun>-<number>` ID from the given word lists
+pub fn generate_short_id_from<S: AsRef<str>>(
+    adjectives: &[S],
+    nouns: &[S],
+    suffix_range: RangeInclusive<u32>,
+) -> String {
     let mut rng = rand::rng();
-    let adjective = ADJECTIVES[rng.random_range(0..ADJECTIVES.len())];
-    let noun = NOUNS[rng.random_range(0..NOUNS.len())];
-    let number = rng.random_range(1000..10000);
+    let adjective = adjectives[rng.random_range(0..adjectives.len())].as_ref();
+    let noun = nouns[rng.random_range(0..nouns.len())].as_ref();
+    let number = rng.random_range(suffix_range);
     format!("{adjective}-{noun}-{number}")
 }
 
@@ -212,8 +225,53 @@ pub async fn clean_all_worktrees(
     skip_confirmation: bool,
     force: bool,
     auto_clean_branch: bool,
+    include_heuristic: bool,
+    older_than: Option<Duration>,
 ) -> Result<()> {
-    let worktrees = get_matching_worktrees(branch_prefix)?;
+    let mut worktrees = get_matching_worktrees(branch_prefix)?;
+
+    let current_dir = std::env::current_dir().context("Could not get current directory")?;
+    let repo_root = find_git_repo_root(&current_dir)?;
+    let locked_worktrees = get_locked_worktrees(&repo_root)?;
+    let filter = CleanFilter {
+        branch_prefix,
+        repo_root: &repo_root,
+        force,
+        older_than,
+    };
+    worktrees.retain(|(path, _, branch)| {
+        // Only look up the age when it matters, as it runs git
+        let age = older_than.and_then(|_| worktree_age(Path::new(path)));
+        match should_clean_worktree(branch, path, locked_worktrees.get(path), age, &filter) {
+            CleanDecision::Remove => true,
+            CleanDecision::NotTask => false,
+            CleanDecision::Locked(Some(reason)) => {
+                println!("Skipping locked worktree: {path} ({reason}) (use --force to remove)");
+                false
+            }
+            CleanDecision::Locked(None) => {
+                println!("Skipping locked worktree: {path} (use --force to remove)");
+                false
+            }
+            CleanDecision::Recent(age) => {
+                println!(
+                    "Skipping recent worktree: {} ({}, last active {} ago)",
+                    path,
+                    branch.strip_prefix("refs/heads/").unwrap_or(branch),
+                    format_age(age)
+                );
+                false
+            }
+            CleanDecision::UnknownAge => {
+                println!(
+                    "Skipping worktree with unknown age: {} ({})",
+                    path,
+                    branch.strip_prefix("refs/heads/").unwrap_or(branch)
+                );
+                false
+            }
+        }
+    });
 
     if worktrees.is_empty() {
         println!("No worktrees to clean up.");
@@ -259,16 +317,150 @@ pub async fn clean_all_worktrees(
                 println!("Skipping unclean worktree: {path} (use --force to remove)");
                 continue;
             }
+            if !include_heuristic && status.relies_on_heuristic_merge() {
+                println!(
+                    "Skipping heuristically-merged worktree: {path} (use --include-heuristic to remove)"
+                );
+                continue;
+            }
         }
-        remove_git_worktree(task_id, branch_prefix, auto_clean_branch)?;
+        if locked_worktrees.contains_key(&path) {
+            set_worktree_lock(&path, false, None)?;
+        }
+        remove_git_worktree(task_id, branch_prefix, auto_clean_branch, None)?;
     }
 
     Ok(())
 }
 
+/// Parse an age such as `30m`, `48h`, `7d` or `2w`
+pub fn parse_age(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split_at = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow::anyhow!("Missing unit in '{}' (use m, h, d or w)", input))?;
+    let (amount, unit) = input.split_at(split_at);
+
+    let amount: u64 = amount
+        .parse()
+        .with_context(|| format!("Invalid number in '{input}'"))?;
+    let seconds_per_unit = match unit {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => anyhow::bail!("Unknown unit '{}' in '{}' (use m, h, d or w)", unit, input),
+    };
+
+    let seconds = amount
+        .checked_mul(seconds_per_unit)
+        .ok_or_else(|| anyhow::anyhow!("Age '{}' is too large", input))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format a duration compactly, e.g. "3d", "5h" or "12m"
+pub fn format_age(age: Duration) -> String {
+    let hours = age.as_secs() / 3600;
+    if hours >= 48 {
+        format!("{}d", hours / 24)
+    } else if hours >= 1 {
+        format!("{hours}h")
+    } else {
+        format!("{}m", age.as_secs() / 60)
+    }
+}
+
+/// Time since the worktree was last active: the newer of its last commit and its directory's mtime
+fn worktree_age(worktree_path: &Path) -> Option<Duration> {
+    let commit_time = Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(worktree_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .trim()
+                .parse::<u64>()
+                .ok()
+        })
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+    let modified_time = fs::metadata(worktree_path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    let last_active = commit_time.into_iter().chain(modified_time).max()?;
+    Some(
+        SystemTime::now()
+            .duration_since(last_active)
+            .unwrap_or_default(),
+    )
+}
+
+/// Turn a task ID into something git accepts as a branch name component.
+///
+/// Each run of disallowed characters (including `.` and `/`, so `..` and `.lock`
+/// can't appear) and dashes becomes a single `-`, and leading and trailing dashes
+/// are dropped so the name can't be read as an option. Input with nothing usable
+/// left maps to a `task-<hash>` ID derived from the whole name, so the same task ID
+/// always finds the same branch and worktree.
 pub fn sanitize_branch_name(name: &str) -> String {
+    let re = Regex::new(r"[^a-zA-Z0-9_]+").unwrap();
+    let replaced = re.replace_all(name, "-");
+    let sanitized = replaced.trim_matches('-');
+    if sanitized.is_empty() {
+        return hashed_task_name(name);
+    }
+    sanitized.to_string()
+}
+
+/// How earlier versions sanitized task IDs: one `-` per disallowed character, with
+/// only leading dashes dropped. Branches created then are still found by this name.
+fn legacy_sanitize_branch_name(name: &str) -> String {
     let re = Regex::new(r"[^a-zA-Z0-9\-_]").unwrap();
-    re.replace_all(name, "-").to_string()
+    let replaced = re.replace_all(name, "-");
+    let sanitized = replaced.trim_start_matches('-');
+    if sanitized.is_empty() {
+        return hashed_task_name(name);
+    }
+    sanitized.to_string()
+}
+
+fn hashed_task_name(name: &str) -> String {
+    let digest = Sha256::digest(name.as_bytes());
+    let hash: String = digest
+        .iter()
+        .take(8)
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("task-{hash}")
+}
+
+/// Branch names a task's branch may have: the current form first, then the one
+/// earlier versions used if it differs
+pub fn task_branch_names(task_id: &str, branch_prefix: &str) -> Vec<String> {
+    let mut names = vec![format!("{branch_prefix}{}", sanitize_branch_name(task_id))];
+    let legacy = format!("{branch_prefix}{}", legacy_sanitize_branch_name(task_id));
+    if !names.contains(&legacy) {
+        names.push(legacy);
+    }
+    names
+}
+
+/// Find the worktree for `task_id` under any of its [`task_branch_names`]. Returns the
+/// branch it was found under (or the current form if none) and its path.
+fn find_task_worktree(
+    repo_root: &Path,
+    task_id: &str,
+    branch_prefix: &str,
+) -> Result<(String, Option<String>)> {
+    let names = task_branch_names(task_id, branch_prefix);
+    for name in &names {
+        if let Some(path) = find_worktree_path_for_branch(repo_root, name)? {
+            return Ok((name.clone(), Some(path)));
+        }
+    }
+    Ok((names[0].clone(), None))
 }
 
 pub fn find_git_repo_root(start_path: &Path) -> Result<PathBuf> {
@@ -330,62 +522,208 @@ pub fn get_repo_name(worktree_path: &Path) -> String {
         .to_string()
 }
 
-pub fn get_worktree_directory(worktree_base_dir: &str) -> Result<PathBuf> {
-    let worktree_dir = worktree_base_dir.to_string();
-
-    let worktree_path = if worktree_dir.starts_with('/') || worktree_dir.starts_with('~') {
-        // Absolute path or home directory path
-        if worktree_dir.starts_with('~') {
-            let home_dir = std::env::var("HOME").context("Could not find HOME directory")?;
-            PathBuf::from(worktree_dir.replacen('~', &home_dir, 1))
-        } else {
-            PathBuf::from(worktree_dir)
-        }
+/// Resolve the worktree base directory: `~` expands to the home directory and
+/// relative paths are relative to the current directory
+pub fn resolve_worktree_directory(worktree_base_dir: &str) -> Result<PathBuf> {
+    let expanded = crate::config::Config::expand_tilde(worktree_base_dir)?;
+    if expanded.is_absolute() {
+        Ok(expanded)
     } else {
-        // Relative path - relative to current directory
-        std::env::current_dir()
+        Ok(std::env::current_dir()
             .context("Could not get current directory")?
-            .join(worktree_dir)
-    };
+            .join(expanded))
+    }
+}
+
+/// Resolve the worktree base directory and make sure it exists
+pub fn get_worktree_directory(worktree_base_dir: &str) -> Result<PathBuf> {
+    let worktree_path = resolve_worktree_directory(worktree_base_dir)?;
 
     fs::create_dir_all(&worktree_path)
         .with_context(|| format!("Failed to create worktree directory: {worktree_path:?}"))?;
     Ok(worktree_path)
 }
 
+/// Which uncommitted changes in the repository root to copy into a new worktree
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CarryChanges {
+    #[default]
+    None,
+    /// Modifications to tracked files
+    Tracked,
+    /// Modifications to tracked files plus untracked (non-ignored) files
+    All,
+}
+
+/// Optional behaviour for `create_git_worktree`
+#[derive(Debug, Clone, Default)]
+pub struct WorktreeCreateOptions<'a> {
+    /// Branch to use instead of `<branch_prefix><task_id>`, checked out if it already exists
+    pub branch: Option<&'a str>,
+    /// Ref to start a new branch from (default: the repository's current HEAD)
+    pub base_ref: Option<&'a str>,
+    pub carry_changes: CarryChanges,
+    /// Initialize submodules in the new worktree
+    pub recurse_submodules: bool,
+    /// Shell command run in the new worktree once it is set up
+    pub post_create_hook: Option<&'a str>,
+    /// Directory inside the repository to create the worktree for (default: the current directory)
+    pub repo_dir: Option<&'a Path>,
+}
+
+/// How many names `reserve_worktree_path` tries before giving up
+const WORKTREE_PATH_ATTEMPTS: usize = 5;
+
+/// Claim a fresh `<name>_<timestamp>` directory under `base_dir` for a new worktree.
+///
+/// The directory is created with `create_dir`, which fails if it already exists,
+/// so concurrent runs can't both end up with the same path. On a collision a
+/// random suffix is added and the next name is tried. `git worktree add`
+/// accepts the empty directory.
+pub fn reserve_worktree_path(base_dir: &Path, name: &str) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let mut candidate = base_dir.join(format!("{name}_{timestamp:x}"));
+
+    for _ in 0..WORKTREE_PATH_ATTEMPTS {
+        match fs::create_dir(&candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let suffix: u16 = rand::rng().random();
+                candidate = base_dir.join(format!("{name}_{timestamp:x}-{suffix:04x}"));
+            }
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to create worktree directory {candidate:?}"))
+            }
+        }
+    }
+
+    Err(anyhow::anyhow!(
+        "Could not find a free worktree directory for '{}' in {:?} after {} attempts. \
+        Remove stale directories there or try again.",
+        name,
+        base_dir,
+        WORKTREE_PATH_ATTEMPTS
+    ))
+}
+
+/// Create a worktree for `task_id` on `<branch_prefix><task_id>` (or `options.branch`)
 pub fn create_git_worktree(
     task_id: &str,
     branch_prefix: &str,
     worktree_base_dir: &str,
+    options: &WorktreeCreateOptions,
 ) -> Result<(PathBuf, String)> {
-    let current_dir = std::env::current_dir().context("Could not get current directory")?;
-    let repo_root = find_git_repo_root(&current_dir)?;
+    let WorktreeCreateOptions {
+        branch,
+        base_ref,
+        carry_changes,
+        recurse_submodules,
+        post_create_hook,
+        repo_dir,
+    } = *options;
+
+    let repo_root = match repo_dir {
+        Some(repo_dir) => find_git_repo_root(repo_dir)?,
+        None => {
+            let current_dir = std::env::current_dir().context("Could not get current directory")?;
+            find_git_repo_root(&current_dir)?
+        }
+    };
 
     let sanitized_name = sanitize_branch_name(task_id);
-    let branch_name = format!("{branch_prefix}{sanitized_name}");
+    let branch_name = match branch {
+        Some(branch) => branch.to_string(),
+        None => format!("{branch_prefix}{sanitized_name}"),
+    };
+
+    // Reuse an explicitly requested branch if it exists, unless another worktree has it checked out
+    let use_existing_branch = branch.is_some() && branch_exists(&repo_root, &branch_name);
+    if use_existing_branch {
+        if let Some(existing_path) = find_worktree_path_for_branch(&repo_root, &branch_name)? {
+            return Err(anyhow::anyhow!(
+                "Branch '{}' is already checked out in another worktree: {}\n\
+                Remove that worktree first or work in it directly.",
+                branch_name,
+                existing_path
+            ));
+        }
+        if let Some(base_ref) = base_ref {
+            return Err(anyhow::anyhow!(
+                "Branch '{}' already exists, so it can't be started from '{}'.\n\
+                Drop --from to reuse the branch as it is, or pick a new branch name.",
+                branch_name,
+                base_ref
+            ));
+        }
+    }
+
+    if let Some(base_ref) = base_ref {
+        if !ref_exists(&repo_root, base_ref) {
+            return Err(anyhow::anyhow!(
+                "Base ref '{}' does not exist. Use a branch, tag or commit, e.g. 'main' or 'origin/main'.",
+                base_ref
+            ));
+        }
+    }
 
     let worktree_base_dir = get_worktree_directory(worktree_base_dir)?;
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    let worktree_path = worktree_base_dir.join(format!("{sanitized_name}_{timestamp:x}"));
+    let worktree_path = reserve_worktree_path(&worktree_base_dir, &sanitized_name)?;
 
     println!("Creating git worktree...");
     println!("Repository root: {repo_root:?}");
     println!("Branch name: {branch_name}");
+    if let Some(base_ref) = base_ref {
+        println!("Base ref: {base_ref}");
+    }
     println!("Worktree path: {worktree_path:?}");
 
     // Create the worktree
-    let output = Command::new("git")
-        .args(["worktree", "add", "-b", &branch_name])
-        .arg(&worktree_path)
-        .current_dir(&repo_root)
-        .output()
-        .context("Failed to execute git worktree command")?;
+    let mut command = Command::new("git");
+    command.args(["worktree", "add"]);
+    if use_existing_branch {
+        println!("Using existing branch '{branch_name}'");
+        command.arg(&worktree_path).arg(&branch_name);
+    } else {
+        command.args(["-b", &branch_name]).arg(&worktree_path);
+        if let Some(base_ref) = base_ref {
+            command.arg(base_ref);
+        }
+    }
+    let output = match command.current_dir(&repo_root).output() {
+        Ok(output) => output,
+        Err(e) => {
+            // Release the reserved directory, as git never got to use it
+            let _ = fs::remove_dir(&worktree_path);
+            return Err(e).context("Failed to execute git worktree command");
+        }
+    };
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        // Release the reserved directory; git leaves it empty when it fails
+        let _ = fs::remove_dir(&worktree_path);
+
+        // A previous task with the same ID leaves its branch behind
+        if stderr.contains("already exists") && branch_exists(&repo_root, &branch_name) {
+            let suggested_id = next_available_task_id(&repo_root, branch_prefix, &sanitized_name);
+            return Err(anyhow::anyhow!(
+                "Branch '{}' already exists (probably from a previous task with the same ID).\n\n\
+                To continue, either:\n\
+                1. Use a different task ID, e.g. '{}'\n\
+                2. Remove the old worktree and branch: claude-task worktree remove {} && git branch -D {}\n\
+                3. Reuse the existing branch: git worktree add <path> {}",
+                branch_name,
+                suggested_id,
+                task_id,
+                branch_name,
+                branch_name
+            ));
+        }
+
         return Err(anyhow::anyhow!("Git worktree command failed: {}", stderr));
     }
 
@@ -393,10 +731,332 @@ pub fn create_git_worktree(
     println!("  Branch: {branch_name}");
     println!("  Path: {worktree_path:?}");
 
+    if recurse_submodules {
+        init_submodules(&worktree_path);
+    }
+
+    if carry_changes != CarryChanges::None {
+        carry_uncommitted_changes(&repo_root, &worktree_path, carry_changes)?;
+    }
+
+    if let Some(hook) = post_create_hook {
+        run_post_create_hook(hook, &worktree_path, task_id, &branch_name);
+    }
+
     Ok((worktree_path, branch_name))
 }
 
-pub fn list_git_worktrees(branch_prefix: &str) -> Result<()> {
+/// Run the configured post-create hook in the new worktree, passing its path as the first
+/// argument. A failing hook only warns so the worktree is kept.
+fn run_post_create_hook(hook: &str, worktree_path: &Path, task_id: &str, branch_name: &str) {
+    println!("Running post-create hook: {hook}");
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{hook} \"$@\""))
+        .arg("sh")
+        .arg(worktree_path)
+        .current_dir(worktree_path)
+        .env("CLAUDE_TASK_ID", task_id)
+        .env("CLAUDE_TASK_BRANCH", branch_name)
+        .status();
+
+    match status {
+        Ok(status) if status.success() => println!("✓ Post-create hook completed"),
+        Ok(status) => {
+            println!("⚠️  Warning: Post-create hook failed ({status}); the worktree was kept")
+        }
+        Err(e) => println!("⚠️  Warning: Failed to run post-create hook: {e}"),
+    }
+}
+
+/// Initialize submodules in a new worktree, showing git's progress. Failures only warn,
+/// since the worktree itself is still usable.
+fn init_submodules(worktree_path: &Path) {
+    if !worktree_path.join(".gitmodules").exists() {
+        return;
+    }
+
+    println!("Initializing submodules...");
+    let status = Command::new("git")
+        .args(["submodule", "update", "--init", "--recursive"])
+        .current_dir(worktree_path)
+        .status();
+
+    match status {
+        Ok(status) if status.success() => println!("✓ Submodules initialized"),
+        Ok(status) => {
+            println!("⚠️  Warning: Submodule update failed ({status})");
+            println!("   Retry inside the worktree with: git submodule update --init --recursive");
+        }
+        Err(e) => println!("⚠️  Warning: Failed to run git submodule update: {e}"),
+    }
+}
+
+/// Copy uncommitted changes from `repo_root` into `worktree_path`. A patch that doesn't apply
+/// cleanly is reported as a warning and leaves the worktree as it was.
+fn carry_uncommitted_changes(
+    repo_root: &Path,
+    worktree_path: &Path,
+    carry_changes: CarryChanges,
+) -> Result<()> {
+    let diff_output = Command::new("git")
+        .args(["diff", "HEAD", "--binary"])
+        .current_dir(repo_root)
+        .output()
+        .context("Failed to execute git diff command")?;
+
+    if !diff_output.status.success() {
+        let stderr = String::from_utf8_lossy(&diff_output.stderr);
+        return Err(anyhow::anyhow!("Git diff command failed: {}", stderr));
+    }
+
+    if diff_output.stdout.is_empty() {
+        println!("ℹ️  No uncommitted changes to tracked files to carry over");
+    } else {
+        let mut apply = Command::new("git")
+            .args(["apply", "--binary", "-"])
+            .current_dir(worktree_path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .context("Failed to execute git apply command")?;
+
+        {
+            use std::io::Write;
+            let mut stdin = apply
+                .stdin
+                .take()
+                .context("Failed to open git apply stdin")?;
+            stdin
+                .write_all(&diff_output.stdout)
+                .context("Failed to pass changes to git apply")?;
+        }
+
+        let output = apply
+            .wait_with_output()
+            .context("Failed to wait for git apply")?;
+        if output.status.success() {
+            println!("✓ Carried over uncommitted changes to tracked files");
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            println!("⚠️  Warning: Could not apply uncommitted changes to the new worktree:");
+            for line in stderr.lines() {
+                println!("   {line}");
+            }
+            println!("   The worktree was created without them.");
+        }
+    }
+
+    if carry_changes == CarryChanges::All {
+        let output = Command::new("git")
+            .args(["ls-files", "--others", "--exclude-standard", "-z"])
+            .current_dir(repo_root)
+            .output()
+            .context("Failed to list untracked files")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("Git ls-files command failed: {}", stderr));
+        }
+
+        let mut copied = 0;
+        for relative_path in String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+        {
+            let target = worktree_path.join(relative_path);
+            if target.exists() {
+                println!("⚠️  Warning: Not overwriting existing file: {relative_path}");
+                continue;
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {parent:?}"))?;
+            }
+            fs::copy(repo_root.join(relative_path), &target)
+                .with_context(|| format!("Failed to copy untracked file: {relative_path}"))?;
+            copied += 1;
+        }
+
+        if copied > 0 {
+            println!("✓ Copied {copied} untracked file(s)");
+        }
+    }
+
+    Ok(())
+}
+
+fn branch_exists(repo_root: &Path, branch_name: &str) -> bool {
+    Command::new("git")
+        .args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{branch_name}"),
+        ])
+        .current_dir(repo_root)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+fn ref_exists(repo_root: &Path, git_ref: &str) -> bool {
+    Command::new("git")
+        .args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{git_ref}^{{commit}}"),
+        ])
+        .current_dir(repo_root)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Find the first `<task_id>-<n>` whose branch doesn't exist yet
+fn next_available_task_id(repo_root: &Path, branch_prefix: &str, task_id: &str) -> String {
+    (2..)
+        .map(|n| format!("{task_id}-{n}"))
+        .find(|candidate| !branch_exists(repo_root, &format!("{branch_prefix}{candidate}")))
+        .unwrap_or_else(|| task_id.to_string())
+}
+
+/// A worktree matching the branch prefix together with its status
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorktreeInfo {
+    pub path: String,
+    pub branch: String,
+    pub head: String,
+    pub repo_name: String,
+    /// `None` when the status could not be determined
+    pub status: Option<WorktreeStatus>,
+    /// Disk usage in bytes, only computed when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+}
+
+impl WorktreeInfo {
+    fn new(path: String, head: String, branch: String, with_size: bool) -> Self {
+        // Clean up branch name by removing refs/heads/ prefix
+        let branch = match branch.strip_prefix("refs/heads/") {
+            Some(clean_branch) => clean_branch.to_string(),
+            None if branch.is_empty() => "unknown".to_string(),
+            None => branch,
+        };
+
+        let path_buf = PathBuf::from(&path);
+        let repo_name = get_repo_name(&path_buf);
+        let status = check_worktree_status(&path_buf).ok();
+        let size_bytes = with_size.then(|| directory_size(&path_buf));
+
+        Self {
+            path,
+            branch,
+            head,
+            repo_name,
+            status,
+            size_bytes,
+        }
+    }
+}
+
+/// Total size of the files under `path`, without following symlinks
+pub fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.path().symlink_metadata().ok()?;
+            Some(if metadata.is_dir() {
+                directory_size(&entry.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next_unit in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next_unit;
+    }
+    format!("{size:.1} {unit}")
+}
+
+/// Narrow `worktree list` to worktrees in a given state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WorktreeFilter {
+    /// Safe to clean: no uncommitted or unpushed work
+    Clean,
+    /// Has uncommitted changes, unpushed commits or no remote
+    Dirty,
+    /// Branch appears to be merged into the base branch
+    Merged,
+}
+
+impl WorktreeFilter {
+    pub fn matches(&self, info: &WorktreeInfo) -> bool {
+        let Some(status) = &info.status else {
+            return false;
+        };
+
+        match self {
+            WorktreeFilter::Clean => status.is_clean(),
+            WorktreeFilter::Dirty => !status.is_clean(),
+            WorktreeFilter::Merged => status.is_likely_merged,
+        }
+    }
+}
+
+impl std::fmt::Display for WorktreeFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WorktreeFilter::Clean => write!(f, "clean"),
+            WorktreeFilter::Dirty => write!(f, "dirty"),
+            WorktreeFilter::Merged => write!(f, "merged"),
+        }
+    }
+}
+
+pub fn list_git_worktrees(
+    branch_prefix: &str,
+    json: bool,
+    with_size: bool,
+    only: Option<WorktreeFilter>,
+) -> Result<()> {
+    let filter_worktrees = |mut worktrees: Vec<WorktreeInfo>| {
+        if let Some(filter) = only {
+            worktrees.retain(|info| filter.matches(info));
+        }
+        worktrees
+    };
+
+    if json {
+        let worktrees = filter_worktrees(get_worktree_infos(branch_prefix, with_size)?);
+        let output = serde_json::to_string_pretty(&worktrees)
+            .context("Failed to serialize worktree list")?;
+        println!("{output}");
+        return Ok(());
+    }
+
     let current_dir = std::env::current_dir().context("Could not get current directory")?;
     let repo_root = find_git_repo_root(&current_dir)?;
 
@@ -404,6 +1064,40 @@ pub fn list_git_worktrees(branch_prefix: &str) -> Result<()> {
     println!("Repository root: {repo_root:?}");
     println!();
 
+    let all_worktrees = get_worktree_infos(branch_prefix, with_size)?;
+    let total_count = all_worktrees.len();
+    let worktrees = filter_worktrees(all_worktrees);
+    if total_count == 0 {
+        println!("No worktrees found matching branch prefix '{branch_prefix}'.");
+    } else if worktrees.is_empty() {
+        if let Some(filter) = only {
+            println!(
+                "No worktrees match the '{filter}' filter ({total_count} worktrees in total)."
+            );
+        }
+    } else {
+        for info in &worktrees {
+            print_worktree_info(info, branch_prefix);
+        }
+
+        if with_size {
+            let total: u64 = worktrees.iter().filter_map(|info| info.size_bytes).sum();
+            println!(
+                "💾 Total size of {} worktrees: {}",
+                worktrees.len(),
+                format_size(total)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the worktrees matching `branch_prefix` along with their status and, if requested, size
+pub fn get_worktree_infos(branch_prefix: &str, with_size: bool) -> Result<Vec<WorktreeInfo>> {
+    let current_dir = std::env::current_dir().context("Could not get current directory")?;
+    let repo_root = find_git_repo_root(&current_dir)?;
+
     let output = Command::new("git")
         .args(["worktree", "list", "--porcelain"])
         .current_dir(&repo_root)
@@ -422,8 +1116,7 @@ pub fn list_git_worktrees(branch_prefix: &str) -> Result<()> {
     let lines: Vec<&str> = stdout.lines().collect();
 
     if lines.is_empty() {
-        println!("No worktrees found.");
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     let mut current_worktree: Option<(String, String, String)> = None; // (path, head, branch)
@@ -469,16 +1162,75 @@ pub fn list_git_worktrees(branch_prefix: &str) -> Result<()> {
         }
     }
 
-    // Print all matching worktrees
-    if matching_worktrees.is_empty() {
-        println!("No worktrees found matching branch prefix '{branch_prefix}'.");
-    } else {
-        for (path, head, branch) in matching_worktrees {
-            print_worktree_info(&path, &head, &branch);
-        }
-    }
+    collect_worktree_infos(matching_worktrees, with_size)
+}
 
-    Ok(())
+/// Most worktree status checks run at once; each one spawns several git processes
+const MAX_STATUS_WORKERS: usize = 8;
+
+/// Gather repo name and status for each `(path, head, branch)` entry concurrently.
+/// Each status check spawns several git processes, so running them in parallel keeps
+/// `worktree list` fast with many worktrees. Results are sorted by path.
+pub fn collect_worktree_infos(
+    worktrees: Vec<(String, String, String)>,
+    with_size: bool,
+) -> Result<Vec<WorktreeInfo>> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_STATUS_WORKERS);
+    let mut infos = map_concurrently(worktrees, workers, |(path, head, branch)| {
+        WorktreeInfo::new(path, head, branch, with_size)
+    })
+    .context("Failed to check worktree status")?;
+
+    infos.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(infos)
+}
+
+/// Apply `f` to every item on at most `workers` threads, keeping the input order.
+/// Fails if `f` panics on any item.
+fn map_concurrently<T, R, F>(items: Vec<T>, workers: usize, f: F) -> Result<Vec<R>>
+where
+    T: Send,
+    R: Send,
+    F: Fn(T) -> R + Sync,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    let queue = std::sync::Mutex::new(items.into_iter().enumerate());
+
+    let batches = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        // `f` never runs under the lock, so it can't be poisoned mid-`next`
+                        let next = queue
+                            .lock()
+                            .unwrap_or_else(std::sync::PoisonError::into_inner)
+                            .next();
+                        let Some((index, item)) = next else {
+                            return done;
+                        };
+                        done.push((index, f(item)));
+                    }
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| anyhow::anyhow!("A worker thread panicked"))
+            })
+            .collect::<Result<Vec<_>>>()
+    })?;
+
+    let mut results: Vec<(usize, R)> = batches.into_iter().flatten().collect();
+    results.sort_by_key(|(index, _)| *index);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
 }
 
 pub fn should_include_worktree(
@@ -508,24 +1260,14 @@ pub fn should_include_worktree(
         || ((clean_branch == "main" || clean_branch == "master") && worktree_path != repo_root)
 }
 
-pub fn print_worktree_info(path: &str, head: &str, branch: &str) {
-    let path_buf = PathBuf::from(path);
-    let dir_name = path_buf
+pub fn print_worktree_info(info: &WorktreeInfo, branch_prefix: &str) {
+    let dir_name = Path::new(&info.path)
         .file_name()
         .and_then(|name| name.to_str())
         .unwrap_or("unknown");
 
-    // Clean up branch name by removing refs/heads/ prefix
-    let clean_branch = if branch.starts_with("refs/heads/") {
-        branch.strip_prefix("refs/heads/").unwrap_or(branch)
-    } else if branch.is_empty() {
-        "unknown"
-    } else {
-        branch
-    };
-
     // Determine if this is a Claude task worktree
-    let is_claude_task = clean_branch.starts_with("claude-task/");
+    let is_claude_task = info.branch.starts_with(branch_prefix);
     let icon = if is_claude_task { "🌿" } else { "📁" };
     let type_label = if is_claude_task {
         " (Claude task)"
@@ -533,28 +1275,36 @@ pub fn print_worktree_info(path: &str, head: &str, branch: &str) {
         " (worktree)"
     };
 
-    // Get repository name
-    let repo_name = get_repo_name(&path_buf);
-
     // Check worktree status
     println!("{icon} {dir_name}{type_label}");
-    println!("   Path: {path}");
-    println!("   Repository: {repo_name}");
-    println!("   Branch: {clean_branch}");
+    println!("   Path: {}", info.path);
+    println!("   Repository: {}", info.repo_name);
+    println!("   Branch: {}", info.branch);
     println!(
         "   HEAD: {}",
-        if head.len() > 7 { &head[..7] } else { head }
+        if info.head.len() > 7 {
+            &info.head[..7]
+        } else {
+            &info.head
+        }
     );
+    if let Some(size_bytes) = info.size_bytes {
+        println!("   Size: {}", format_size(size_bytes));
+    }
 
-    match check_worktree_status(&path_buf) {
-        Ok(status) => {
+    match &info.status {
+        Some(status) => {
             let status_icon = status.get_status_icon();
             let details = status.get_status_details();
 
             if status.is_clean() {
                 if status.is_likely_merged {
                     let merge_type = status.merge_info.as_deref().unwrap_or("merged");
-                    println!("   Status: {status_icon} Clean ({merge_type})");
+                    if status.merge_confidence == Some(MergeConfidence::Heuristic) {
+                        println!("   Status: {status_icon} Clean ({merge_type}, heuristic)");
+                    } else {
+                        println!("   Status: {status_icon} Clean ({merge_type})");
+                    }
                 } else {
                     println!("   Status: {status_icon} Clean");
                 }
@@ -600,7 +1350,7 @@ pub fn print_worktree_info(path: &str, head: &str, branch: &str) {
                 }
             }
         }
-        Err(_) => {
+        None => {
             println!("   Status: ❓ Status unknown");
         }
     };
@@ -608,26 +1358,14 @@ pub fn print_worktree_info(path: &str, head: &str, branch: &str) {
     println!();
 }
 
-pub fn remove_git_worktree(
-    task_id: &str,
-    branch_prefix: &str,
-    auto_clean_branch: bool,
-) -> Result<()> {
-    let current_dir = std::env::current_dir().context("Could not get current directory")?;
-    let repo_root = find_git_repo_root(&current_dir)?;
-
-    let sanitized_id = sanitize_branch_name(task_id);
-    let branch_name = format!("{branch_prefix}{sanitized_id}");
-
-    println!("Removing git worktree for task '{task_id}'...");
-    println!("Repository root: {repo_root:?}");
-    println!("Target branch: {branch_name}");
-    println!();
-
-    // First, get list of worktrees to find the one with matching branch
+/// Find the path of the worktree that has `branch_name` checked out
+pub fn find_worktree_path_for_branch(
+    repo_root: &Path,
+    branch_name: &str,
+) -> Result<Option<String>> {
     let output = Command::new("git")
         .args(["worktree", "list", "--porcelain"])
-        .current_dir(&repo_root)
+        .current_dir(repo_root)
         .output()
         .context("Failed to execute git worktree list command")?;
 
@@ -640,29 +1378,253 @@ pub fn remove_git_worktree(
     }
 
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let lines: Vec<&str> = stdout.lines().collect();
-
-    let mut worktree_path: Option<String> = None;
     let mut current_path: Option<String> = None;
 
-    for line in lines {
+    for line in stdout.lines() {
         if line.starts_with("worktree ") {
             current_path = Some(line.strip_prefix("worktree ").unwrap_or(line).to_string());
         } else if line.starts_with("branch ") {
             let branch = line.strip_prefix("branch ").unwrap_or(line);
-            let clean_branch = if branch.starts_with("refs/heads/") {
-                branch.strip_prefix("refs/heads/").unwrap_or(branch)
-            } else {
-                branch
-            };
+            let clean_branch = branch.strip_prefix("refs/heads/").unwrap_or(branch);
 
             if clean_branch == branch_name {
-                worktree_path = current_path.clone();
-                break;
+                return Ok(current_path);
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Show the changes a task produced: committed changes since the base branch plus the working tree diff
+pub fn diff_git_worktree(task_id: &str, branch_prefix: &str, no_pager: bool) -> Result<()> {
+    let current_dir = std::env::current_dir().context("Could not get current directory")?;
+    let repo_root = find_git_repo_root(&current_dir)?;
+
+    let (branch_name, worktree_path) = find_task_worktree(&repo_root, task_id, branch_prefix)?;
+    let worktree_path = worktree_path
+        .ok_or_else(|| anyhow::anyhow!("No worktree found for branch '{}'", branch_name))?;
+    let worktree_path = PathBuf::from(worktree_path);
+
+    let base_branch = find_base_branch(&worktree_path).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Could not determine the base branch to diff against (no origin/HEAD, main or master)"
+        )
+    })?;
+
+    for mut command in worktree_diff_commands(&worktree_path, &base_branch, no_pager) {
+        let status = command
+            .status()
+            .context("Failed to execute git diff command")?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("Git diff command failed"));
+        }
+    }
+
+    Ok(())
+}
+
+/// The `git diff` commands showing a task's changes: the commits on `HEAD` since it
+/// diverged from `base_branch`, then the uncommitted changes in the working tree
+fn worktree_diff_commands(worktree_path: &Path, base_branch: &str, no_pager: bool) -> [Command; 2] {
+    let git_diff = |range: &str| {
+        let mut command = Command::new("git");
+        if no_pager {
+            command.arg("--no-pager");
+        }
+        command.arg("diff").arg(range).current_dir(worktree_path);
+        command
+    };
+
+    [git_diff(&format!("{base_branch}...HEAD")), git_diff("HEAD")]
+}
+
+/// Paths of locked worktrees mapped to their lock reason, from `git worktree list --porcelain`
+pub fn get_locked_worktrees(repo_root: &Path) -> Result<HashMap<String, Option<String>>> {
+    let output = Command::new("git")
+        .args(["worktree", "list", "--porcelain"])
+        .current_dir(repo_root)
+        .output()
+        .context("Failed to execute git worktree list command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!(
+            "Git worktree list command failed: {}",
+            stderr
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut current_path: Option<&str> = None;
+    let mut locked = HashMap::new();
+
+    for line in stdout.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            current_path = Some(path);
+        } else if line == "locked" || line.starts_with("locked ") {
+            if let Some(path) = current_path {
+                let reason = line
+                    .strip_prefix("locked ")
+                    .map(str::trim)
+                    .filter(|reason| !reason.is_empty())
+                    .map(str::to_string);
+                locked.insert(path.to_string(), reason);
             }
         }
     }
 
+    Ok(locked)
+}
+
+fn set_worktree_lock(worktree_path: &str, lock: bool, reason: Option<&str>) -> Result<()> {
+    let mut command = Command::new("git");
+    command.args(["worktree", if lock { "lock" } else { "unlock" }]);
+    if let Some(reason) = reason {
+        command.args(["--reason", reason]);
+    }
+    let output = command
+        .arg(worktree_path)
+        .output()
+        .context("Failed to execute git worktree lock command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!(
+            "Git worktree {} failed: {}",
+            if lock { "lock" } else { "unlock" },
+            stderr.trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Lock or unlock the worktree for `task_id` so cleanup leaves it alone
+pub fn lock_git_worktree(
+    task_id: &str,
+    branch_prefix: &str,
+    lock: bool,
+    reason: Option<&str>,
+) -> Result<()> {
+    let current_dir = std::env::current_dir().context("Could not get current directory")?;
+    let repo_root = find_git_repo_root(&current_dir)?;
+
+    let (branch_name, worktree_path) = find_task_worktree(&repo_root, task_id, branch_prefix)?;
+    let worktree_path = worktree_path
+        .ok_or_else(|| anyhow::anyhow!("No worktree found for branch '{}'", branch_name))?;
+
+    set_worktree_lock(&worktree_path, lock, reason)?;
+
+    if lock {
+        println!("🔒 Locked worktree for task '{task_id}': {worktree_path}");
+    } else {
+        println!("🔓 Unlocked worktree for task '{task_id}': {worktree_path}");
+    }
+
+    Ok(())
+}
+
+/// Rename a task's branch and move its worktree to a directory named after the new ID
+pub fn rename_git_worktree(
+    task_id: &str,
+    new_id: &str,
+    branch_prefix: &str,
+) -> Result<(PathBuf, String)> {
+    let current_dir = std::env::current_dir().context("Could not get current directory")?;
+    let repo_root = find_git_repo_root(&current_dir)?;
+
+    let old_branches = task_branch_names(task_id, branch_prefix);
+    let sanitized_new_id = sanitize_branch_name(new_id);
+    let new_branch = format!("{branch_prefix}{sanitized_new_id}");
+
+    let (old_path, old_branch) = get_matching_worktrees(branch_prefix)?
+        .into_iter()
+        .find_map(|(path, _, branch)| {
+            let branch = branch.strip_prefix("refs/heads/").unwrap_or(&branch);
+            old_branches
+                .iter()
+                .find(|old_branch| *old_branch == branch)
+                .map(|old_branch| (PathBuf::from(path), old_branch.clone()))
+        })
+        .ok_or_else(|| anyhow::anyhow!("No worktree found for branch '{}'", old_branches[0]))?;
+
+    if branch_exists(&repo_root, &new_branch) {
+        return Err(anyhow::anyhow!(
+            "Branch '{}' already exists. Choose a different name.",
+            new_branch
+        ));
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let new_path = old_path
+        .parent()
+        .unwrap_or(&old_path)
+        .join(format!("{sanitized_new_id}_{timestamp:x}"));
+
+    println!("Renaming task '{task_id}' to '{new_id}'...");
+    println!("Branch: {old_branch} -> {new_branch}");
+    println!("Worktree: {old_path:?} -> {new_path:?}");
+
+    let output = Command::new("git")
+        .args(["branch", "-m", &old_branch, &new_branch])
+        .current_dir(&repo_root)
+        .output()
+        .context("Failed to execute git branch rename command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("Git branch rename failed: {}", stderr));
+    }
+
+    let output = Command::new("git")
+        .args(["worktree", "move"])
+        .arg(&old_path)
+        .arg(&new_path)
+        .current_dir(&repo_root)
+        .output()
+        .context("Failed to execute git worktree move command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        // Put the branch back so the worktree stays consistent with its task ID
+        let _ = Command::new("git")
+            .args(["branch", "-m", &new_branch, &old_branch])
+            .current_dir(&repo_root)
+            .output();
+
+        return Err(anyhow::anyhow!("Git worktree move failed: {}", stderr));
+    }
+
+    println!("✓ Worktree renamed");
+    println!("  Branch: {new_branch}");
+    println!("  Path: {new_path:?}");
+
+    Ok((new_path, new_branch))
+}
+
+/// Remove the worktree for `task_id`, first archiving its contents into `archive_dir` if given
+pub fn remove_git_worktree(
+    task_id: &str,
+    branch_prefix: &str,
+    auto_clean_branch: bool,
+    archive_dir: Option<&Path>,
+) -> Result<()> {
+    let current_dir = std::env::current_dir().context("Could not get current directory")?;
+    let repo_root = find_git_repo_root(&current_dir)?;
+
+    // First, get list of worktrees to find the one with matching branch
+    let (branch_name, worktree_path) = find_task_worktree(&repo_root, task_id, branch_prefix)?;
+
+    println!("Removing git worktree for task '{task_id}'...");
+    println!("Repository root: {repo_root:?}");
+    println!("Target branch: {branch_name}");
+    println!();
+
     let worktree_path = match worktree_path {
         Some(path) => path,
         None => {
@@ -673,6 +1635,13 @@ pub fn remove_git_worktree(
 
     println!("Found worktree: {worktree_path}");
 
+    if let Some(archive_dir) = archive_dir {
+        match archive_worktree(Path::new(&worktree_path), &branch_name, archive_dir)? {
+            Some(archive_path) => println!("📦 Worktree archived to: {}", archive_path.display()),
+            None => println!("ℹ️  Worktree is empty, skipping archive"),
+        }
+    }
+
     // Remove the worktree
     println!("Removing worktree...");
     let output = Command::new("git")
@@ -683,6 +1652,13 @@ pub fn remove_git_worktree(
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("locked") {
+            return Err(anyhow::anyhow!(
+                "Worktree is locked: {}\nUnlock it first with: claude-task worktree unlock {}",
+                worktree_path,
+                task_id
+            ));
+        }
         return Err(anyhow::anyhow!(
             "Git worktree remove command failed: {}",
             stderr
@@ -717,6 +1693,59 @@ pub fn remove_git_worktree(
     Ok(())
 }
 
+/// Write the worktree's files (including untracked ones, excluding the `.git` link) to
+/// `<archive_dir>/<branch>-<timestamp>.tar.gz`. Returns `None` if there is nothing to archive.
+pub fn archive_worktree(
+    worktree_path: &Path,
+    branch_name: &str,
+    archive_dir: &Path,
+) -> Result<Option<PathBuf>> {
+    let entries: Vec<_> = fs::read_dir(worktree_path)
+        .with_context(|| format!("Failed to read worktree directory: {worktree_path:?}"))?
+        .collect::<std::io::Result<_>>()
+        .context("Failed to read worktree directory entry")?;
+    let entries: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| entry.file_name() != ".git")
+        .collect();
+
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(archive_dir)
+        .with_context(|| format!("Failed to create archive directory: {archive_dir:?}"))?;
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let archive_path = archive_dir.join(format!(
+        "{}-{timestamp}.tar.gz",
+        sanitize_branch_name(branch_name)
+    ));
+
+    let file = fs::File::create(&archive_path)
+        .with_context(|| format!("Failed to create archive: {archive_path:?}"))?;
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    builder.follow_symlinks(false);
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name();
+        if entry.file_type()?.is_dir() {
+            builder.append_dir_all(&name, &path)
+        } else {
+            builder.append_path_with_name(&path, &name)
+        }
+        .with_context(|| format!("Failed to add {path:?} to archive"))?;
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .with_context(|| format!("Failed to write archive: {archive_path:?}"))?;
+
+    Ok(Some(archive_path))
+}
+
 pub fn get_matching_worktrees(branch_prefix: &str) -> Result<Vec<(String, String, String)>> {
     let current_dir = std::env::current_dir().context("Could not get current directory")?;
     let repo_root = find_git_repo_root(&current_dir)?;
@@ -749,7 +1778,7 @@ pub fn get_matching_worktrees(branch_prefix: &str) -> Result<Vec<(String, String
         if line.starts_with("worktree ") {
             // If we have a previous worktree, check if it matches and store it
             if let Some((path, head, branch)) = current_worktree.take() {
-                if should_clean_worktree(&branch, branch_prefix, &path, &repo_root) {
+                if is_task_worktree(&branch, branch_prefix, &path, &repo_root) {
                     matching_worktrees.push((path, head, branch));
                 }
             }
@@ -780,7 +1809,7 @@ pub fn get_matching_worktrees(branch_prefix: &str) -> Result<Vec<(String, String
 
     // Handle the last worktree if it exists
     if let Some((path, head, branch)) = current_worktree {
-        if should_clean_worktree(&branch, branch_prefix, &path, &repo_root) {
+        if is_task_worktree(&branch, branch_prefix, &path, &repo_root) {
             matching_worktrees.push((path, head, branch));
         }
     }
@@ -788,12 +1817,56 @@ pub fn get_matching_worktrees(branch_prefix: &str) -> Result<Vec<(String, String
     Ok(matching_worktrees)
 }
 
+/// Which worktrees `worktree clean` keeps regardless of their status
+pub struct CleanFilter<'a> {
+    pub branch_prefix: &'a str,
+    pub repo_root: &'a Path,
+    /// Remove locked worktrees too
+    pub force: bool,
+    /// Keep worktrees active more recently than this
+    pub older_than: Option<Duration>,
+}
+
+/// Whether `worktree clean` may remove a worktree, and if not, why
+#[derive(Debug, Clone, PartialEq)]
+pub enum CleanDecision {
+    Remove,
+    /// Not a task worktree: another branch, or the main checkout
+    NotTask,
+    /// Locked, with the lock's reason if one was given
+    Locked(Option<String>),
+    /// Last active this long ago, more recently than `older_than`
+    Recent(Duration),
+    /// `older_than` is set but the worktree's last activity couldn't be determined
+    UnknownAge,
+}
+
+/// Decide whether `worktree clean` may remove the worktree at `path`. `lock` is its
+/// entry from [`get_locked_worktrees`] and `age` comes from its last activity.
 pub fn should_clean_worktree(
     branch: &str,
-    branch_prefix: &str,
     path: &str,
-    repo_root: &std::path::Path,
-) -> bool {
+    lock: Option<&Option<String>>,
+    age: Option<Duration>,
+    filter: &CleanFilter,
+) -> CleanDecision {
+    if !is_task_worktree(branch, filter.branch_prefix, path, filter.repo_root) {
+        return CleanDecision::NotTask;
+    }
+    if let (Some(reason), false) = (lock, filter.force) {
+        return CleanDecision::Locked(reason.clone());
+    }
+    match (age, filter.older_than) {
+        (Some(age), Some(min_age)) if age < min_age => CleanDecision::Recent(age),
+        // Without an age the worktree can't be shown to be old enough, so keep it
+        (None, Some(_)) => CleanDecision::UnknownAge,
+        _ => CleanDecision::Remove,
+    }
+}
+
+/// Whether a worktree belongs to a task: its branch has the prefix and it isn't the
+/// main checkout
+fn is_task_worktree(branch: &str, branch_prefix: &str, path: &str, repo_root: &Path) -> bool {
     // Clean up branch name by removing refs/heads/ prefix for comparison
     let clean_branch = if branch.starts_with("refs/heads/") {
         branch.strip_prefix("refs/heads/").unwrap_or(branch)
@@ -811,7 +1884,18 @@ pub fn should_clean_worktree(
     clean_branch.starts_with(branch_prefix)
 }
 
-#[derive(Debug)]
+/// How reliable a merge detection result is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MergeConfidence {
+    /// Detected via git merge history, an empty diff or a merged PR
+    Confident,
+    /// Detected via fuzzy squash-merge heuristics
+    Heuristic,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct WorktreeStatus {
     pub has_uncommitted_changes: bool,
     pub has_unpushed_commits: bool,
@@ -825,6 +1909,7 @@ pub struct WorktreeStatus {
     pub unpushed_commits: Vec<(String, String)>, // (commit_id, message)
     pub is_likely_merged: bool,
     pub merge_info: Option<String>, // e.g., "squash-merged", "merged", "PR #123"
+    pub merge_confidence: Option<MergeConfidence>,
 }
 
 impl WorktreeStatus {
@@ -834,6 +1919,13 @@ impl WorktreeStatus {
             && (!self.has_no_remote || self.is_likely_merged)
     }
 
+    /// Whether the worktree is only considered clean because of a heuristic merge detection
+    pub fn relies_on_heuristic_merge(&self) -> bool {
+        self.is_likely_merged
+            && self.merge_confidence == Some(MergeConfidence::Heuristic)
+            && (self.has_unpushed_commits || self.has_no_remote)
+    }
+
     pub fn get_status_icon(&self) -> &'static str {
         if self.is_clean() {
             "✅"
@@ -865,31 +1957,103 @@ impl WorktreeStatus {
     }
 }
 
-pub fn check_if_branch_merged(branch: &str, worktree_path: &Path) -> (bool, Option<String>) {
-    // Try to detect if this branch has been merged into main/master
-
-    // First, find the main branch (main or master)
-    let main_branches = ["main", "master"];
-    let mut main_branch = None;
-
-    for mb in &main_branches {
-        let output = Command::new("git")
-            .args(["rev-parse", "--verify", mb])
+/// Find the base branch of the repository containing `worktree_path`: the remote's default
+/// branch (from `origin/HEAD`) if known, otherwise main or master
+pub fn find_base_branch(worktree_path: &Path) -> Option<String> {
+    let ref_exists = |git_ref: &str| {
+        Command::new("git")
+            .args(["rev-parse", "--verify", "--quiet", git_ref])
             .current_dir(worktree_path)
-            .output();
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    };
 
-        if let Ok(output) = output {
-            if output.status.success() {
-                main_branch = Some(*mb);
-                break;
-            }
+    if let Some(default_branch) = remote_default_branch(worktree_path) {
+        // Prefer the local branch so unpushed local merges are still detected
+        if ref_exists(&default_branch) {
+            return Some(default_branch);
         }
+        let remote_ref = format!("origin/{default_branch}");
+        if ref_exists(&remote_ref) {
+            return Some(remote_ref);
+        }
+    }
+
+    ["main", "master"]
+        .into_iter()
+        .find(|mb| ref_exists(mb))
+        .map(str::to_string)
+}
+
+/// The commit `HEAD` points to in `worktree_path`
+pub fn head_commit(worktree_path: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(worktree_path)
+        .output()
+        .context("Failed to execute git rev-parse")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to resolve HEAD: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Number of commits on `HEAD` in `worktree_path` that aren't reachable from `base`
+pub fn count_commits_since(worktree_path: &Path, base: &str) -> Result<usize> {
+    let output = Command::new("git")
+        .args(["rev-list", "--count", &format!("{base}..HEAD")])
+        .current_dir(worktree_path)
+        .output()
+        .context("Failed to execute git rev-list")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to count commits since {base}: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
     }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .context("Unexpected git rev-list output")
+}
+
+/// The default branch of `origin` (e.g. "develop"), as recorded in `refs/remotes/origin/HEAD`
+fn remote_default_branch(worktree_path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args([
+            "symbolic-ref",
+            "--quiet",
+            "--short",
+            "refs/remotes/origin/HEAD",
+        ])
+        .current_dir(worktree_path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let remote_head = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    remote_head
+        .strip_prefix("origin/")
+        .filter(|branch| !branch.is_empty())
+        .map(str::to_string)
+}
+
+pub fn check_if_branch_merged(
+    branch: &str,
+    worktree_path: &Path,
+) -> (bool, Option<String>, Option<MergeConfidence>) {
+    // Try to detect if this branch has been merged into the default branch
 
-    let main_branch = match main_branch {
+    // First, find the default branch (origin's HEAD, falling back to main or master)
+    let main_branch = match find_base_branch(worktree_path) {
         Some(mb) => mb,
-        None => return (false, None), // Can't detect without a main branch
+        None => return (false, None, None), // Can't detect without a main branch
     };
+    let main_branch = main_branch.as_str();
 
     // Method 1: Check if branch is in --merged list (regular merge)
     if let Ok(output) = Command::new("git")
@@ -902,7 +2066,11 @@ pub fn check_if_branch_merged(branch: &str, worktree_path: &Path) -> (bool, Opti
             for line in output_str.lines() {
                 let line = line.trim().trim_start_matches('*').trim();
                 if line == branch {
-                    return (true, Some("merged".to_string()));
+                    return (
+                        true,
+                        Some("merged".to_string()),
+                        Some(MergeConfidence::Confident),
+                    );
                 }
             }
         }
@@ -928,7 +2096,11 @@ pub fn check_if_branch_merged(branch: &str, worktree_path: &Path) -> (bool, Opti
             {
                 if diff_output.status.success() {
                     // No diff means no changes
-                    return (true, Some("no changes".to_string()));
+                    return (
+                        true,
+                        Some("no changes".to_string()),
+                        Some(MergeConfidence::Confident),
+                    );
                 }
 
                 // There are changes, check if they're already in main using git log --grep
@@ -957,7 +2129,11 @@ pub fn check_if_branch_merged(branch: &str, worktree_path: &Path) -> (bool, Opti
                                 .output()
                             {
                                 if main_log.status.success() && !main_log.stdout.is_empty() {
-                                    return (true, Some("likely squash-merged".to_string()));
+                                    return (
+                                        true,
+                                        Some("likely squash-merged".to_string()),
+                                        Some(MergeConfidence::Heuristic),
+                                    );
                                 }
                             }
 
@@ -1004,6 +2180,7 @@ pub fn check_if_branch_merged(branch: &str, worktree_path: &Path) -> (bool, Opti
                                             return (
                                                 true,
                                                 Some("likely squash-merged".to_string()),
+                                                Some(MergeConfidence::Heuristic),
                                             );
                                         }
                                     }
@@ -1035,12 +2212,16 @@ pub fn check_if_branch_merged(branch: &str, worktree_path: &Path) -> (bool, Opti
             let json_str = String::from_utf8_lossy(&output.stdout);
             if json_str.contains("number") {
                 // Simple check - if there's a merged PR for this branch
-                return (true, Some("PR merged".to_string()));
+                return (
+                    true,
+                    Some("PR merged".to_string()),
+                    Some(MergeConfidence::Confident),
+                );
             }
         }
     }
 
-    (false, None)
+    (false, None, None)
 }
 
 pub fn check_worktree_status(worktree_path: &Path) -> Result<WorktreeStatus> {
@@ -1151,10 +2332,10 @@ pub fn check_worktree_status(worktree_path: &Path) -> Result<WorktreeStatus> {
     }
 
     // Check if branch is likely merged
-    let (is_likely_merged, merge_info) = if has_no_remote {
+    let (is_likely_merged, merge_info, merge_confidence) = if has_no_remote {
         check_if_branch_merged(&current_branch, worktree_path)
     } else {
-        (false, None)
+        (false, None, None)
     };
 
     Ok(WorktreeStatus {
@@ -1170,5 +2351,386 @@ pub fn check_worktree_status(worktree_path: &Path) -> Result<WorktreeStatus> {
         unpushed_commits,
         is_likely_merged,
         merge_info,
+        merge_confidence,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+
+    /// Run git in `dir` with a fixed identity, panicking on failure
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Create a repository on `main` with a single commit
+    fn init_repo(dir: &Path) {
+        git(dir, &["init", "-q", "-b", "main"]);
+        fs::write(dir.join("README.md"), "hello\n").unwrap();
+        git(dir, &["add", "README.md"]);
+        git(dir, &["commit", "-q", "-m", "initial commit"]);
+    }
+
+    /// Commit `contents` to `file` on the current branch
+    fn commit_file(dir: &Path, file: &str, contents: &str, message: &str) {
+        fs::write(dir.join(file), contents).unwrap();
+        git(dir, &["add", file]);
+        git(dir, &["commit", "-q", "-m", message]);
+    }
+
+    fn merged_status(confidence: Option<MergeConfidence>) -> WorktreeStatus {
+        WorktreeStatus {
+            has_uncommitted_changes: false,
+            has_unpushed_commits: false,
+            has_no_remote: true,
+            current_branch: "claude-task/fix".to_string(),
+            remote_branch: None,
+            ahead_count: 0,
+            behind_count: 0,
+            changed_files: Vec::new(),
+            untracked_files: Vec::new(),
+            unpushed_commits: Vec::new(),
+            is_likely_merged: confidence.is_some(),
+            merge_info: confidence.map(|_| "merged".to_string()),
+            merge_confidence: confidence,
+        }
+    }
+
+    #[test]
+    fn test_relies_on_heuristic_merge() {
+        // A branch without a remote only counts as clean once it's merged
+        let heuristic = merged_status(Some(MergeConfidence::Heuristic));
+        assert!(heuristic.is_clean());
+        assert!(heuristic.relies_on_heuristic_merge());
+
+        let confident = merged_status(Some(MergeConfidence::Confident));
+        assert!(confident.is_clean());
+        assert!(!confident.relies_on_heuristic_merge());
+
+        let unmerged = merged_status(None);
+        assert!(!unmerged.is_clean());
+        assert!(!unmerged.relies_on_heuristic_merge());
+
+        // Pushed and up to date, the merge result isn't what makes it clean
+        let pushed = WorktreeStatus {
+            has_no_remote: false,
+            ..merged_status(Some(MergeConfidence::Heuristic))
+        };
+        assert!(pushed.is_clean());
+        assert!(!pushed.relies_on_heuristic_merge());
+    }
+
+    #[test]
+    fn test_find_base_branch() {
+        let temp = tempfile::tempdir().unwrap();
+        let repo = temp.path();
+        init_repo(repo);
+        assert_eq!(find_base_branch(repo).as_deref(), Some("main"));
+
+        // The remote's default branch wins over main
+        git(repo, &["branch", "develop"]);
+        git(repo, &["update-ref", "refs/remotes/origin/develop", "HEAD"]);
+        git(
+            repo,
+            &[
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/develop",
+            ],
+        );
+        assert_eq!(find_base_branch(repo).as_deref(), Some("develop"));
+
+        // Without a local copy the remote-tracking branch is used
+        git(repo, &["branch", "-q", "-D", "develop"]);
+        assert_eq!(find_base_branch(repo).as_deref(), Some("origin/develop"));
+    }
+
+    #[test]
+    fn test_worktree_diff() {
+        let temp = tempfile::tempdir().unwrap();
+        let repo = temp.path().join("repo");
+        fs::create_dir(&repo).unwrap();
+        init_repo(&repo);
+
+        let worktree = temp.path().join("task");
+        git(
+            &repo,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                "claude-task/diff",
+                worktree.to_str().unwrap(),
+            ],
+        );
+        commit_file(&worktree, "task.txt", "done\n", "task work");
+        fs::write(worktree.join("README.md"), "hello\nmore\n").unwrap();
+
+        let found = find_worktree_path_for_branch(&repo, "claude-task/diff")
+            .unwrap()
+            .map(PathBuf::from);
+        assert_eq!(
+            found.map(|path| path.canonicalize().unwrap()),
+            Some(worktree.canonicalize().unwrap())
+        );
+        assert_eq!(
+            find_worktree_path_for_branch(&repo, "claude-task/missing").unwrap(),
+            None
+        );
+
+        let diffs: Vec<String> = worktree_diff_commands(&worktree, "main", true)
+            .into_iter()
+            .map(|mut command| {
+                let output = command.output().unwrap();
+                assert!(output.status.success());
+                String::from_utf8_lossy(&output.stdout).to_string()
+            })
+            .collect();
+        // Committed work first, then what's still uncommitted
+        assert!(diffs[0].contains("+++ b/task.txt"), "{}", diffs[0]);
+        assert!(!diffs[0].contains("README.md"), "{}", diffs[0]);
+        assert!(diffs[1].contains("+more"), "{}", diffs[1]);
+        assert!(!diffs[1].contains("task.txt"), "{}", diffs[1]);
+    }
+
+    #[test]
+    fn test_check_if_branch_merged_confidence() {
+        let temp = tempfile::tempdir().unwrap();
+        let repo = temp.path();
+        init_repo(repo);
+
+        // A regular merge is in main's history
+        git(repo, &["checkout", "-q", "-b", "task/merged"]);
+        commit_file(repo, "merged.txt", "merged\n", "add merged");
+        git(repo, &["checkout", "-q", "main"]);
+        git(
+            repo,
+            &["merge", "-q", "--no-ff", "--no-edit", "task/merged"],
+        );
+        let (merged, info, confidence) = check_if_branch_merged("task/merged", repo);
+        assert!(merged);
+        assert_eq!(info.as_deref(), Some("merged"));
+        assert_eq!(confidence, Some(MergeConfidence::Confident));
+
+        // A squash merge can only be guessed from main's commit messages
+        git(repo, &["checkout", "-q", "-b", "task/squashed"]);
+        commit_file(repo, "squashed.txt", "one\n", "first step");
+        commit_file(repo, "squashed.txt", "two\n", "second step");
+        git(repo, &["checkout", "-q", "main"]);
+        git(repo, &["merge", "-q", "--squash", "task/squashed"]);
+        git(repo, &["commit", "-q", "-m", "Squash task/squashed"]);
+        let (merged, info, confidence) = check_if_branch_merged("task/squashed", repo);
+        assert!(merged);
+        assert_eq!(info.as_deref(), Some("likely squash-merged"));
+        assert_eq!(confidence, Some(MergeConfidence::Heuristic));
+    }
+
+    #[test]
+    fn test_map_concurrently_runs_items_in_parallel() -> Result<()> {
+        // Each item waits for the other one, which only works if both run at once
+        let (first_tx, first_rx) = mpsc::channel();
+        let (second_tx, second_rx) = mpsc::channel();
+        let channels = std::sync::Mutex::new(vec![(first_tx, second_rx), (second_tx, first_rx)]);
+
+        let met = map_concurrently(vec![0, 1], 2, |_| {
+            let (tx, rx) = channels.lock().unwrap().pop().unwrap();
+            tx.send(()).unwrap();
+            rx.recv_timeout(Duration::from_secs(30)).is_ok()
+        })?;
+        assert_eq!(met, vec![true, true]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_concurrently_caps_workers_and_keeps_order() -> Result<()> {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let doubled = map_concurrently((0..32).collect(), 3, |n: u32| {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(2));
+            running.fetch_sub(1, Ordering::SeqCst);
+            n * 2
+        })?;
+
+        assert_eq!(doubled, (0..32).map(|n| n * 2).collect::<Vec<_>>());
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_concurrently_reports_panics() {
+        let result = map_concurrently(vec![1, 2, 3], 2, |n: u32| {
+            if n == 2 {
+                panic!("status check failed");
+            }
+            n
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KB");
+        assert_eq!(format_size(5 * 1024 * 1024 + 512 * 1024), "5.5 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_sanitize_branch_name() {
+        assert_eq!(sanitize_branch_name("brave-otter-1234"), "brave-otter-1234");
+        assert_eq!(sanitize_branch_name("fix_login"), "fix_login");
+        assert_eq!(sanitize_branch_name("..foo"), "foo");
+        assert_eq!(sanitize_branch_name("my/branch.lock"), "my-branch-lock");
+        assert_eq!(sanitize_branch_name("a..b"), "a-b");
+        assert_eq!(sanitize_branch_name("--fix  the bug!--"), "fix-the-bug");
+        assert_eq!(sanitize_branch_name("fix--login"), "fix-login");
+
+        // Nothing usable left: derive a stable ID from the whole name
+        let symbols = sanitize_branch_name("!!!");
+        assert!(symbols.starts_with("task-"));
+        assert_eq!(symbols.len(), "task-".len() + 16);
+        assert_eq!(symbols, sanitize_branch_name("!!!"));
+        assert_ne!(symbols, sanitize_branch_name("!!!!"));
+        // Inputs sharing a long prefix still get different IDs
+        let long = "!".repeat(40);
+        assert_ne!(
+            sanitize_branch_name(&format!("{long}?")),
+            sanitize_branch_name(&format!("{long}#"))
+        );
+        assert!(sanitize_branch_name("").starts_with("task-"));
+    }
+
+    #[test]
+    fn test_task_branch_names() {
+        assert_eq!(
+            task_branch_names("brave-otter-1234", "claude-task/"),
+            vec!["claude-task/brave-otter-1234"]
+        );
+        // Branches named by the older one-for-one mapping are still found
+        assert_eq!(
+            task_branch_names("fix login!", "claude-task/"),
+            vec!["claude-task/fix-login", "claude-task/fix-login-"]
+        );
+        assert_eq!(
+            task_branch_names("fix--login", "ct/"),
+            vec!["ct/fix-login", "ct/fix--login"]
+        );
+        assert_eq!(task_branch_names("!!!", "ct/").len(), 1);
+    }
+
+    #[test]
+    fn test_reserve_worktree_path_avoids_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = (0..20)
+            .map(|_| reserve_worktree_path(dir.path(), "task").unwrap())
+            .collect();
+
+        let unique: std::collections::HashSet<_> = paths.iter().collect();
+        assert_eq!(unique.len(), paths.len());
+        for path in &paths {
+            assert!(path.is_dir());
+            let name = path.file_name().unwrap().to_string_lossy();
+            assert!(name.starts_with("task_"));
+        }
+    }
+
+    #[test]
+    fn test_resolve_worktree_directory_relative() {
+        assert_eq!(
+            resolve_worktree_directory("worktrees").unwrap(),
+            std::env::current_dir().unwrap().join("worktrees")
+        );
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30m").unwrap(), Duration::from_secs(30 * 60));
+        assert_eq!(parse_age("48h").unwrap(), Duration::from_secs(48 * 3600));
+        assert_eq!(parse_age("7d").unwrap(), Duration::from_secs(7 * 86400));
+        assert_eq!(parse_age("2w").unwrap(), Duration::from_secs(14 * 86400));
+
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_should_clean_worktree() {
+        let repo_root = Path::new("/repo");
+        let mut filter = CleanFilter {
+            branch_prefix: "claude-task/",
+            repo_root,
+            force: false,
+            older_than: None,
+        };
+        let path = "/worktrees/fix_1";
+        let branch = "refs/heads/claude-task/fix";
+        let decide = |filter: &CleanFilter, lock, age| {
+            should_clean_worktree(branch, path, lock, age, filter)
+        };
+
+        assert_eq!(decide(&filter, None, None), CleanDecision::Remove);
+        assert_eq!(
+            should_clean_worktree("refs/heads/main", path, None, None, &filter),
+            CleanDecision::NotTask
+        );
+        assert_eq!(
+            should_clean_worktree(branch, "/repo", None, None, &filter),
+            CleanDecision::NotTask
+        );
+
+        // Locked worktrees are kept unless forced
+        let reason = Some("reviewing".to_string());
+        assert_eq!(
+            decide(&filter, Some(&reason), None),
+            CleanDecision::Locked(reason.clone())
+        );
+        assert_eq!(
+            decide(&filter, Some(&None), None),
+            CleanDecision::Locked(None)
+        );
+
+        // So are recently active ones, when an age is required
+        let hour = Duration::from_secs(3600);
+        filter.older_than = Some(24 * hour);
+        assert_eq!(
+            decide(&filter, None, Some(hour)),
+            CleanDecision::Recent(hour)
+        );
+        assert_eq!(
+            decide(&filter, None, Some(48 * hour)),
+            CleanDecision::Remove
+        );
+        // Without a known age a worktree can't be shown to be old enough
+        assert_eq!(decide(&filter, None, None), CleanDecision::UnknownAge);
+
+        filter.force = true;
+        assert_eq!(
+            decide(&filter, Some(&reason), Some(48 * hour)),
+            CleanDecision::Remove
+        );
+        assert_eq!(
+            decide(&filter, Some(&reason), Some(hour)),
+            CleanDecision::Recent(hour)
+        );
+    }
+}
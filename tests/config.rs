@@ -54,6 +54,337 @@ fn test_config_init_creates_default_config() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_config_init_yaml_round_trips() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.yaml");
+    let config_arg = config_path.to_str().unwrap();
+
+    let (_, stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "init",
+        "--format",
+        "yaml",
+    ])?;
+    assert!(success, "stderr: {stderr}");
+
+    let content = std::fs::read_to_string(&config_path)?;
+    let config: serde_yaml::Value = serde_yaml::from_str(&content)?;
+    assert_eq!(config["paths"]["branchPrefix"], "claude-task/");
+    assert_eq!(config["taskRunner"], "docker");
+
+    // Saving keeps the file in YAML
+    let (_, stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "runner",
+        "kubernetes",
+    ])?;
+    assert!(success, "stderr: {stderr}");
+    let content = std::fs::read_to_string(&config_path)?;
+    assert!(serde_json::from_str::<serde_json::Value>(&content).is_err());
+    let config: serde_yaml::Value = serde_yaml::from_str(&content)?;
+    assert_eq!(config["taskRunner"], "kubernetes");
+
+    let (_, stderr, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "validate"])?;
+    assert!(success, "stderr: {stderr}");
+
+    // The extension decides the format, so a mismatched --format is rejected
+    let json_path = temp_dir.path().join("config.json");
+    let (_, _, success) = run_claude_task(&[
+        "--config-path",
+        json_path.to_str().unwrap(),
+        "config",
+        "init",
+        "--format",
+        "yaml",
+    ])?;
+    assert!(!success);
+    assert!(!json_path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_config_path_from_env() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let env_path = temp_dir.path().join("env-config.json");
+    let flag_path = temp_dir.path().join("flag-config.json");
+    let run_with_env = |args: &[&str]| -> Result<(String, bool)> {
+        let output = Command::new(env!("CARGO_BIN_EXE_claude-task"))
+            .env("CLAUDE_TASK_CONFIG", &env_path)
+            .args(args)
+            .output()?;
+        Ok((
+            String::from_utf8_lossy(&output.stderr).to_string(),
+            output.status.success(),
+        ))
+    };
+
+    // A missing file named by the variable is an error, like a missing --config-path
+    let (stderr, success) = run_with_env(&["config", "show"])?;
+    assert!(!success);
+    assert!(stderr.contains("Config file not found"), "stderr: {stderr}");
+
+    let (stderr, success) = run_with_env(&["config", "init", "--no-schema"])?;
+    assert!(success, "stderr: {stderr}");
+    assert!(env_path.exists());
+
+    // --config-path takes precedence over the variable
+    let (stderr, success) = run_with_env(&[
+        "--config-path",
+        flag_path.to_str().unwrap(),
+        "config",
+        "init",
+        "--no-schema",
+    ])?;
+    assert!(success, "stderr: {stderr}");
+    assert!(flag_path.exists());
+
+    let (stderr, success) = run_with_env(&["config", "runner", "kubernetes"])?;
+    assert!(success, "stderr: {stderr}");
+    let env_config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&env_path)?)?;
+    let flag_config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&flag_path)?)?;
+    assert_eq!(env_config["taskRunner"], "kubernetes");
+    assert_eq!(flag_config["taskRunner"], "docker");
+
+    Ok(())
+}
+
+#[test]
+fn test_config_expands_env_vars() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.json");
+    let config_arg = config_path.to_str().unwrap();
+    let (_, stderr, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "init", "--no-schema"])?;
+    assert!(success, "stderr: {stderr}");
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    config["docker"]["imageName"] = "${CT_TEST_REGISTRY}/claude-task:latest".into();
+    config["paths"]["branchPrefix"] = "${CT_TEST_PREFIX:-team}/".into();
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let run_with_registry = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_claude-task"))
+            .env("CT_TEST_REGISTRY", "registry.example.com")
+            .env_remove("CT_TEST_PREFIX")
+            .args(["--config-path", config_arg])
+            .args(args)
+            .output()
+    };
+
+    let output = run_with_registry(&["config", "show", "--json"])?;
+    assert!(output.status.success());
+    let shown: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        shown["docker"]["imageName"],
+        "registry.example.com/claude-task:latest"
+    );
+    assert_eq!(shown["paths"]["branchPrefix"], "team/");
+
+    // Updating the config keeps the references
+    let output = run_with_registry(&["config", "runner", "kubernetes"])?;
+    assert!(output.status.success());
+    let saved = std::fs::read_to_string(&config_path)?;
+    assert!(saved.contains("${CT_TEST_REGISTRY}/claude-task:latest"));
+    assert!(saved.contains("${CT_TEST_PREFIX:-team}/"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_claude-task"))
+        .env_remove("CT_TEST_REGISTRY")
+        .args(["--config-path", config_arg, "config", "show"])
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("CT_TEST_REGISTRY is not set"));
+
+    Ok(())
+}
+
+#[test]
+fn test_config_set() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.json");
+    let config_arg = config_path.to_str().unwrap();
+    let (_, stderr, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "init", "--no-schema"])?;
+    assert!(success, "stderr: {stderr}");
+
+    let (stdout, stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "set",
+        "docker.imageName",
+        "registry.example.com/claude-task:1.0",
+    ])?;
+    assert!(success, "stderr: {stderr}");
+    assert!(stdout.contains("Set docker.imageName"));
+    let (_, stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "set",
+        "docker.timeoutSeconds",
+        "900",
+    ])?;
+    assert!(success, "stderr: {stderr}");
+
+    let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    assert_eq!(
+        config["docker"]["imageName"],
+        "registry.example.com/claude-task:1.0"
+    );
+    assert_eq!(config["docker"]["timeoutSeconds"], 900);
+
+    // Unknown keys and values that fail validation leave the file alone
+    let (_, stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "set",
+        "docker.nope",
+        "x",
+    ])?;
+    assert!(!success);
+    assert!(stderr.contains("Valid keys"), "stderr: {stderr}");
+    let (_, _, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "set",
+        "docker.networkMode",
+        "",
+    ])?;
+    assert!(!success);
+    let unchanged: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    assert_eq!(unchanged, config);
+
+    Ok(())
+}
+
+#[test]
+fn test_config_unset_token() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.json");
+    let config_arg = config_path.to_str().unwrap();
+    let (_, stderr, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "init", "--no-schema"])?;
+    assert!(success, "stderr: {stderr}");
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    config["claudeCredentials"] = serde_json::json!({ "token": "sk-ant-oat01-test" });
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let (stdout, stderr, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "unset-token"])?;
+    assert!(success, "stderr: {stderr}");
+    assert!(stdout.contains("token removed"), "stdout: {stdout}");
+    let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    assert!(config.get("claudeCredentials").is_none_or(|c| c.is_null()));
+
+    // Unsetting again is a no-op
+    let (stdout, _, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "unset-token"])?;
+    assert!(success);
+    assert!(stdout.contains("No Claude OAuth token"), "stdout: {stdout}");
+
+    Ok(())
+}
+
+#[test]
+fn test_config_show_diff() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.json");
+    let config_arg = config_path.to_str().unwrap();
+    let (_, stderr, success) = run_claude_task(&["--config-path", config_arg, "config", "init"])?;
+    assert!(success, "stderr: {stderr}");
+
+    let (stdout, stderr, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "show", "--diff"])?;
+    assert!(success, "stderr: {stderr}");
+    assert!(
+        stdout.contains("No changes from the defaults"),
+        "stdout: {stdout}"
+    );
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    config["docker"]["imageName"] = "my/image:1".into();
+    config["claudeCredentials"] = serde_json::json!({ "token": "sk-ant-oat01-secret-value" });
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let (stdout, _, success) =
+        run_claude_task(&["--config-path", config_arg, "config", "show", "--diff"])?;
+    assert!(success);
+    assert!(stdout.contains("docker.imageName"), "stdout: {stdout}");
+    assert!(stdout.contains("\"my/image:1\""), "stdout: {stdout}");
+    assert!(!stdout.contains("secret-value"), "stdout: {stdout}");
+    assert!(!stdout.contains("paths."), "stdout: {stdout}");
+
+    let (stdout, _, success) = run_claude_task(&[
+        "--config-path",
+        config_arg,
+        "config",
+        "show",
+        "--diff",
+        "--json",
+    ])?;
+    assert!(success);
+    let changes: serde_json::Value = serde_json::from_str(&stdout)?;
+    let keys: Vec<&str> = changes
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|change| change["key"].as_str())
+        .collect();
+    assert_eq!(keys, vec!["claudeCredentials", "docker.imageName"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_config_schema_uses_camel_case_keys() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("config.json");
+
+    let (stdout, _stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_path.to_str().unwrap(),
+        "config",
+        "schema",
+    ])?;
+    assert!(success);
+
+    let schema: serde_json::Value = serde_json::from_str(&stdout)?;
+    let properties = &schema["properties"];
+    assert!(properties.get("globalOptionDefaults").is_some());
+    assert!(properties.get("taskRunner").is_some());
+    assert!(properties.get("kubeConfig").is_some());
+    assert!(properties.get("global_option_defaults").is_none());
+
+    // init writes the schema next to the config and references it
+    let (_stdout, _stderr, success) = run_claude_task(&[
+        "--config-path",
+        config_path.to_str().unwrap(),
+        "config",
+        "init",
+    ])?;
+    assert!(success);
+    assert!(temp_dir.path().join("config.schema.json").exists());
+    let config: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    assert_eq!(config["$schema"], "./config.schema.json");
+
+    Ok(())
+}
+
 #[test]
 fn test_config_init_with_existing_file() -> Result<()> {
     let temp_dir = TempDir::new()?;
@@ -188,6 +519,49 @@ fn test_config_show_json_format() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_config_env_overrides() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let config_path = temp_dir.path().join("test-config.json");
+    run_claude_task(&[
+        "--config-path",
+        config_path.to_str().unwrap(),
+        "config",
+        "init",
+    ])?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_claude-task"))
+        .args([
+            "--config-path",
+            config_path.to_str().unwrap(),
+            "config",
+            "show",
+            "--json",
+        ])
+        .env("CLAUDE_TASK_IMAGE", "registry.example.com/claude-task:ci")
+        .env("CLAUDE_TASK_WORKTREE_BASE_DIR", "/ci/worktrees")
+        .env("CLAUDE_TASK_KUBE_NAMESPACE", "ci-jobs")
+        .output()?;
+    assert!(output.status.success());
+
+    let config: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(
+        config["docker"]["imageName"],
+        "registry.example.com/claude-task:ci"
+    );
+    assert_eq!(config["paths"]["worktreeBaseDir"], "/ci/worktrees");
+    assert_eq!(config["kubeConfig"]["namespace"], "ci-jobs");
+
+    // The overrides are never written back to the file
+    let on_disk: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    assert_eq!(
+        on_disk["paths"]["worktreeBaseDir"],
+        "~/.claude-task/worktrees"
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_config_show_pretty_format() -> Result<()> {
     let temp_dir = TempDir::new()?;
@@ -0,0 +1,64 @@
+use anyhow::Result;
+use claude_task::config::Config;
+use claude_task::docker::{versioned_image_name, ClaudeTaskConfig, DockerManager};
+use std::process::Command;
+use tempfile::TempDir;
+
+fn build_config(context: &TempDir) -> ClaudeTaskConfig {
+    ClaudeTaskConfig {
+        task_id: "pin-test".to_string(),
+        workspace_path: context.path().to_string_lossy().to_string(),
+        timezone: "UTC".to_string(),
+        dockerfile_path: context
+            .path()
+            .join("Dockerfile")
+            .to_string_lossy()
+            .to_string(),
+        context_path: context.path().to_string_lossy().to_string(),
+        ht_mcp_port: None,
+        web_view_proxy_port: None,
+        no_cache: false,
+        pull: false,
+    }
+}
+
+fn manager_for(image: &str) -> Result<DockerManager> {
+    let mut docker = Config::default().docker;
+    docker.image_name = image.to_string();
+    DockerManager::new(docker)
+}
+
+/// Builds two differently tagged images and checks that each one's runs use its own
+/// version pin rather than whichever was built last
+#[tokio::test]
+#[ignore = "docker"]
+async fn test_differently_tagged_builds_keep_separate_pins() -> Result<()> {
+    const PY: &str = "claude-task-pin-test:py";
+    const NODE: &str = "claude-task-pin-test:node";
+
+    let mut pins = Vec::new();
+    for (image, marker) in [(PY, "py"), (NODE, "node")] {
+        let context = TempDir::new()?;
+        std::fs::write(context.path().join("marker"), marker)?;
+        std::fs::write(
+            context.path().join("Dockerfile"),
+            "FROM scratch\nCOPY marker /marker\n",
+        )?;
+        manager_for(image)?
+            .build_image(&build_config(&context))
+            .await?;
+        pins.push(versioned_image_name(image).unwrap());
+    }
+    assert_ne!(pins[0], pins[1]);
+
+    let py = manager_for(PY)?.preferred_image().await;
+    let node = manager_for(NODE)?.preferred_image().await;
+
+    let _ = Command::new("docker")
+        .args(["rmi", "-f", PY, NODE, &pins[0], &pins[1]])
+        .output();
+
+    assert_eq!(py.as_deref(), Some(pins[0].as_str()));
+    assert_eq!(node.as_deref(), Some(pins[1].as_str()));
+    Ok(())
+}
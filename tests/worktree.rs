@@ -0,0 +1,725 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Helper to run a git command in `dir`, panicking on failure
+fn git(dir: &Path, args: &[&str]) {
+    let output = Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// Create a git repository with a single commit and a config file pointing worktrees into the temp dir
+fn setup_repo(temp_dir: &TempDir) -> Result<(PathBuf, PathBuf)> {
+    setup_repo_with_branch(temp_dir, "main")
+}
+
+fn setup_repo_with_branch(temp_dir: &TempDir, default_branch: &str) -> Result<(PathBuf, PathBuf)> {
+    let repo_dir = temp_dir.path().join("repo");
+    std::fs::create_dir_all(&repo_dir)?;
+    git(&repo_dir, &["init", "-q", "-b", default_branch]);
+    std::fs::write(repo_dir.join("README.md"), "hello\n")?;
+    git(&repo_dir, &["add", "README.md"]);
+    git(&repo_dir, &["commit", "-q", "-m", "initial commit"]);
+
+    let config_path = temp_dir.path().join("config.json");
+    let (_, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &[
+            "--config-path",
+            config_path.to_str().unwrap(),
+            "config",
+            "init",
+        ],
+    )?;
+    assert!(success, "config init failed: {stderr}");
+
+    let content = std::fs::read_to_string(&config_path)?;
+    let mut config: serde_json::Value = serde_json::from_str(&content)?;
+    config["paths"]["worktreeBaseDir"] =
+        serde_json::Value::String(temp_dir.path().join("worktrees").to_string_lossy().into());
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    Ok((repo_dir, config_path))
+}
+
+/// Helper to run claude-task in `dir` with an isolated HOME and capture output
+fn run_claude_task(dir: &Path, home: &Path, args: &[&str]) -> Result<(String, String, bool)> {
+    let exe_path = PathBuf::from(env!("CARGO_BIN_EXE_claude-task"));
+    let output = Command::new(exe_path)
+        .args(args)
+        .current_dir(dir)
+        .env("HOME", home)
+        .output()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+    Ok((stdout, stderr, output.status.success()))
+}
+
+#[test]
+fn test_worktree_create_with_existing_branch() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+
+    // Leave a branch behind as a previous task with the same ID would
+    git(&repo_dir, &["branch", "claude-task/my-task"]);
+
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &[
+            "--config-path",
+            config_path.to_str().unwrap(),
+            "worktree",
+            "create",
+            "my-task",
+        ],
+    )?;
+
+    assert!(!success, "stdout: {stdout}");
+    assert!(stderr.contains("Branch 'claude-task/my-task' already exists"));
+    assert!(stderr.contains("my-task-2"));
+    assert!(stderr.contains("git worktree add <path> claude-task/my-task"));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_list_json_without_upstream() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+
+    let (_, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "create", "my-task"],
+    )?;
+    assert!(success, "worktree create failed: {stderr}");
+
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "list", "--json"],
+    )?;
+    assert!(success, "worktree list failed: {stderr}");
+
+    let worktrees: serde_json::Value = serde_json::from_str(&stdout)?;
+    let worktrees = worktrees.as_array().expect("expected a JSON array");
+    assert_eq!(worktrees.len(), 1);
+
+    let worktree = &worktrees[0];
+    assert_eq!(worktree["branch"], "claude-task/my-task");
+    assert_eq!(worktree["head"].as_str().map(str::len), Some(40));
+    assert_eq!(worktree["status"]["hasNoRemote"], true);
+    assert_eq!(worktree["status"]["hasUncommittedChanges"], false);
+    assert_eq!(worktree["status"]["aheadCount"], 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_create_from_existing_branch() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+
+    git(&repo_dir, &["branch", "feature/resume-me"]);
+
+    let create_args = [
+        "--config-path",
+        config_arg,
+        "worktree",
+        "create",
+        "resume",
+        "--branch",
+        "feature/resume-me",
+    ];
+    let (stdout, stderr, success) = run_claude_task(&repo_dir, temp_dir.path(), &create_args)?;
+    assert!(success, "worktree create failed: {stderr}");
+    assert!(stdout.contains("Using existing branch 'feature/resume-me'"));
+
+    // The branch is now checked out, so a second worktree for it must be refused
+    let (_, stderr, success) = run_claude_task(&repo_dir, temp_dir.path(), &create_args)?;
+    assert!(!success);
+    assert!(stderr.contains("already checked out in another worktree"));
+    // The hint starts on its own line, without the source indentation
+    assert!(stderr.contains("\nRemove that worktree first"));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_create_from_base_ref() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+
+    // Move the repo onto a feature branch with an extra commit
+    git(&repo_dir, &["checkout", "-q", "-b", "feature"]);
+    std::fs::write(repo_dir.join("feature.txt"), "feature\n")?;
+    git(&repo_dir, &["add", "feature.txt"]);
+    git(&repo_dir, &["commit", "-q", "-m", "feature commit"]);
+
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &[
+            "--config-path",
+            config_arg,
+            "worktree",
+            "create",
+            "from-main",
+            "--from",
+            "main",
+        ],
+    )?;
+    assert!(success, "worktree create failed: {stderr}");
+    assert!(stdout.contains("Base ref: main"));
+
+    let worktree_dir = std::fs::read_dir(temp_dir.path().join("worktrees"))?
+        .next()
+        .expect("worktree directory should exist")?
+        .path();
+    assert!(worktree_dir.join("README.md").exists());
+    assert!(!worktree_dir.join("feature.txt").exists());
+
+    let (_, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &[
+            "--config-path",
+            config_arg,
+            "worktree",
+            "create",
+            "bad-ref",
+            "--from",
+            "does-not-exist",
+        ],
+    )?;
+    assert!(!success);
+    assert!(stderr.contains("Base ref 'does-not-exist' does not exist"));
+
+    Ok(())
+}
+
+#[test]
+fn test_collect_worktree_infos_sorts_by_path() -> Result<()> {
+    use claude_task::worktree::collect_worktree_infos;
+
+    const WORKTREE_COUNT: usize = 8;
+
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, _) = setup_repo(&temp_dir)?;
+
+    let mut entries = Vec::new();
+    for i in (0..WORKTREE_COUNT).rev() {
+        let path = temp_dir.path().join(format!("wt-{i}"));
+        let branch = format!("claude-task/task-{i}");
+        git(
+            &repo_dir,
+            &[
+                "worktree",
+                "add",
+                "-q",
+                "-b",
+                &branch,
+                path.to_str().unwrap(),
+            ],
+        );
+        entries.push((
+            path.to_string_lossy().to_string(),
+            String::new(),
+            format!("refs/heads/{branch}"),
+        ));
+    }
+
+    let infos = collect_worktree_infos(entries, false)?;
+
+    // Output is ordered by path regardless of completion order
+    let paths: Vec<&str> = infos.iter().map(|info| info.path.as_str()).collect();
+    let mut sorted = paths.clone();
+    sorted.sort();
+    assert_eq!(paths, sorted);
+    assert_eq!(infos.len(), WORKTREE_COUNT);
+    assert!(infos.iter().all(|info| info.status.is_some()));
+    assert!(infos[0].branch.starts_with("claude-task/task-"));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_remove_with_archive() -> Result<()> {
+    use flate2::read::GzDecoder;
+
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+
+    let (_, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "create", "keep-me"],
+    )?;
+    assert!(success, "worktree create failed: {stderr}");
+
+    let worktree_dir = std::fs::read_dir(temp_dir.path().join("worktrees"))?
+        .next()
+        .expect("worktree directory should exist")?
+        .path();
+    std::fs::write(worktree_dir.join("notes.txt"), "uncommitted work\n")?;
+
+    let archive_dir = temp_dir.path().join("archives");
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &[
+            "--config-path",
+            config_arg,
+            "worktree",
+            "remove",
+            "keep-me",
+            "--archive",
+            archive_dir.to_str().unwrap(),
+        ],
+    )?;
+    assert!(success, "worktree remove failed: {stderr}");
+    assert!(stdout.contains("Worktree archived to"));
+    assert!(!worktree_dir.exists());
+
+    let archive_path = std::fs::read_dir(&archive_dir)?
+        .next()
+        .expect("archive should exist")?
+        .path();
+    let file_name = archive_path.file_name().unwrap().to_string_lossy();
+    assert!(file_name.starts_with("claude-task-keep-me-"));
+    assert!(file_name.ends_with(".tar.gz"));
+
+    let mut archive = tar::Archive::new(GzDecoder::new(std::fs::File::open(&archive_path)?));
+    let names: Vec<String> = archive
+        .entries()?
+        .map(|entry| Ok(entry?.path()?.to_string_lossy().to_string()))
+        .collect::<Result<_>>()?;
+    assert!(names.contains(&"notes.txt".to_string()));
+    assert!(names.contains(&"README.md".to_string()));
+    assert!(!names.iter().any(|name| name == ".git"));
+
+    Ok(())
+}
+
+#[test]
+fn test_create_worktree_carries_uncommitted_changes() -> Result<()> {
+    use claude_task::worktree::{create_git_worktree, CarryChanges, WorktreeCreateOptions};
+
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, _) = setup_repo(&temp_dir)?;
+
+    std::fs::write(repo_dir.join("README.md"), "hello\nwork in progress\n")?;
+    std::fs::create_dir_all(repo_dir.join("drafts"))?;
+    std::fs::write(repo_dir.join("drafts/plan.md"), "plan\n")?;
+
+    let (worktree_path, _) = create_git_worktree(
+        "carry",
+        "claude-task/",
+        temp_dir.path().join("worktrees").to_str().unwrap(),
+        &WorktreeCreateOptions {
+            carry_changes: CarryChanges::All,
+            repo_dir: Some(&repo_dir),
+            ..Default::default()
+        },
+    )?;
+
+    assert_eq!(
+        std::fs::read_to_string(worktree_path.join("README.md"))?,
+        "hello\nwork in progress\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(worktree_path.join("drafts/plan.md"))?,
+        "plan\n"
+    );
+    // The original repository keeps its changes
+    assert!(repo_dir.join("drafts/plan.md").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_rename() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+    let ct = |args: &[&str]| {
+        let mut full_args = vec!["--config-path", config_arg, "worktree"];
+        full_args.extend_from_slice(args);
+        run_claude_task(&repo_dir, temp_dir.path(), &full_args)
+    };
+
+    let (_, stderr, success) = ct(&["create", "misty-river-1234"])?;
+    assert!(success, "worktree create failed: {stderr}");
+    let (_, stderr, success) = ct(&["create", "taken"])?;
+    assert!(success, "worktree create failed: {stderr}");
+
+    // Renaming onto an existing task is refused
+    let (_, stderr, success) = ct(&["rename", "misty-river-1234", "taken"])?;
+    assert!(!success);
+    assert!(stderr.contains("Branch 'claude-task/taken' already exists"));
+
+    let (_, stderr, success) = ct(&["rename", "misty-river-1234", "login-fix"])?;
+    assert!(success, "worktree rename failed: {stderr}");
+
+    let (stdout, _, success) = ct(&["list", "--json"])?;
+    assert!(success);
+    let worktrees: serde_json::Value = serde_json::from_str(&stdout)?;
+    let renamed = worktrees
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|wt| wt["branch"] == "claude-task/login-fix")
+        .expect("renamed worktree should be listed");
+    assert!(renamed["path"].as_str().unwrap().contains("login-fix_"));
+    assert!(!stdout.contains("misty-river-1234"));
+
+    let (stdout, stderr, success) = ct(&["remove", "login-fix"])?;
+    assert!(success, "worktree remove failed: {stderr}");
+    assert!(stdout.contains("Worktree removed"));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_commands_find_legacy_branch_names() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+    let ct = |args: &[&str]| {
+        let mut full_args = vec!["--config-path", config_arg, "worktree"];
+        full_args.extend_from_slice(args);
+        run_claude_task(&repo_dir, temp_dir.path(), &full_args)
+    };
+
+    // A worktree created before repeated dashes were collapsed
+    let legacy_path = temp_dir.path().join("worktrees").join("fix--login_1");
+    git(
+        &repo_dir,
+        &[
+            "worktree",
+            "add",
+            "-q",
+            "-b",
+            "claude-task/fix--login",
+            legacy_path.to_str().unwrap(),
+        ],
+    );
+
+    let (_, stderr, success) = ct(&["lock", "fix--login"])?;
+    assert!(success, "worktree lock failed: {stderr}");
+    let (_, stderr, success) = ct(&["unlock", "fix--login"])?;
+    assert!(success, "worktree unlock failed: {stderr}");
+
+    let (stdout, stderr, success) = ct(&["remove", "fix--login"])?;
+    assert!(success, "worktree remove failed: {stderr}");
+    assert!(stdout.contains("Target branch: claude-task/fix--login"));
+    assert!(stdout.contains("Worktree removed"));
+    assert!(!legacy_path.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_merge_detection_uses_remote_default_branch() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo_with_branch(&temp_dir, "trunk")?;
+    let config_arg = config_path.to_str().unwrap();
+
+    // Publish to a bare origin whose HEAD points at trunk
+    let origin_dir = temp_dir.path().join("origin.git");
+    git(
+        temp_dir.path(),
+        &[
+            "clone",
+            "-q",
+            "--bare",
+            repo_dir.to_str().unwrap(),
+            origin_dir.to_str().unwrap(),
+        ],
+    );
+    git(
+        &repo_dir,
+        &["remote", "add", "origin", origin_dir.to_str().unwrap()],
+    );
+    git(&repo_dir, &["fetch", "-q", "origin"]);
+    git(&repo_dir, &["remote", "set-head", "origin", "--auto"]);
+
+    let (_, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "create", "done"],
+    )?;
+    assert!(success, "worktree create failed: {stderr}");
+
+    // A branch with no commits beyond trunk counts as merged into it
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "list", "--json"],
+    )?;
+    assert!(success, "worktree list failed: {stderr}");
+
+    let worktrees: serde_json::Value = serde_json::from_str(&stdout)?;
+    let status = &worktrees[0]["status"];
+    assert_eq!(status["hasNoRemote"], true);
+    assert_eq!(status["isLikelyMerged"], true, "status: {status}");
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_list_labels_tasks_with_custom_prefix() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    config["paths"]["branchPrefix"] = serde_json::Value::String("agent/".to_string());
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let (_, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &[
+            "--config-path",
+            config_arg,
+            "worktree",
+            "create",
+            "labelled",
+        ],
+    )?;
+    assert!(success, "worktree create failed: {stderr}");
+
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "list"],
+    )?;
+    assert!(success, "worktree list failed: {stderr}");
+    assert!(stdout.contains("Branch: agent/labelled"), "{stdout}");
+    assert!(stdout.contains("(Claude task)"), "{stdout}");
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_list_only_filter() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+    let ct = |args: &[&str]| {
+        let mut full_args = vec!["--config-path", config_arg, "worktree"];
+        full_args.extend_from_slice(args);
+        run_claude_task(&repo_dir, temp_dir.path(), &full_args)
+    };
+    let listed_branches = |filter: &str| -> Result<Vec<String>> {
+        let (stdout, stderr, success) = ct(&["list", "--json", "--only", filter])?;
+        assert!(success, "worktree list failed: {stderr}");
+        let worktrees: serde_json::Value = serde_json::from_str(&stdout)?;
+        Ok(worktrees
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|wt| wt["branch"].as_str().unwrap().to_string())
+            .collect())
+    };
+
+    // A task with no changes is clean; one with uncommitted edits is dirty
+    ct(&["create", "untouched"])?;
+    ct(&["create", "edited"])?;
+    let edited_dir = std::fs::read_dir(temp_dir.path().join("worktrees"))?
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().contains("edited_"))
+        .expect("edited worktree should exist");
+    std::fs::write(edited_dir.join("new.txt"), "work\n")?;
+
+    assert_eq!(listed_branches("clean")?, vec!["claude-task/untouched"]);
+    // Neither branch has commits of its own, so both count as merged regardless of local edits
+    assert_eq!(listed_branches("merged")?.len(), 2);
+    assert_eq!(listed_branches("dirty")?, vec!["claude-task/edited"]);
+
+    ct(&["remove", "edited"])?;
+    let (stdout, _, success) = ct(&["list", "--only", "dirty"])?;
+    assert!(success);
+    assert!(stdout.contains("No worktrees match the 'dirty' filter"));
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_create_releases_directory_when_git_cannot_run() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_claude-task"))
+        .args([
+            "--config-path",
+            config_path.to_str().unwrap(),
+            "worktree",
+            "create",
+            "no-git",
+        ])
+        .current_dir(&repo_dir)
+        .env("HOME", temp_dir.path())
+        .env("PATH", temp_dir.path().join("empty"))
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!output.status.success());
+    assert!(
+        stderr.contains("Failed to execute git worktree command"),
+        "{stderr}"
+    );
+
+    let worktrees_dir = temp_dir.path().join("worktrees");
+    let leftovers: Vec<_> = std::fs::read_dir(&worktrees_dir)?.collect();
+    assert!(leftovers.is_empty(), "reserved directory left behind");
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_worktree_create_runs_post_create_hook() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+
+    let hook_path = temp_dir.path().join("hook.sh");
+    std::fs::write(
+        &hook_path,
+        "#!/bin/sh\necho \"$CLAUDE_TASK_ID $CLAUDE_TASK_BRANCH\" > \"$1/hook.txt\"\nexit 3\n",
+    )?;
+    std::fs::set_permissions(&hook_path, std::fs::Permissions::from_mode(0o755))?;
+
+    let mut config: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&config_path)?)?;
+    config["worktree"]["postCreateHook"] = hook_path.to_string_lossy().into();
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let (stdout, stderr, success) = run_claude_task(
+        &repo_dir,
+        temp_dir.path(),
+        &["--config-path", config_arg, "worktree", "create", "hooked"],
+    )?;
+    // A failing hook warns but keeps the worktree
+    assert!(success, "worktree create failed: {stderr}");
+    assert!(stdout.contains("Post-create hook failed"));
+
+    let worktree_dir = std::fs::read_dir(temp_dir.path().join("worktrees"))?
+        .next()
+        .expect("worktree directory should exist")?
+        .path();
+    assert_eq!(
+        std::fs::read_to_string(worktree_dir.join("hook.txt"))?,
+        "hooked claude-task/hooked\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_worktree_clean_skips_locked_worktrees() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, config_path) = setup_repo(&temp_dir)?;
+    let config_arg = config_path.to_str().unwrap();
+    let ct = |args: &[&str]| {
+        let mut full_args = vec!["--config-path", config_arg, "worktree"];
+        full_args.extend_from_slice(args);
+        run_claude_task(&repo_dir, temp_dir.path(), &full_args)
+    };
+
+    ct(&["create", "keep"])?;
+    let (_, stderr, success) = ct(&["lock", "keep", "--reason", "still reviewing"])?;
+    assert!(success, "worktree lock failed: {stderr}");
+
+    let (stdout, _, success) = ct(&["clean", "--yes"])?;
+    assert!(success);
+    assert!(stdout.contains("Skipping locked worktree"));
+    assert!(stdout.contains("still reviewing"));
+
+    let (stdout, _, _) = ct(&["list", "--json"])?;
+    assert!(stdout.contains("claude-task/keep"));
+
+    // Explicit removal explains how to unlock
+    let (_, stderr, success) = ct(&["remove", "keep"])?;
+    assert!(!success);
+    assert!(stderr.contains("claude-task worktree unlock keep"));
+
+    let (_, stderr, success) = ct(&["unlock", "keep"])?;
+    assert!(success, "worktree unlock failed: {stderr}");
+    let (stdout, _, success) = ct(&["clean", "--yes"])?;
+    assert!(success);
+    assert!(!stdout.contains("Skipping locked worktree"));
+
+    let (stdout, _, _) = ct(&["list", "--json"])?;
+    assert!(!stdout.contains("claude-task/keep"));
+
+    Ok(())
+}
+
+#[test]
+fn test_count_commits_since_head_commit() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let (repo_dir, _) = setup_repo(&temp_dir)?;
+
+    let base = claude_task::worktree::head_commit(&repo_dir)?;
+    assert_eq!(
+        claude_task::worktree::count_commits_since(&repo_dir, &base)?,
+        0
+    );
+
+    std::fs::write(repo_dir.join("CHANGES.md"), "one\n")?;
+    git(&repo_dir, &["add", "CHANGES.md"]);
+    git(&repo_dir, &["commit", "-q", "-m", "first change"]);
+    std::fs::write(repo_dir.join("CHANGES.md"), "two\n")?;
+    git(&repo_dir, &["commit", "-q", "-am", "second change"]);
+
+    assert_eq!(
+        claude_task::worktree::count_commits_since(&repo_dir, &base)?,
+        2
+    );
+    assert_ne!(claude_task::worktree::head_commit(&repo_dir)?, base);
+
+    Ok(())
+}
+
+#[test]
+fn test_resolve_worktree_directory_matches_expand_tilde() -> Result<()> {
+    use claude_task::config::Config;
+    use claude_task::worktree::resolve_worktree_directory;
+
+    let home = dirs::home_dir().unwrap();
+    assert_eq!(resolve_worktree_directory("~")?, home);
+    assert_eq!(
+        resolve_worktree_directory("~/.claude-task/worktrees")?,
+        Config::expand_tilde("~/.claude-task/worktrees")?
+    );
+    assert_eq!(
+        resolve_worktree_directory("/tmp/worktrees")?,
+        PathBuf::from("/tmp/worktrees")
+    );
+
+    Ok(())
+}